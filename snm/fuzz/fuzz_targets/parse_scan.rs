@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use snm::connection::Iw;
+
+fuzz_target!(|data: &str| {
+    for ap in Iw::parse_scan("wlan0", data) {
+        assert!(!ap.essid.is_empty());
+        assert!((1..=100).contains(&ap.quality()));
+        assert!(ap.bssid.is_empty() || ap.bssid.len() == 17);
+    }
+});
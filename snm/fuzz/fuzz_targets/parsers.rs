@@ -0,0 +1,26 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use snm::connection::parsers::{parse, Parsers};
+use snm::connection::Iw;
+
+fuzz_target!(|data: &str| {
+    for parser in [
+        Parsers::WpaState,
+        Parsers::NetworkQuality,
+        Parsers::NetworkEnc,
+        Parsers::NetworkEssid,
+        Parsers::Bssid,
+        Parsers::Frequency,
+        Parsers::LastSeen,
+        Parsers::ConnectedTo,
+        Parsers::TxBitrate,
+        Parsers::RxBitrate,
+        Parsers::ConnectedTime,
+    ] {
+        if let Some(caps) = parse(parser, data) {
+            let _ = caps[1].parse::<i32>();
+        }
+    }
+    let _ = Iw::parse_link(data);
+    let _ = Iw::parse_station(data);
+});
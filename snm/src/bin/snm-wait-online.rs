@@ -0,0 +1,157 @@
+use rustbus::connection::{Error, Timeout};
+use snm::dbus::{Bus, CallError, Proxy};
+use std::{env, process, time};
+
+const DEFAULT_TIMEOUT: u32 = 120;
+const CALL_TIMEOUT_MARGIN: u64 = 5;
+
+const EXIT_ONLINE: i32 = 0;
+const EXIT_TIMEOUT: i32 = 1;
+const EXIT_FAILURE: i32 = 2;
+const EXIT_USAGE: i32 = 64;
+
+enum Criteria {
+    Any,
+    Interface(String),
+    Ipv4,
+    Ipv6,
+    Connectivity,
+}
+
+impl Criteria {
+    fn code(&self) -> u32 {
+        match self {
+            Criteria::Any => 0,
+            Criteria::Interface(_) => 1,
+            Criteria::Ipv4 => 2,
+            Criteria::Ipv6 => 3,
+            Criteria::Connectivity => 4,
+        }
+    }
+
+    fn interface(&self) -> &str {
+        match self {
+            Criteria::Interface(name) => name,
+            _ => "",
+        }
+    }
+}
+
+struct Options {
+    timeout: u32,
+    criteria: Criteria,
+    quiet: bool,
+}
+
+fn help_text() -> String {
+    format!(
+        "Usage: snm-wait-online [OPTIONS]\n\n\
+         Block until snm reports the system as online.\n\n\
+         Options:\n  \
+         -t, --timeout SECS      give up after SECS seconds (default {}, 0 to only check)\n  \
+         -i, --interface IFACE   wait for IFACE to be the connected interface\n  \
+         -4, --ipv4              wait for an IPv4 address\n  \
+         -6, --ipv6              wait for a global IPv6 address\n  \
+         -c, --connectivity      wait for verified internet connectivity\n  \
+         -q, --quiet             do not print anything\n  \
+         -h, --help              show this help\n\n\
+         Exit status: {} online, {} timed out, {} daemon unavailable, {} bad usage",
+        DEFAULT_TIMEOUT, EXIT_ONLINE, EXIT_TIMEOUT, EXIT_FAILURE, EXIT_USAGE
+    )
+}
+
+fn usage() -> ! {
+    eprintln!("{}", help_text());
+    process::exit(EXIT_USAGE);
+}
+
+fn help() -> ! {
+    println!("{}", help_text());
+    process::exit(EXIT_ONLINE);
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        timeout: DEFAULT_TIMEOUT,
+        criteria: Criteria::Any,
+        quiet: false,
+    };
+    let mut criteria_set = false;
+    let mut set_criteria = |options: &mut Options, criteria: Criteria| {
+        if criteria_set {
+            eprintln!("only one wait criteria can be specified");
+            usage();
+        }
+        criteria_set = true;
+        options.criteria = criteria;
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-t" | "--timeout" => {
+                options.timeout = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_else(|| usage());
+            }
+            "-i" | "--interface" => {
+                let name = args.next().unwrap_or_else(|| usage());
+                set_criteria(&mut options, Criteria::Interface(name));
+            }
+            "-4" | "--ipv4" => set_criteria(&mut options, Criteria::Ipv4),
+            "-6" | "--ipv6" => set_criteria(&mut options, Criteria::Ipv6),
+            "-c" | "--connectivity" => set_criteria(&mut options, Criteria::Connectivity),
+            "-q" | "--quiet" => options.quiet = true,
+            "-h" | "--help" => help(),
+            _ => usage(),
+        }
+    }
+    options
+}
+
+fn wait_online(options: &Options) -> Result<bool, String> {
+    let mut proxy =
+        Proxy::new(Bus::System, "com.github.okeri.snm", "/").map_err(|e| e.to_string())?;
+    let mut call = proxy.method("wait_online");
+    call.body
+        .push_param3(
+            options.timeout,
+            options.criteria.code(),
+            options.criteria.interface(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let timeout = time::Duration::from_secs(options.timeout as u64 + CALL_TIMEOUT_MARGIN);
+    let response = proxy
+        .call(call, Timeout::Duration(timeout))
+        .map_err(|e| match e {
+            CallError::Bus(Error::TimedOut) => "daemon did not answer in time".to_owned(),
+            e => e.to_string(),
+        })?;
+    response
+        .body
+        .parser()
+        .get::<bool>()
+        .map_err(|e| e.to_string())
+}
+
+fn main() {
+    let options = parse_args();
+    let code = match wait_online(&options) {
+        Ok(true) => EXIT_ONLINE,
+        Ok(false) => {
+            if !options.quiet {
+                eprintln!("timed out waiting for network");
+            }
+            EXIT_TIMEOUT
+        }
+        Err(e) => {
+            if !options.quiet {
+                eprintln!("cannot query snm: {}", e);
+            }
+            EXIT_FAILURE
+        }
+    };
+    process::exit(code);
+}
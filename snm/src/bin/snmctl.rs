@@ -0,0 +1,1192 @@
+use rustbus::{connection::Timeout, message_builder::MarshalledMessage};
+use snm::connection::{
+    AccessPoint, Band, BandPolicy, ConnectionInfo, ConnectionSetting, ConnectionStatus,
+    Connectivity, DataUsage, HotspotClient, HotspotSetting, KnownNetwork, LinkStats, MacPolicy,
+    MeteredState, NetworkInfo, NetworkList, RoamEvent, Security, Ssid, Statistics, StatusChange,
+    WiredAuth, WiredProfile, DUPLEX_FULL, DUPLEX_HALF, IP_METHOD_DHCP, IP_METHOD_STATIC, MIN_MTU,
+};
+use snm::convert::{convert, Convert};
+use snm::dbus::{Bus, Proxy};
+use snm::error::{Error, Reason};
+use snm::json::{ToJson, Value};
+use std::{env, process, time};
+
+const SCAN_TIMEOUT: u64 = 30;
+const CONNECT_TIMEOUT: u64 = 90;
+const DEFAULT_THRESHOLD: i32 = -65;
+const MEGABYTE: u64 = 1024 * 1024;
+
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 64;
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: snmctl [--json] COMMAND\n\n\
+         Commands:\n  \
+         status                      show connection state and connectivity\n  \
+         scan                        scan for networks and list them\n  \
+         list                        list networks found by the last scan\n  \
+         aps SSID                    list access points of SSID found by the last scan\n  \
+         link                        show signal and bitrates of the wifi link\n  \
+         stats [IFACE]               show traffic counters of IFACE or the connection\n  \
+         usage SSID [--reset]        show or reset data used on SSID\n  \
+         connect SSID [OPTIONS]      connect to a wifi network\n      \
+         --password PASS           remember PASS for SSID before connecting\n      \
+         --wait                    wait until connected or failed\n  \
+         connect --ethernet [--wait] connect to the wired network\n  \
+         cancel OP                   cancel connect operation OP\n  \
+         disconnect                  disconnect and stop auto connecting\n  \
+         profile show SSID           show the stored profile of SSID\n  \
+         profile set SSID [OPTIONS]  create or update the profile of SSID\n      \
+         --password PASS | --open\n      \
+         --auto | --no-auto\n      \
+         --threshold DBM | --no-roaming\n      \
+         --bssid MAC | --no-bssid  only connect through access point MAC\n      \
+         --band 2.4|5|6 | --any-band\n      \
+         --band-only | --prefer-band\n      \
+         --frequencies MHZ,... | --any-frequency\n      \
+         --metered | --unmetered | --metered-auto\n      \
+         --data-cap MB | --no-data-cap  monthly limit of data used\n      \
+         --mac-policy permanent|random|stable  address used on the network\n      \
+         --mtu BYTES | --default-mtu\n      \
+         --dhcp-mtu | --no-dhcp-mtu  prefer the mtu offered by the dhcp server\n  \
+         profile delete SSID         forget SSID\n  \
+         wired show [PORT]           show 802.1X credentials of PORT, the wired port by default\n  \
+         wired set [PORT] [OPTIONS]  store 802.1X credentials for PORT, an interface or MAC\n      \
+         --eap METHODS --identity NAME --password PASS\n      \
+         --anonymous-identity NAME --phase2 AUTH --ca-cert FILE\n      \
+         --client-cert FILE --private-key FILE --private-key-password PASS\n  \
+         wired delete [PORT]         forget the 802.1X credentials of PORT\n  \
+         wired-profile list          list wired profiles and the one in use\n  \
+         wired-profile show NAME     show the wired profile NAME\n  \
+         wired-profile set NAME [OPTIONS]  create or update the wired profile NAME\n      \
+         --interface IFACE | --any-interface\n      \
+         --mac MAC | --any-mac\n      \
+         --gateway-mac MAC | --any-gateway-mac\n      \
+         --auto | --no-auto\n      \
+         --dhcp | --static CIDR\n      \
+         --gateway IP | --no-gateway\n      \
+         --dns IP,... | --lease-dns\n      \
+         --mtu BYTES | --default-mtu\n      \
+         --dhcp-mtu | --no-dhcp-mtu  prefer the mtu offered by the dhcp server\n      \
+         --speed MBPS | --any-speed\n      \
+         --duplex half|full | --any-duplex\n      \
+         --autoneg | --no-autoneg | --default-autoneg\n      \
+         --wol | --no-wol | --default-wol  wake-on-LAN by magic packet\n  \
+         wired-profile delete NAME   forget the wired profile NAME\n  \
+         hotspot start SSID [OPTIONS]  share the wired connection over wifi\n      \
+         --password PASS           8 to 63 characters, open without it\n      \
+         --band 2.4|5\n  \
+         hotspot stop                stop the hotspot and go back to the previous network\n  \
+         hotspot status              show the hotspot and its clients\n  \
+         monitor                     print daemon signals as they arrive"
+    );
+    process::exit(EXIT_USAGE);
+}
+
+struct Client {
+    proxy: Proxy,
+    json: bool,
+}
+
+impl Client {
+    fn new(json: bool) -> Result<Self, String> {
+        let proxy =
+            Proxy::new(Bus::System, "com.github.okeri.snm", "/").map_err(|e| e.to_string())?;
+        Ok(Client { proxy, json })
+    }
+
+    fn call_with<F>(&mut self, member: &str, push: F) -> Result<MarshalledMessage, String>
+    where
+        F: FnOnce(&mut MarshalledMessage) -> Result<(), rustbus::wire::errors::MarshalError>,
+    {
+        let mut call = self.proxy.method(member);
+        push(&mut call).map_err(|e| e.to_string())?;
+        self.proxy
+            .call(call, Timeout::Infinite)
+            .map_err(|e| e.to_string())
+    }
+
+    fn call(&mut self, member: &str) -> Result<MarshalledMessage, String> {
+        self.call_with(member, |_| Ok(()))
+    }
+
+    fn get<T: Convert>(&mut self, member: &str) -> Result<T, String> {
+        let reply = self.call(member)?;
+        convert(reply).map_err(|_| format!("unexpected reply to {}", member))
+    }
+
+    fn get_props(&mut self, essid: &str) -> Result<KnownNetwork, String> {
+        let reply = self.call_with("get_props", |call| call.body.push_param(essid))?;
+        convert(reply).map_err(|_| "unexpected reply to get_props".to_owned())
+    }
+
+    fn set_props(&mut self, essid: &str, props: &KnownNetwork) -> Result<(), String> {
+        self.call_with("set_props", |call| {
+            call.body.push_param(essid)?;
+            call.body
+                .push_param(props.password.clone().unwrap_or_default())?;
+            call.body
+                .push_param(props.threshold.unwrap_or(DEFAULT_THRESHOLD))?;
+            call.body.push_param(props.auto)?;
+            call.body.push_param(props.password.is_some())?;
+            call.body.push_param(props.threshold.is_some())
+        })?;
+        Ok(())
+    }
+
+    fn next_signal(&mut self, deadline: Option<time::Instant>) -> Result<Signal, String> {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let now = time::Instant::now();
+                if now >= deadline {
+                    return Err("timed out".to_owned());
+                }
+                Timeout::Duration(deadline - now)
+            }
+            None => Timeout::Infinite,
+        };
+        let msg = self.proxy.next_signal(timeout).map_err(|e| match e {
+            rustbus::connection::Error::TimedOut => "timed out".to_owned(),
+            e => e.to_string(),
+        })?;
+        Ok(Signal::from_message(msg))
+    }
+
+    fn print<T: ToJson>(&self, value: &T, text: impl FnOnce(&T) -> String) {
+        if self.json {
+            println!("{}", value.to_json());
+        } else {
+            println!("{}", text(value));
+        }
+    }
+}
+
+enum Signal {
+    StateChanged(ConnectionInfo),
+    NetworkList(NetworkList),
+    ConnectStatusChanged(StatusChange),
+    ConnectivityChanged(Connectivity),
+    ConnectFinished(u32, Reason),
+    Roamed(RoamEvent),
+    LinkStats(String, LinkStats),
+    Statistics(String, Statistics),
+    MeteredChanged(MeteredState),
+    Other(String),
+}
+
+impl Signal {
+    fn from_message(msg: MarshalledMessage) -> Signal {
+        let member = msg.dynheader.member.clone().unwrap_or_default();
+        let signal = match member.as_str() {
+            "state_changed" => convert(msg).map(Signal::StateChanged),
+            "network_list" => convert(msg).map(Signal::NetworkList),
+            "connect_status_changed" => convert(msg).map(Signal::ConnectStatusChanged),
+            "connectivity_changed" => convert(msg).map(Signal::ConnectivityChanged),
+            "connect_finished" => convert::<(u32, Reason)>(msg)
+                .map(|(op, reason)| Signal::ConnectFinished(op, reason)),
+            "roamed" => convert(msg).map(Signal::Roamed),
+            "link_stats" => convert::<(String, LinkStats)>(msg)
+                .map(|(iface, stats)| Signal::LinkStats(iface, stats)),
+            "statistics" => convert::<(String, Statistics)>(msg)
+                .map(|(iface, stats)| Signal::Statistics(iface, stats)),
+            "metered_changed" => convert(msg).map(Signal::MeteredChanged),
+            _ => Err(Error::InvalidArgs),
+        };
+        signal.unwrap_or(Signal::Other(member))
+    }
+
+    fn print(&self, json: bool) {
+        let (name, value, text) = match self {
+            Signal::StateChanged(info) => ("state_changed", info.to_json(), describe_state(info)),
+            Signal::NetworkList(networks) => (
+                "network_list",
+                networks.to_json(),
+                format!("{} networks", networks.len()),
+            ),
+            Signal::ConnectStatusChanged(change) => (
+                "connect_status_changed",
+                change.to_json(),
+                describe_status(change),
+            ),
+            Signal::ConnectivityChanged(connectivity) => (
+                "connectivity_changed",
+                connectivity.to_json(),
+                describe_connectivity(connectivity).to_owned(),
+            ),
+            Signal::ConnectFinished(op, reason) => (
+                "connect_finished",
+                Value::Object(vec![("op", (*op).into()), ("result", reason.name().into())]),
+                if *reason == Reason::None {
+                    format!("operation {} succeeded", op)
+                } else {
+                    format!("operation {} failed ({})", op, reason.name())
+                },
+            ),
+            Signal::Roamed(event) => (
+                "roamed",
+                event.to_json(),
+                format!(
+                    "{} roamed on {} from {} to {}",
+                    event.iface, event.essid, event.from, event.to
+                ),
+            ),
+            Signal::LinkStats(iface, stats) => (
+                "link_stats",
+                link_json(iface, stats),
+                describe_link(iface, stats),
+            ),
+            Signal::Statistics(iface, stats) => (
+                "statistics",
+                statistics_json(iface, stats),
+                describe_statistics(iface, stats),
+            ),
+            Signal::MeteredChanged(state) => {
+                ("metered_changed", state.to_json(), describe_metered(state))
+            }
+            Signal::Other(name) => (name.as_str(), Value::Null, String::new()),
+        };
+        if json {
+            println!(
+                "{}",
+                Value::Object(vec![("signal", name.into()), ("value", value)])
+            );
+        } else {
+            println!("{}: {}", name, text);
+        }
+    }
+}
+
+fn describe_state(info: &ConnectionInfo) -> String {
+    match info {
+        ConnectionInfo::NotConnected => "disconnected".to_owned(),
+        ConnectionInfo::Ethernet(ip) => format!("ethernet, ip {}", ip),
+        ConnectionInfo::Wifi(essid, quality, _, ip, band) => match band.name() {
+            Some(band) => format!(
+                "wifi {} ({}%, {} GHz), ip {}",
+                essid.key(),
+                quality,
+                band,
+                ip
+            ),
+            None => format!("wifi {} ({}%), ip {}", essid.key(), quality, ip),
+        },
+        ConnectionInfo::ConnectingEth => "connecting to ethernet".to_owned(),
+        ConnectionInfo::ConnectingWifi(essid) => format!("connecting to wifi {}", essid.key()),
+    }
+}
+
+fn describe_status(change: &StatusChange) -> String {
+    let text = match change.status {
+        ConnectionStatus::Initializing => "bringing interface up",
+        ConnectionStatus::Connecting => "connecting",
+        ConnectionStatus::Authenticating => "authenticating",
+        ConnectionStatus::GettingIP => "getting ip address",
+        ConnectionStatus::AuthFail => "authorization failed",
+        ConnectionStatus::Aborted => "connection canceled",
+        ConnectionStatus::ConnectFail => "connection failed",
+    };
+    if change.reason == Reason::None {
+        text.to_owned()
+    } else {
+        format!("{} ({})", text, change.reason.name())
+    }
+}
+
+fn describe_connectivity(connectivity: &Connectivity) -> &'static str {
+    match connectivity {
+        Connectivity::Unknown => "unknown",
+        Connectivity::None => "none",
+        Connectivity::Portal => "captive portal",
+        Connectivity::Limited => "limited",
+        Connectivity::Full => "full",
+    }
+}
+
+fn describe_networks(networks: &NetworkList) -> String {
+    networks
+        .iter()
+        .map(|network| match network {
+            NetworkInfo::Ethernet => "ethernet".to_owned(),
+            NetworkInfo::Wifi(essid, quality, enc) => {
+                let enc = if *enc { "*" } else { " " };
+                format!("{:>3}% {} {}", quality, enc, essid.key())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn describe_props(props: &KnownNetwork) -> String {
+    format!(
+        "auto connect: {}\npassword: {}\nroaming threshold: {}\nbssid: {}\nband: {}\nfrequencies: {}\nmetered: {}\ndata cap: {}\nmac address: {}\nmtu: {}",
+        if props.auto { "yes" } else { "no" },
+        if props.password.is_some() {
+            "set"
+        } else {
+            "none"
+        },
+        props
+            .threshold
+            .map_or("disabled".to_owned(), |t| format!("{} dBm", t)),
+        props.bssid.as_deref().unwrap_or("any"),
+        match props.band {
+            Some(ref band) if props.band_only => format!("{} GHz only", band),
+            Some(ref band) => format!("{} GHz preferred", band),
+            None => "any".to_owned(),
+        },
+        if props.frequencies.is_empty() {
+            "any".to_owned()
+        } else {
+            props
+                .frequencies
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        },
+        match props.metered {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "as announced by dhcp",
+        },
+        props
+            .data_cap
+            .map_or("none".to_owned(), |cap| format!("{} MB per month", cap / MEGABYTE)),
+        props.mac_policy().name(),
+        describe_mtu(props.mtu, props.dhcp_mtu),
+    )
+}
+
+fn describe_mtu(mtu: Option<u32>, dhcp_mtu: bool) -> String {
+    let mtu = mtu.map_or_else(|| "default".to_owned(), |mtu| mtu.to_string());
+    if dhcp_mtu {
+        format!("{}, or as offered by dhcp", mtu)
+    } else {
+        mtu
+    }
+}
+
+fn describe_metered(state: &MeteredState) -> String {
+    match (state.metered, state.over_cap) {
+        (_, true) => "data cap reached".to_owned(),
+        (true, false) => "metered".to_owned(),
+        (false, false) => "not metered".to_owned(),
+    }
+}
+
+fn describe_access_points(access_points: &[AccessPoint]) -> String {
+    access_points
+        .iter()
+        .map(|ap| {
+            let band = ap.band().name().unwrap_or("?");
+            let security = match ap.security {
+                Security::Open => "open",
+                Security::Wep => "wep",
+                Security::Wpa => "wpa",
+                Security::Wpa2 => "wpa2",
+                Security::Wpa3 => "wpa3",
+            };
+            format!(
+                "{} {:>4} dBm {:>3} GHz ch {:<3} {}",
+                ap.bssid,
+                ap.signal,
+                band,
+                ap.channel(),
+                security
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn link_json(iface: &str, stats: &LinkStats) -> Value {
+    Value::Object(vec![
+        ("interface", iface.into()),
+        ("stats", stats.to_json()),
+    ])
+}
+
+fn describe_link(iface: &str, stats: &LinkStats) -> String {
+    format!(
+        "{}: signal {} dBm ({}%), tx {:.1} MBit/s, rx {:.1} MBit/s, connected {}s",
+        iface,
+        stats.signal,
+        stats.quality(),
+        f64::from(stats.tx_bitrate) / 1000.0,
+        f64::from(stats.rx_bitrate) / 1000.0,
+        stats.connected_time
+    )
+}
+
+fn link(client: &mut Client) -> Result<(), String> {
+    let (iface, stats) = client.get::<(String, LinkStats)>("get_link_stats")?;
+    if client.json {
+        println!("{}", link_json(&iface, &stats));
+    } else {
+        println!("{}", describe_link(&iface, &stats));
+    }
+    Ok(())
+}
+
+fn statistics_json(iface: &str, stats: &Statistics) -> Value {
+    Value::Object(vec![
+        ("interface", iface.into()),
+        ("statistics", stats.to_json()),
+    ])
+}
+
+fn describe_statistics(iface: &str, stats: &Statistics) -> String {
+    format!(
+        "{}: rx {} bytes ({} B/s), tx {} bytes ({} B/s), \
+         {} errors, {} dropped",
+        iface,
+        stats.rx_bytes,
+        stats.rx_rate,
+        stats.tx_bytes,
+        stats.tx_rate,
+        stats.rx_errors + stats.tx_errors,
+        stats.rx_dropped + stats.tx_dropped
+    )
+}
+
+fn statistics(client: &mut Client, mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let iface = args.next().unwrap_or_default();
+    let reply = client.call_with("get_statistics", |call| {
+        call.body.push_param(iface.as_str())
+    })?;
+    let (iface, stats) = convert::<(String, Statistics)>(reply)
+        .map_err(|_| "unexpected reply to get_statistics".to_owned())?;
+    if client.json {
+        println!("{}", statistics_json(&iface, &stats));
+    } else {
+        println!("{}", describe_statistics(&iface, &stats));
+    }
+    Ok(())
+}
+
+fn data_usage(client: &mut Client, mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let essid = args.next().unwrap_or_else(|| usage());
+    let key = Ssid::from_key(&essid).key();
+    match args.next().as_deref() {
+        None => {
+            let reply = client.call_with("get_usage", |call| call.body.push_param(key.as_str()))?;
+            let data = convert::<DataUsage>(reply)
+                .map_err(|_| "unexpected reply to get_usage".to_owned())?;
+            client.print(&data, |data| {
+                format!(
+                    "rx {} bytes, tx {} bytes\nthis month: rx {} bytes, tx {} bytes",
+                    data.rx, data.tx, data.month_rx, data.month_tx
+                )
+            });
+        }
+        Some("--reset") => {
+            client.call_with("reset_usage", |call| call.body.push_param(key.as_str()))?;
+        }
+        Some(_) => usage(),
+    }
+    Ok(())
+}
+
+fn status(client: &mut Client) -> Result<(), String> {
+    let (state, mac) = client.get::<(ConnectionInfo, String)>("get_state")?;
+    let connectivity = client.get::<Connectivity>("get_connectivity")?;
+    let metered = client.get::<MeteredState>("get_metered")?;
+    if client.json {
+        println!(
+            "{}",
+            Value::Object(vec![
+                ("state", state.to_json()),
+                ("mac", Some(mac).filter(|mac| !mac.is_empty()).into()),
+                ("connectivity", connectivity.to_json()),
+                ("metered", metered.to_json()),
+            ])
+        );
+    } else {
+        println!("state: {}", describe_state(&state));
+        if !mac.is_empty() {
+            println!("mac address: {}", mac);
+        }
+        println!("connectivity: {}", describe_connectivity(&connectivity));
+        println!("metered: {}", describe_metered(&metered));
+    }
+    Ok(())
+}
+
+fn scan(client: &mut Client) -> Result<(), String> {
+    client.proxy.subscribe().map_err(|e| e.to_string())?;
+    client.call("scan")?;
+    let deadline = time::Instant::now() + time::Duration::from_secs(SCAN_TIMEOUT);
+    loop {
+        if let Signal::NetworkList(networks) = client.next_signal(Some(deadline))? {
+            client.print(&networks, describe_networks);
+            return Ok(());
+        }
+    }
+}
+
+fn list(client: &mut Client) -> Result<(), String> {
+    let networks = client.get::<NetworkList>("get_networks")?;
+    client.print(&networks, describe_networks);
+    Ok(())
+}
+
+fn access_points(
+    client: &mut Client,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), String> {
+    let essid = args.next().unwrap_or_else(|| usage());
+    let reply = client.call_with("get_access_points", |call| {
+        call.body.push_param(Ssid::from_key(&essid).key())
+    })?;
+    let access_points = convert::<Vec<AccessPoint>>(reply)
+        .map_err(|_| "unexpected reply to get_access_points".to_owned())?;
+    client.print(&access_points, |aps| describe_access_points(aps));
+    Ok(())
+}
+
+fn connect(client: &mut Client, mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut essid: Option<String> = None;
+    let mut ethernet = false;
+    let mut password: Option<String> = None;
+    let mut wait = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ethernet" => ethernet = true,
+            "--password" => password = Some(args.next().unwrap_or_else(|| usage())),
+            "--wait" => wait = true,
+            _ if essid.is_none() && !arg.starts_with("--") => essid = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let setting = match (essid, ethernet) {
+        (None, true) => ConnectionSetting::Ethernet,
+        (Some(essid), false) => {
+            let essid = Ssid::from_key(&essid);
+            if let Some(password) = password {
+                let mut props = client.get_props(&essid.key())?;
+                props.password = Some(password);
+                props.auto = true;
+                client.set_props(&essid.key(), &props)?;
+            }
+            let networks = client.get::<NetworkList>("get_networks")?;
+            let enc = networks.iter().any(|network| match network {
+                NetworkInfo::Wifi(net_essid, _, enc) => *enc && *net_essid == essid,
+                _ => false,
+            });
+            if enc {
+                ConnectionSetting::Wifi {
+                    essid,
+                    password: String::new(),
+                    threshold: None,
+                    bssid: None,
+                    bands: BandPolicy::default(),
+                    mac_policy: MacPolicy::Permanent,
+                    mtu: None,
+                    dhcp_mtu: false,
+                }
+            } else {
+                ConnectionSetting::OpenWifi {
+                    essid,
+                    threshold: None,
+                    bssid: None,
+                    bands: BandPolicy::default(),
+                    mac_policy: MacPolicy::Permanent,
+                    mtu: None,
+                    dhcp_mtu: false,
+                }
+            }
+        }
+        _ => usage(),
+    };
+
+    if wait {
+        client.proxy.subscribe().map_err(|e| e.to_string())?;
+    }
+    let reply = client.call_with("connect", |call| call.body.push_param(&setting))?;
+    let op = convert::<u32>(reply).map_err(|_| "unexpected reply to connect".to_owned())?;
+    if !wait {
+        if client.json {
+            println!("{}", Value::Object(vec![("op", op.into())]));
+        } else {
+            println!("started operation {}", op);
+        }
+        return Ok(());
+    }
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(CONNECT_TIMEOUT);
+    loop {
+        let signal = client.next_signal(Some(deadline))?;
+        match signal {
+            Signal::ConnectFinished(finished, reason) if finished == op => {
+                signal.print(client.json);
+                if reason == Reason::None {
+                    return Ok(());
+                }
+                return Err("connection failed".to_owned());
+            }
+            Signal::ConnectStatusChanged(ref change) if change.op == op && !client.json => {
+                signal.print(false);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn cancel(client: &mut Client, mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let op = args
+        .next()
+        .and_then(|op| op.parse::<u32>().ok())
+        .unwrap_or_else(|| usage());
+    client.call_with("cancel", |call| call.body.push_param(op))?;
+    Ok(())
+}
+
+fn profile(client: &mut Client, mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let command = args.next().unwrap_or_else(|| usage());
+    let essid = args.next().unwrap_or_else(|| usage());
+    match command.as_str() {
+        "show" => {
+            let props = client.get_props(&essid)?;
+            client.print(&props, describe_props);
+        }
+        "set" => {
+            let mut props = client.get_props(&essid)?;
+            let mut pin: Option<String> = None;
+            let mut bands: Option<BandPolicy> = None;
+            let mut metering: Option<(Option<bool>, Option<u64>)> = None;
+            let mut mac_policy: Option<MacPolicy> = None;
+            let mut mtu: Option<(Option<u32>, bool)> = None;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--password" => {
+                        props.password = Some(args.next().unwrap_or_else(|| usage()));
+                    }
+                    "--open" => props.password = None,
+                    "--auto" => props.auto = true,
+                    "--no-auto" => props.auto = false,
+                    "--threshold" => {
+                        props.threshold = Some(
+                            args.next()
+                                .and_then(|value| value.parse().ok())
+                                .unwrap_or_else(|| usage()),
+                        );
+                    }
+                    "--no-roaming" => props.threshold = None,
+                    "--bssid" => pin = Some(args.next().unwrap_or_else(|| usage())),
+                    "--no-bssid" => pin = Some(String::new()),
+                    "--band" => {
+                        let band = args.next().as_deref().and_then(Band::from_name);
+                        bands.get_or_insert_with(|| props.bands()).band =
+                            Some(band.unwrap_or_else(|| usage()));
+                    }
+                    "--any-band" => bands.get_or_insert_with(|| props.bands()).band = None,
+                    "--band-only" => bands.get_or_insert_with(|| props.bands()).only = true,
+                    "--prefer-band" => bands.get_or_insert_with(|| props.bands()).only = false,
+                    "--frequencies" => {
+                        let frequencies = args
+                            .next()
+                            .and_then(|list| {
+                                list.split(',')
+                                    .map(|frequency| frequency.trim().parse().ok())
+                                    .collect::<Option<Vec<u32>>>()
+                            })
+                            .unwrap_or_else(|| usage());
+                        bands.get_or_insert_with(|| props.bands()).frequencies = frequencies;
+                    }
+                    "--any-frequency" => {
+                        bands.get_or_insert_with(|| props.bands()).frequencies = vec![];
+                    }
+                    "--metered" => {
+                        metering.get_or_insert((props.metered, props.data_cap)).0 = Some(true)
+                    }
+                    "--unmetered" => {
+                        metering.get_or_insert((props.metered, props.data_cap)).0 = Some(false)
+                    }
+                    "--metered-auto" => {
+                        metering.get_or_insert((props.metered, props.data_cap)).0 = None
+                    }
+                    "--data-cap" => {
+                        let megabytes = args
+                            .next()
+                            .and_then(|value| value.parse::<u64>().ok())
+                            .filter(|megabytes| *megabytes > 0)
+                            .unwrap_or_else(|| usage());
+                        metering.get_or_insert((props.metered, props.data_cap)).1 =
+                            Some(megabytes * MEGABYTE);
+                    }
+                    "--mac-policy" => {
+                        let policy = args.next().as_deref().and_then(MacPolicy::from_name);
+                        mac_policy = Some(policy.unwrap_or_else(|| usage()));
+                    }
+                    "--no-data-cap" => {
+                        metering.get_or_insert((props.metered, props.data_cap)).1 = None
+                    }
+                    "--mtu" => {
+                        let bytes = args
+                            .next()
+                            .and_then(|value| value.parse::<u32>().ok())
+                            .filter(|bytes| *bytes >= MIN_MTU)
+                            .unwrap_or_else(|| usage());
+                        mtu.get_or_insert((props.mtu, props.dhcp_mtu)).0 = Some(bytes);
+                    }
+                    "--default-mtu" => mtu.get_or_insert((props.mtu, props.dhcp_mtu)).0 = None,
+                    "--dhcp-mtu" => mtu.get_or_insert((props.mtu, props.dhcp_mtu)).1 = true,
+                    "--no-dhcp-mtu" => mtu.get_or_insert((props.mtu, props.dhcp_mtu)).1 = false,
+                    _ => usage(),
+                }
+            }
+            client.set_props(&essid, &props)?;
+            if let Some(bssid) = pin {
+                client.call_with("pin_bssid", |call| {
+                    call.body.push_param(essid.as_str())?;
+                    call.body.push_param(bssid)
+                })?;
+            }
+            if let Some(bands) = bands {
+                client.call_with("set_bands", |call| {
+                    call.body.push_param(essid.as_str())?;
+                    call.body
+                        .push_param(bands.band.and_then(Band::name).unwrap_or_default())?;
+                    call.body.push_param(bands.only)?;
+                    call.body.push_param(bands.frequencies)
+                })?;
+            }
+            if let Some((mtu, dhcp_mtu)) = mtu {
+                client.call_with("set_mtu", |call| {
+                    call.body.push_param(essid.as_str())?;
+                    call.body.push_param(mtu.unwrap_or_default())?;
+                    call.body.push_param(dhcp_mtu)
+                })?;
+            }
+            if let Some(policy) = mac_policy {
+                client.call_with("set_mac_policy", |call| {
+                    call.body.push_param(essid.as_str())?;
+                    call.body.push_param(policy.name())
+                })?;
+            }
+            if let Some((metered, data_cap)) = metering {
+                client.call_with("set_metered", |call| {
+                    call.body.push_param(essid.as_str())?;
+                    call.body.push_param(match metered {
+                        None => 0u32,
+                        Some(true) => 1,
+                        Some(false) => 2,
+                    })?;
+                    call.body.push_param(data_cap.unwrap_or_default())
+                })?;
+            }
+        }
+        "delete" => {
+            client.set_props(&essid, &KnownNetwork::default())?;
+        }
+        _ => usage(),
+    }
+    Ok(())
+}
+
+fn describe_wired(port: &str, auth: &Option<WiredAuth>) -> String {
+    let auth = match auth {
+        Some(auth) => auth,
+        None => return format!("port: {}\n802.1X: off", port),
+    };
+    let text = |value: &Option<String>| value.clone().unwrap_or_else(|| "none".to_owned());
+    let secret = |value: &Option<String>| if value.is_some() { "set" } else { "none" };
+    format!(
+        "port: {}\neap: {}\nidentity: {}\nanonymous identity: {}\npassword: {}\nphase2: {}\n\
+         ca cert: {}\nclient cert: {}\nprivate key: {}\nprivate key password: {}",
+        port,
+        auth.eap,
+        auth.identity,
+        text(&auth.anonymous_identity),
+        secret(&auth.password),
+        text(&auth.phase2),
+        text(&auth.ca_cert),
+        text(&auth.client_cert),
+        text(&auth.private_key),
+        secret(&auth.private_key_password),
+    )
+}
+
+fn wired(client: &mut Client, args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut args = args.peekable();
+    let command = args.next().unwrap_or_else(|| usage());
+    let port = match args.peek() {
+        Some(arg) if !arg.starts_with("--") => args.next().unwrap_or_default(),
+        _ => String::new(),
+    };
+    let reply = client.call_with("get_wired_auth", |call| call.body.push_param(port.as_str()))?;
+    let (port, current) = convert::<(String, Option<WiredAuth>)>(reply)
+        .map_err(|_| "unexpected reply to get_wired_auth".to_owned())?;
+    let auth = match command.as_str() {
+        "show" => {
+            if client.json {
+                println!(
+                    "{}",
+                    Value::Object(vec![
+                        ("port", port.as_str().into()),
+                        (
+                            "auth",
+                            current.as_ref().map_or(Value::Null, ToJson::to_json)
+                        ),
+                    ])
+                );
+            } else {
+                println!("{}", describe_wired(&port, &current));
+            }
+            return Ok(());
+        }
+        "set" => {
+            let mut auth = current.unwrap_or_default();
+            while let Some(arg) = args.next() {
+                let value = args.next().unwrap_or_else(|| usage());
+                let optional = Some(value.clone()).filter(|value| !value.is_empty());
+                match arg.as_str() {
+                    "--eap" => auth.eap = value,
+                    "--identity" => auth.identity = value,
+                    "--password" => auth.password = optional,
+                    "--anonymous-identity" => auth.anonymous_identity = optional,
+                    "--phase2" => auth.phase2 = optional,
+                    "--ca-cert" => auth.ca_cert = optional,
+                    "--client-cert" => auth.client_cert = optional,
+                    "--private-key" => auth.private_key = optional,
+                    "--private-key-password" => auth.private_key_password = optional,
+                    _ => usage(),
+                }
+            }
+            if !auth.valid() {
+                return Err("an eap method like PEAP, TTLS or TLS is required".to_owned());
+            }
+            auth
+        }
+        "delete" => WiredAuth::default(),
+        _ => usage(),
+    };
+    client.call_with("set_wired_auth", |call| {
+        call.body.push_param(port.as_str())?;
+        call.body.push_param(&auth)
+    })?;
+    Ok(())
+}
+
+fn describe_wired_profile(name: &str, profile: &WiredProfile) -> String {
+    let text = |value: &Option<String>| value.clone().unwrap_or_else(|| "any".to_owned());
+    let address = if profile.is_static() {
+        format!(
+            "static {} via {}",
+            profile.address.as_deref().unwrap_or_default(),
+            profile.gateway.as_deref().unwrap_or("none"),
+        )
+    } else {
+        profile.method.clone()
+    };
+    let dns = if profile.dns.is_empty() {
+        "from lease".to_owned()
+    } else {
+        profile.dns.join(", ")
+    };
+    let switch = |value: Option<bool>| match value {
+        Some(true) => "on",
+        Some(false) => "off",
+        None => "unchanged",
+    };
+    format!(
+        "profile: {}\nauto: {}\ninterface: {}\nmac: {}\ngateway mac: {}\naddress: {}\n\
+         dns: {}\nmtu: {}\nspeed: {}\nduplex: {}\nautoneg: {}\nwake-on-lan: {}",
+        name,
+        profile.auto,
+        text(&profile.interface),
+        text(&profile.mac),
+        text(&profile.gateway_mac),
+        address,
+        dns,
+        describe_mtu(profile.mtu, profile.dhcp_mtu),
+        profile
+            .speed
+            .map_or_else(|| "any".to_owned(), |speed| format!("{} Mb/s", speed)),
+        text(&profile.duplex),
+        switch(profile.autoneg),
+        switch(profile.wake_on_lan),
+    )
+}
+
+fn wired_profile(
+    client: &mut Client,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), String> {
+    let command = args.next().unwrap_or_else(|| usage());
+    if command == "list" {
+        let reply = client.call("get_wired_profiles")?;
+        let (names, active) = convert::<(Vec<String>, String)>(reply)
+            .map_err(|_| "unexpected reply to get_wired_profiles".to_owned())?;
+        if client.json {
+            println!(
+                "{}",
+                Value::Object(vec![
+                    (
+                        "profiles",
+                        Value::Array(names.iter().map(|name| name.as_str().into()).collect()),
+                    ),
+                    (
+                        "active",
+                        Some(active).filter(|name| !name.is_empty()).into()
+                    ),
+                ])
+            );
+        } else {
+            for name in names {
+                let marker = if name == active { "*" } else { " " };
+                println!("{} {}", marker, name);
+            }
+        }
+        return Ok(());
+    }
+    let name = args.next().unwrap_or_else(|| usage());
+    match command.as_str() {
+        "show" | "set" => {
+            let reply = client.call_with("get_wired_profile", |call| {
+                call.body.push_param(name.as_str())
+            })?;
+            let mut profile = convert::<WiredProfile>(reply)
+                .map_err(|_| "unexpected reply to get_wired_profile".to_owned())?;
+            if command == "show" {
+                if client.json {
+                    println!("{}", profile.to_json());
+                } else {
+                    println!("{}", describe_wired_profile(&name, &profile));
+                }
+                return Ok(());
+            }
+            let link = profile.link();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--interface" => {
+                        profile.interface = Some(args.next().unwrap_or_else(|| usage()))
+                    }
+                    "--any-interface" => profile.interface = None,
+                    "--mac" => {
+                        profile.mac =
+                            Some(args.next().unwrap_or_else(|| usage()).to_ascii_lowercase())
+                    }
+                    "--any-mac" => profile.mac = None,
+                    "--gateway-mac" => {
+                        profile.gateway_mac =
+                            Some(args.next().unwrap_or_else(|| usage()).to_ascii_lowercase())
+                    }
+                    "--any-gateway-mac" => profile.gateway_mac = None,
+                    "--auto" => profile.auto = true,
+                    "--no-auto" => profile.auto = false,
+                    "--dhcp" => {
+                        profile.method = IP_METHOD_DHCP.to_owned();
+                        profile.address = None;
+                    }
+                    "--static" => {
+                        profile.method = IP_METHOD_STATIC.to_owned();
+                        profile.address = Some(args.next().unwrap_or_else(|| usage()));
+                    }
+                    "--gateway" => profile.gateway = Some(args.next().unwrap_or_else(|| usage())),
+                    "--no-gateway" => profile.gateway = None,
+                    "--dns" => {
+                        profile.dns = args
+                            .next()
+                            .unwrap_or_else(|| usage())
+                            .split(',')
+                            .map(|server| server.trim().to_owned())
+                            .filter(|server| !server.is_empty())
+                            .collect();
+                    }
+                    "--lease-dns" => profile.dns.clear(),
+                    "--mtu" => {
+                        profile.mtu = Some(
+                            args.next()
+                                .and_then(|value| value.parse().ok())
+                                .unwrap_or_else(|| usage()),
+                        );
+                    }
+                    "--default-mtu" => profile.mtu = None,
+                    "--dhcp-mtu" => profile.dhcp_mtu = true,
+                    "--no-dhcp-mtu" => profile.dhcp_mtu = false,
+                    "--speed" => {
+                        profile.speed = Some(
+                            args.next()
+                                .and_then(|value| value.parse().ok())
+                                .unwrap_or_else(|| usage()),
+                        );
+                    }
+                    "--any-speed" => profile.speed = None,
+                    "--duplex" => {
+                        let duplex = args
+                            .next()
+                            .filter(|duplex| duplex == DUPLEX_HALF || duplex == DUPLEX_FULL);
+                        profile.duplex = Some(duplex.unwrap_or_else(|| usage()));
+                    }
+                    "--any-duplex" => profile.duplex = None,
+                    "--autoneg" => profile.autoneg = Some(true),
+                    "--no-autoneg" => profile.autoneg = Some(false),
+                    "--default-autoneg" => profile.autoneg = None,
+                    "--wol" => profile.wake_on_lan = Some(true),
+                    "--no-wol" => profile.wake_on_lan = Some(false),
+                    "--default-wol" => profile.wake_on_lan = None,
+                    _ => usage(),
+                }
+            }
+            if !profile.valid() {
+                return Err("invalid wired profile".to_owned());
+            }
+            client.call_with("set_wired_profile", |call| {
+                call.body.push_param(name.as_str())?;
+                call.body.push_param(&profile)
+            })?;
+            if profile.link() != link {
+                client.call_with("set_wired_link", |call| {
+                    call.body.push_param(name.as_str())?;
+                    call.body.push_param(&profile.link())
+                })?;
+            }
+        }
+        "delete" => {
+            client.call_with("delete_wired_profile", |call| {
+                call.body.push_param(name.as_str())
+            })?;
+        }
+        _ => usage(),
+    }
+    Ok(())
+}
+
+fn describe_hotspot(
+    hotspot: &Option<(HotspotSetting, String)>,
+    clients: &[HotspotClient],
+) -> String {
+    let (setting, iface) = match hotspot {
+        Some(hotspot) => hotspot,
+        None => return "hotspot: off".to_owned(),
+    };
+    let mut text = format!(
+        "hotspot: {} on {}\nband: {} GHz\nsecurity: {}\nclients: {}",
+        setting.essid,
+        iface,
+        setting.band.name().unwrap_or("?"),
+        if setting.password.is_some() {
+            "wpa2"
+        } else {
+            "open"
+        },
+        clients.len()
+    );
+    for client in clients {
+        let hostname = if client.hostname.is_empty() {
+            "-"
+        } else {
+            client.hostname.as_str()
+        };
+        text.push_str(&format!(
+            "\n  {} {:<15} {}",
+            client.mac, client.ip, hostname
+        ));
+    }
+    text
+}
+
+fn hotspot(client: &mut Client, mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    match args.next().unwrap_or_else(|| usage()).as_str() {
+        "start" => {
+            let essid = args.next().unwrap_or_else(|| usage());
+            let (mut password, mut band) = (String::new(), String::new());
+            while let Some(arg) = args.next() {
+                let value = args.next().unwrap_or_else(|| usage());
+                match arg.as_str() {
+                    "--password" => password = value,
+                    "--band" if value == "2.4" || value == "5" => band = value,
+                    _ => usage(),
+                }
+            }
+            client.call_with("start_hotspot", |call| {
+                call.body.push_param(essid.as_str())?;
+                call.body.push_param(password.as_str())?;
+                call.body.push_param(band.as_str())
+            })?;
+        }
+        "stop" => {
+            let op = client.get::<u32>("stop_hotspot")?;
+            if op != 0 && !client.json {
+                println!("reconnecting as operation {}", op);
+            }
+        }
+        "status" => {
+            let hotspot = client.get::<Option<(HotspotSetting, String)>>("get_hotspot")?;
+            let clients = client.get::<Vec<HotspotClient>>("get_hotspot_clients")?;
+            if client.json {
+                println!(
+                    "{}",
+                    Value::Object(vec![
+                        (
+                            "hotspot",
+                            hotspot
+                                .as_ref()
+                                .map_or(Value::Null, |(setting, _)| setting.to_json())
+                        ),
+                        (
+                            "interface",
+                            hotspot.as_ref().map(|(_, iface)| iface.clone()).into()
+                        ),
+                        ("clients", clients.to_json()),
+                    ])
+                );
+            } else {
+                println!("{}", describe_hotspot(&hotspot, &clients));
+            }
+        }
+        _ => usage(),
+    }
+    Ok(())
+}
+
+fn monitor(client: &mut Client) -> Result<(), String> {
+    client.proxy.subscribe().map_err(|e| e.to_string())?;
+    client.call("hello")?;
+    loop {
+        client.next_signal(None)?.print(client.json);
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    let json = args.peek().is_some_and(|arg| arg == "--json");
+    if json {
+        args.next();
+    }
+    let command = args.next().unwrap_or_else(|| usage());
+    if command == "-h" || command == "--help" {
+        usage();
+    }
+
+    let result = Client::new(json).and_then(|mut client| match command.as_str() {
+        "status" => status(&mut client),
+        "scan" => scan(&mut client),
+        "list" => list(&mut client),
+        "aps" => access_points(&mut client, args),
+        "link" => link(&mut client),
+        "stats" => statistics(&mut client, args),
+        "usage" => data_usage(&mut client, args),
+        "connect" => connect(&mut client, args),
+        "cancel" => cancel(&mut client, args),
+        "disconnect" => client.call("disconnect").map(|_| ()),
+        "profile" => profile(&mut client, args),
+        "wired" => wired(&mut client, args),
+        "wired-profile" => wired_profile(&mut client, args),
+        "hotspot" => hotspot(&mut client, args),
+        "monitor" => monitor(&mut client),
+        _ => usage(),
+    });
+
+    process::exit(match result {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("snmctl: {}", e);
+            EXIT_FAILURE
+        }
+    });
+}
@@ -1,21 +1,190 @@
-use super::connection::KnownNetworks;
-use std::{fs, path::Path};
+use super::connection::{
+    new_mac_secret, ConnectivitySettings, KnownNetworks, RoamingSettings, UsageTotals,
+    WiredCredentials, WiredProfiles,
+};
+use super::events::EventsSettings;
+use super::logger::LogSettings;
+use serde::Deserialize;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io::Write};
 use toml;
 
-const CONFIG_FILE: &str = "/etc/snm/networks";
+const CONFIG_DIR: &str = "/etc/snm";
+const CONFIG_DIR_ENV: &str = "SNM_CONFIG_DIR";
+const CONFIG_FILE: &str = "networks";
+const SETTINGS_FILE: &str = "settings";
+const USAGE_FILE: &str = "usage";
+const WIRED_FILE: &str = "wired";
+const WIRED_PROFILES_FILE: &str = "wired_profiles";
+const MAC_SECRET_FILE: &str = "mac_secret";
 
-pub fn read_networks() -> KnownNetworks {
-    if let Ok(data) = fs::read_to_string(CONFIG_FILE) {
-        toml::decode_str(&data).expect("cannot parse config")
-    } else {
-        KnownNetworks::new()
+pub fn path(name: &str) -> PathBuf {
+    env::var_os(CONFIG_DIR_ENV)
+        .map_or_else(|| PathBuf::from(CONFIG_DIR), PathBuf::from)
+        .join(name)
+}
+
+#[derive(Default, Deserialize)]
+pub struct Settings {
+    #[serde(default = "ConnectivitySettings::default")]
+    pub connectivity: ConnectivitySettings,
+    #[serde(default = "EventsSettings::default")]
+    pub events: EventsSettings,
+    #[serde(default = "LogSettings::default")]
+    pub log: LogSettings,
+    #[serde(default = "RoamingSettings::default")]
+    pub roaming: RoamingSettings,
+}
+
+// a missing file is the default, a malformed one is reported and ignored
+fn read<T: Deserialize + Default>(name: &str) -> T {
+    match fs::read_to_string(path(name)) {
+        Ok(data) => toml::decode_str(&data).unwrap_or_else(|| {
+            warn!(file = name; "cannot parse config, using defaults");
+            T::default()
+        }),
+        Err(_) => T::default(),
     }
 }
 
+// the logger is configured from these, so it is not up yet: a malformed file is
+// flagged for the caller to report once logging is initialized
+pub fn read_settings() -> (Settings, bool) {
+    match fs::read_to_string(path(SETTINGS_FILE)) {
+        Ok(data) => match toml::decode_str(&data) {
+            Some(settings) => (settings, false),
+            None => (Settings::default(), true),
+        },
+        Err(_) => (Settings::default(), false),
+    }
+}
+
+pub fn read_networks() -> KnownNetworks {
+    read(CONFIG_FILE)
+}
+
 pub fn write_networks(networks: &KnownNetworks) -> std::io::Result<()> {
-    let file = Path::new(CONFIG_FILE);
+    let file = path(CONFIG_FILE);
     let dir = file.parent().ok_or(std::io::ErrorKind::NotFound)?;
     fs::create_dir_all(&dir)?;
-    fs::write(file, &toml::encode_str(networks))?;
+    fs::write(&file, &toml::encode_str(networks))?;
     Ok(())
 }
+
+pub fn read_usage() -> UsageTotals {
+    fs::read_to_string(path(USAGE_FILE))
+        .ok()
+        .and_then(|data| toml::decode_str(&data))
+        .unwrap_or_default()
+}
+
+pub fn write_usage(usage: &UsageTotals) -> std::io::Result<()> {
+    let file = path(USAGE_FILE);
+    let dir = file.parent().ok_or(std::io::ErrorKind::NotFound)?;
+    fs::create_dir_all(dir)?;
+    fs::write(&file, toml::encode_str(usage))?;
+    Ok(())
+}
+
+pub fn read_wired() -> WiredCredentials {
+    read(WIRED_FILE)
+}
+
+// the mode given on open only applies to a new file, one left by an older
+// version keeps whatever it had
+fn write_private(file: &Path, data: &[u8]) -> std::io::Result<()> {
+    let dir = file.parent().ok_or(std::io::ErrorKind::NotFound)?;
+    fs::create_dir_all(dir)?;
+    let mut out = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(file)?;
+    out.set_permissions(fs::Permissions::from_mode(0o600))?;
+    out.write_all(data)
+}
+
+pub fn write_wired(credentials: &WiredCredentials) -> std::io::Result<()> {
+    write_private(&path(WIRED_FILE), toml::encode_str(credentials).as_bytes())
+}
+
+pub fn read_wired_profiles() -> WiredProfiles {
+    valid_profiles(read(WIRED_PROFILES_FILE))
+}
+
+// a hand edited file gets the same checks as profiles set over D-Bus
+fn valid_profiles(mut profiles: WiredProfiles) -> WiredProfiles {
+    profiles.retain(|name, profile| {
+        let valid = profile.valid();
+        if !valid {
+            warn!(file = WIRED_PROFILES_FILE, profile = name.as_str(); "ignoring invalid wired profile");
+        }
+        valid
+    });
+    profiles
+}
+
+pub fn write_wired_profiles(profiles: &WiredProfiles) -> std::io::Result<()> {
+    let file = path(WIRED_PROFILES_FILE);
+    let dir = file.parent().ok_or(std::io::ErrorKind::NotFound)?;
+    fs::create_dir_all(dir)?;
+    fs::write(&file, toml::encode_str(profiles))?;
+    Ok(())
+}
+
+// created on first use, stable addresses change along with it
+pub fn read_mac_secret() -> std::io::Result<Vec<u8>> {
+    let file = path(MAC_SECRET_FILE);
+    match fs::read(&file) {
+        Ok(secret) if !secret.is_empty() => {
+            if let Err(e) = fs::set_permissions(&file, fs::Permissions::from_mode(0o600)) {
+                warn!(file = MAC_SECRET_FILE; "cannot restrict permissions: {}", e);
+            }
+            return Ok(secret);
+        }
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let secret = new_mac_secret();
+    write_private(&file, &secret)?;
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::WiredProfile;
+
+    #[test]
+    fn private_files_lose_old_permissions() {
+        let dir = std::env::temp_dir().join(format!("snm-config-{}", std::process::id()));
+        let file = dir.join(WIRED_FILE);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, "old").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&file, b"new").unwrap();
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        let data = fs::read(&file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(data, b"new");
+    }
+
+    #[test]
+    fn invalid_wired_profiles_are_dropped() {
+        let mut profiles = WiredProfiles::new();
+        profiles.insert("dhcp".to_owned(), WiredProfile::default());
+        profiles.insert(
+            "static".to_owned(),
+            WiredProfile {
+                method: "static".to_owned(),
+                ..WiredProfile::default()
+            },
+        );
+        let profiles = valid_profiles(profiles);
+        assert!(profiles.contains_key("dhcp"));
+        assert!(!profiles.contains_key("static"));
+    }
+}
@@ -0,0 +1,329 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::{thread, time};
+
+const MAX_RESPONSE_SIZE: u64 = 16384;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Connectivity {
+    Unknown,
+    None,
+    Portal,
+    Limited,
+    Full,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ConnectivitySettings {
+    #[serde(default = "ConnectivitySettings::default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "ConnectivitySettings::default_uri")]
+    pub uri: String,
+    #[serde(default = "ConnectivitySettings::default_response")]
+    pub response: Option<String>,
+    #[serde(default = "ConnectivitySettings::default_interval")]
+    pub interval: u64,
+    #[serde(default = "ConnectivitySettings::default_timeout")]
+    pub timeout: u64,
+}
+
+impl ConnectivitySettings {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_uri() -> String {
+        "http://connectivitycheck.gstatic.com/generate_204".to_owned()
+    }
+
+    fn default_response() -> Option<String> {
+        None
+    }
+
+    fn default_interval() -> u64 {
+        300
+    }
+
+    fn default_timeout() -> u64 {
+        5
+    }
+}
+
+impl Default for ConnectivitySettings {
+    fn default() -> Self {
+        ConnectivitySettings {
+            enabled: Self::default_enabled(),
+            uri: Self::default_uri(),
+            response: Self::default_response(),
+            interval: Self::default_interval(),
+            timeout: Self::default_timeout(),
+        }
+    }
+}
+
+struct Uri {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Uri {
+    fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(pos) => (&rest[..pos], &rest[pos..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(pos) => (&authority[..pos], authority[pos + 1..].parse().ok()?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Uri {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct ConnectivityChecker {
+    settings: ConnectivitySettings,
+}
+
+impl ConnectivityChecker {
+    pub fn new(settings: ConnectivitySettings) -> Self {
+        ConnectivityChecker { settings }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.settings.interval
+    }
+
+    fn request(&self, uri: &Uri) -> std::io::Result<Vec<u8>> {
+        use std::io::{Error, ErrorKind};
+        let timeout = time::Duration::from_secs(self.settings.timeout);
+        let addr = (uri.host.as_str(), uri.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+        let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: snm\r\nConnection: close\r\n\r\n",
+            uri.path, uri.host
+        );
+        stream.write_all(request.as_bytes())?;
+        let mut response = vec![];
+        stream.take(MAX_RESPONSE_SIZE).read_to_end(&mut response)?;
+        Ok(response)
+    }
+
+    fn classify(&self, response: &[u8]) -> Connectivity {
+        let text = String::from_utf8_lossy(response);
+        let (head, body) = match text.find("\r\n\r\n") {
+            Some(pos) => (&text[..pos], &text[pos + 4..]),
+            None => (text.as_ref(), ""),
+        };
+        let status = head
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u32>().ok());
+        match (status, &self.settings.response) {
+            (Some(204), None) => Connectivity::Full,
+            (Some(200), Some(expected)) if body.trim() == expected.trim() => Connectivity::Full,
+            // portals redirect or answer the probe with their own page
+            (Some(300..=399), _) | (Some(200), _) => Connectivity::Portal,
+            _ => Connectivity::Limited,
+        }
+    }
+
+    fn classify_failure(e: &std::io::Error) -> Connectivity {
+        use std::io::ErrorKind;
+        match e.kind() {
+            // something is there but does not answer
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => Connectivity::Limited,
+            // no route, nobody listening or the name does not resolve
+            _ => Connectivity::None,
+        }
+    }
+
+    pub fn check(&self) -> Connectivity {
+        if !self.settings.enabled {
+            return Connectivity::Unknown;
+        }
+        if let Some(uri) = Uri::parse(&self.settings.uri) {
+            match self.request(&uri) {
+                Ok(response) => self.classify(&response),
+                Err(e) => Self::classify_failure(&e),
+            }
+        } else {
+            Connectivity::Unknown
+        }
+    }
+
+    /// Right after dhcp resolv.conf and routes may still be settling, so
+    /// give the probe a few chances before reporting a degraded state.
+    pub fn check_settled(&self, tries: usize) -> Connectivity {
+        let mut result = self.check();
+        for _ in 1..tries {
+            if result == Connectivity::Full || result == Connectivity::Unknown {
+                break;
+            }
+            thread::sleep(time::Duration::from_secs(1));
+            result = self.check();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn serve(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://127.0.0.1:{}/check", port)
+    }
+
+    fn checker(uri: String, response: Option<&str>) -> ConnectivityChecker {
+        ConnectivityChecker::new(ConnectivitySettings {
+            uri,
+            response: response.map(|r| r.to_owned()),
+            timeout: 2,
+            ..ConnectivitySettings::default()
+        })
+    }
+
+    #[test]
+    fn no_content_is_full() {
+        let uri = serve("HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+        assert_eq!(checker(uri, None).check(), Connectivity::Full);
+    }
+
+    #[test]
+    fn expected_body_is_full() {
+        let uri = serve("HTTP/1.1 200 OK\r\n\r\nsnm is online\n");
+        assert_eq!(
+            checker(uri, Some("snm is online")).check(),
+            Connectivity::Full
+        );
+    }
+
+    #[test]
+    fn redirect_is_portal() {
+        let uri = serve("HTTP/1.1 302 Found\r\nLocation: http://login.hotel/\r\n\r\n");
+        assert_eq!(checker(uri, None).check(), Connectivity::Portal);
+    }
+
+    #[test]
+    fn unexpected_body_is_portal() {
+        let uri = serve("HTTP/1.1 200 OK\r\n\r\n<html>Please log in</html>");
+        assert_eq!(
+            checker(uri, Some("snm is online")).check(),
+            Connectivity::Portal
+        );
+    }
+
+    #[test]
+    fn server_errors_are_limited() {
+        let uri = serve("HTTP/1.1 503 Service Unavailable\r\n\r\n");
+        assert_eq!(checker(uri, None).check(), Connectivity::Limited);
+        let uri = serve("HTTP/1.1 404 Not Found\r\n\r\n");
+        assert_eq!(checker(uri, None).check(), Connectivity::Limited);
+    }
+
+    #[test]
+    fn refused_is_none() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        assert_eq!(checker(uri, None).check(), Connectivity::None);
+    }
+
+    #[test]
+    fn unresolved_is_none() {
+        let uri = "http://snm.invalid/generate_204".to_owned();
+        assert_eq!(checker(uri, None).check(), Connectivity::None);
+    }
+
+    #[test]
+    fn unreachable_is_none() {
+        use std::io::{Error, ErrorKind};
+        for kind in [ErrorKind::NetworkUnreachable, ErrorKind::HostUnreachable].iter() {
+            assert_eq!(
+                ConnectivityChecker::classify_failure(&Error::from(*kind)),
+                Connectivity::None
+            );
+        }
+        assert_eq!(
+            ConnectivityChecker::classify_failure(&Error::from(ErrorKind::TimedOut)),
+            Connectivity::Limited
+        );
+    }
+
+    #[test]
+    fn silent_server_is_limited() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/", listener.local_addr().unwrap());
+        let mut checker = checker(uri, None);
+        checker.settings.timeout = 1;
+        assert_eq!(checker.check(), Connectivity::Limited);
+        drop(listener);
+    }
+
+    #[test]
+    fn garbage_is_limited() {
+        let uri = serve("");
+        assert_eq!(checker(uri, None).check(), Connectivity::Limited);
+    }
+
+    #[test]
+    fn disabled_is_unknown() {
+        let settings = ConnectivitySettings {
+            enabled: false,
+            ..ConnectivitySettings::default()
+        };
+        assert_eq!(
+            ConnectivityChecker::new(settings).check(),
+            Connectivity::Unknown
+        );
+    }
+
+    #[test]
+    fn uri_parsing() {
+        let uri = Uri::parse("http://example.com:8080/a/b").unwrap();
+        assert_eq!(uri.host, "example.com");
+        assert_eq!(uri.port, 8080);
+        assert_eq!(uri.path, "/a/b");
+        let uri = Uri::parse("http://example.com").unwrap();
+        assert_eq!(uri.port, 80);
+        assert_eq!(uri.path, "/");
+        assert!(Uri::parse("https://example.com/").is_none());
+        assert!(Uri::parse("http://:80/").is_none());
+    }
+}
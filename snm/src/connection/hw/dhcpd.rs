@@ -0,0 +1,312 @@
+use super::super::types::HotspotClient;
+use smoltcp::wire::{
+    DhcpMessageType, DhcpPacket, DhcpRepr, EthernetAddress, Ipv4Address, DHCP_MAX_DNS_SERVER_COUNT,
+};
+
+const LEASE_SECS: u32 = 3600;
+const OPT_PAD: u8 = 0;
+const OPT_HOST_NAME: u8 = 12;
+const OPT_END: u8 = 255;
+// bootp relays drop anything shorter
+const MIN_REPLY_LEN: usize = 300;
+
+struct Lease {
+    mac: EthernetAddress,
+    ip: Ipv4Address,
+    hostname: String,
+    // acknowledged, not just offered
+    bound: bool,
+}
+
+// hands out the addresses of the server network to hotspot clients
+pub struct LeasePool {
+    server: Ipv4Address,
+    prefix: u8,
+    leases: Vec<Lease>,
+}
+
+impl LeasePool {
+    // address of the server in CIDR notation
+    pub fn new(address: &str) -> Option<Self> {
+        let mut parts = address.splitn(2, '/');
+        let server = parts.next()?.parse::<std::net::Ipv4Addr>().ok()?;
+        let prefix = parts
+            .next()?
+            .parse::<u8>()
+            .ok()
+            .filter(|p| (8..=30).contains(p))?;
+        Some(LeasePool {
+            server: Ipv4Address::from_bytes(&server.octets()),
+            prefix,
+            leases: Vec::new(),
+        })
+    }
+
+    fn mask(&self) -> u32 {
+        u32::MAX << (32 - self.prefix)
+    }
+
+    fn in_pool(&self, ip: Ipv4Address) -> bool {
+        let (ip, server) = (u32::from_be_bytes(ip.0), u32::from_be_bytes(self.server.0));
+        let host = ip & !self.mask();
+        ip & self.mask() == server & self.mask()
+            && host != 0
+            && host != !self.mask()
+            && ip != server
+    }
+
+    fn free(&self, ip: Ipv4Address, mac: EthernetAddress) -> bool {
+        self.in_pool(ip)
+            && self
+                .leases
+                .iter()
+                .all(|lease| lease.ip != ip || lease.mac == mac)
+    }
+
+    // the address already given to mac, the one it asked for or the lowest free one
+    pub fn lease(
+        &mut self,
+        mac: EthernetAddress,
+        requested: Option<Ipv4Address>,
+        hostname: &str,
+    ) -> Option<Ipv4Address> {
+        if let Some(lease) = self.leases.iter_mut().find(|lease| lease.mac == mac) {
+            if !hostname.is_empty() {
+                lease.hostname = hostname.to_owned();
+            }
+            return Some(lease.ip);
+        }
+        let network = u32::from_be_bytes(self.server.0) & self.mask();
+        let ip = requested.filter(|ip| self.free(*ip, mac)).or_else(|| {
+            (1..!self.mask())
+                .map(|host| Ipv4Address::from_bytes(&(network | host).to_be_bytes()))
+                .find(|ip| self.free(*ip, mac))
+        })?;
+        self.leases.push(Lease {
+            mac,
+            ip,
+            hostname: hostname.to_owned(),
+            bound: false,
+        });
+        Some(ip)
+    }
+
+    // what a request for ip gets, false if another client holds it
+    pub fn bind(&mut self, mac: EthernetAddress, ip: Ipv4Address, hostname: &str) -> bool {
+        if !self.free(ip, mac) {
+            return false;
+        }
+        self.leases.retain(|lease| lease.mac != mac);
+        self.leases.push(Lease {
+            mac,
+            ip,
+            hostname: hostname.to_owned(),
+            bound: true,
+        });
+        true
+    }
+
+    pub fn clients(&self) -> Vec<HotspotClient> {
+        self.leases
+            .iter()
+            .filter(|lease| lease.bound)
+            .map(|lease| HotspotClient {
+                mac: lease
+                    .mac
+                    .as_bytes()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<_>>()
+                    .join(":"),
+                ip: lease.ip.to_string(),
+                hostname: lease.hostname.clone(),
+            })
+            .collect()
+    }
+
+    // the reply to a client message, if it deserves one
+    pub fn answer(&mut self, request: &[u8], dns: &[Ipv4Address]) -> Option<Vec<u8>> {
+        let packet = DhcpPacket::new_checked(request).ok()?;
+        let repr = DhcpRepr::parse(&packet).ok()?;
+        let mac = repr.client_hardware_address;
+        let hostname = host_name(packet.options().ok()?);
+        let (message_type, ip) = match repr.message_type {
+            DhcpMessageType::Discover => (
+                DhcpMessageType::Offer,
+                self.lease(mac, repr.requested_ip, &hostname)?,
+            ),
+            DhcpMessageType::Request => {
+                if repr
+                    .server_identifier
+                    .is_some_and(|server| server != self.server)
+                {
+                    // the client took another offer
+                    self.leases.retain(|lease| lease.mac != mac || lease.bound);
+                    return None;
+                }
+                let ip = repr
+                    .requested_ip
+                    .or_else(|| Some(repr.client_ip).filter(|ip| !ip.is_unspecified()))?;
+                if self.bind(mac, ip, &hostname) {
+                    (DhcpMessageType::Ack, ip)
+                } else {
+                    (DhcpMessageType::Nak, Ipv4Address::UNSPECIFIED)
+                }
+            }
+            DhcpMessageType::Release | DhcpMessageType::Decline => {
+                self.leases.retain(|lease| lease.mac != mac);
+                return None;
+            }
+            _ => return None,
+        };
+        let granted = message_type != DhcpMessageType::Nak;
+        let mut dns_servers = [None; DHCP_MAX_DNS_SERVER_COUNT];
+        for (slot, server) in dns_servers.iter_mut().zip(dns) {
+            *slot = Some(*server);
+        }
+        let reply = DhcpRepr {
+            message_type,
+            transaction_id: repr.transaction_id,
+            client_hardware_address: mac,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: ip,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            router: Some(self.server).filter(|_| granted),
+            subnet_mask: Some(Ipv4Address::from_bytes(&self.mask().to_be_bytes()))
+                .filter(|_| granted),
+            relay_agent_ip: repr.relay_agent_ip,
+            broadcast: repr.broadcast,
+            requested_ip: None,
+            client_identifier: None,
+            server_identifier: Some(self.server),
+            parameter_request_list: None,
+            dns_servers: Some(dns_servers).filter(|_| granted && !dns.is_empty()),
+            max_size: None,
+            lease_duration: Some(LEASE_SECS).filter(|_| granted),
+        };
+        let mut buffer = vec![0; reply.buffer_len().max(MIN_REPLY_LEN)];
+        reply
+            .emit(&mut DhcpPacket::new_unchecked(&mut buffer))
+            .ok()?;
+        Some(buffer)
+    }
+}
+
+fn host_name(mut options: &[u8]) -> String {
+    while let Some((&kind, rest)) = options.split_first() {
+        if kind == OPT_END {
+            break;
+        }
+        if kind == OPT_PAD {
+            options = rest;
+            continue;
+        }
+        let (length, data) = match rest.split_first() {
+            Some((&length, data)) if data.len() >= length as usize => (length as usize, data),
+            _ => break,
+        };
+        if kind == OPT_HOST_NAME {
+            return String::from_utf8_lossy(&data[..length]).into_owned();
+        }
+        options = &data[length..];
+    }
+    String::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHONE: EthernetAddress = EthernetAddress([2, 0, 0, 0, 0, 0xaa]);
+    const LAPTOP: EthernetAddress = EthernetAddress([2, 0, 0, 0, 0, 0xbb]);
+
+    fn request(
+        message_type: DhcpMessageType,
+        mac: EthernetAddress,
+        requested_ip: Option<Ipv4Address>,
+        server: Option<Ipv4Address>,
+    ) -> Vec<u8> {
+        let repr = DhcpRepr {
+            message_type,
+            transaction_id: 0x1234,
+            client_hardware_address: mac,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: Ipv4Address::UNSPECIFIED,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            router: None,
+            subnet_mask: None,
+            relay_agent_ip: Ipv4Address::UNSPECIFIED,
+            broadcast: true,
+            requested_ip,
+            client_identifier: None,
+            server_identifier: server,
+            parameter_request_list: Some(&[1, 3, 6]),
+            dns_servers: None,
+            max_size: None,
+            lease_duration: None,
+        };
+        let mut buffer = vec![0; repr.buffer_len()];
+        repr.emit(&mut DhcpPacket::new_unchecked(&mut buffer))
+            .unwrap();
+        // the host name goes in place of the end option
+        buffer.pop();
+        buffer.extend_from_slice(&[OPT_HOST_NAME, 5]);
+        buffer.extend_from_slice(b"phone");
+        buffer.push(OPT_END);
+        buffer
+    }
+
+    fn reply(pool: &mut LeasePool, request: &[u8]) -> Option<(DhcpMessageType, Ipv4Address)> {
+        let reply = pool.answer(request, &[Ipv4Address::new(1, 1, 1, 1)])?;
+        let packet = DhcpPacket::new_checked(&reply[..]).unwrap();
+        let repr = DhcpRepr::parse(&packet).unwrap();
+        Some((repr.message_type, repr.your_ip))
+    }
+
+    #[test]
+    fn leases_addresses_to_clients() {
+        let server = Ipv4Address::new(10, 42, 0, 1);
+        let mut pool = LeasePool::new("10.42.0.1/24").unwrap();
+        let discover = request(DhcpMessageType::Discover, PHONE, None, None);
+        let offered = Ipv4Address::new(10, 42, 0, 2);
+        assert_eq!(
+            reply(&mut pool, &discover),
+            Some((DhcpMessageType::Offer, offered))
+        );
+        assert!(pool.clients().is_empty());
+
+        let accept = request(DhcpMessageType::Request, PHONE, Some(offered), Some(server));
+        assert_eq!(
+            reply(&mut pool, &accept),
+            Some((DhcpMessageType::Ack, offered))
+        );
+        assert_eq!(
+            pool.clients(),
+            vec![HotspotClient {
+                mac: "02:00:00:00:00:aa".to_owned(),
+                ip: "10.42.0.2".to_owned(),
+                hostname: "phone".to_owned(),
+            }]
+        );
+
+        // taken addresses are refused, offers to others skip them
+        let steal = request(DhcpMessageType::Request, LAPTOP, Some(offered), None);
+        assert_eq!(
+            reply(&mut pool, &steal).map(|(kind, _)| kind),
+            Some(DhcpMessageType::Nak)
+        );
+        let discover = request(DhcpMessageType::Discover, LAPTOP, Some(offered), None);
+        assert_eq!(
+            reply(&mut pool, &discover),
+            Some((DhcpMessageType::Offer, Ipv4Address::new(10, 42, 0, 3)))
+        );
+        let elsewhere = Ipv4Address::new(192, 168, 1, 1);
+        let declined = request(DhcpMessageType::Request, LAPTOP, None, Some(elsewhere));
+        assert_eq!(reply(&mut pool, &declined), None);
+
+        let release = request(DhcpMessageType::Release, PHONE, None, Some(server));
+        assert_eq!(reply(&mut pool, &release), None);
+        assert!(pool.clients().is_empty());
+        assert!(LeasePool::new("10.42.0.1").is_none());
+    }
+}
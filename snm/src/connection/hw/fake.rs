@@ -0,0 +1,710 @@
+use super::super::support;
+use super::super::types::{
+    AccessPoint, DhcpEvent, HotspotClient, LinkMode, LinkStats, Ssid, Station, Statistics,
+};
+use super::dhcpd::LeasePool;
+use crate::error::{Error, Result};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::{fs, io, time};
+
+#[derive(Default)]
+struct Port {
+    plugged: bool,
+    up: bool,
+    ip: Option<String>,
+    lease: Option<String>,
+    // the dhcp server marks the lease metered
+    metered: bool,
+    // interface mtu offered with the lease
+    lease_mtu: Option<u32>,
+    // tx and rx in kbit/s
+    bitrates: (u32, u32),
+    counters: Statistics,
+    mac: Option<String>,
+    permanent_mac: Option<String>,
+    // the driver refuses a new address
+    fixed_mac: bool,
+    // identity and password the switch wants before it forwards traffic
+    dot1x: Option<(String, String)>,
+    authorized: bool,
+    gateway: Option<String>,
+    mtu: Option<u32>,
+    link_mode: LinkMode,
+    wake_on_lan: Option<bool>,
+    supplicant: Option<Association>,
+    // the access point the supplicant runs instead of associating
+    hosting: Option<Hosted>,
+}
+
+// what the supplicant was started with
+struct Association {
+    essid: Ssid,
+    bssid: Option<String>,
+    // tried first while it is around
+    bssid_hint: Option<String>,
+    // any frequency if empty
+    freq_list: Vec<u32>,
+    psk: Option<String>,
+    // address of the port when the supplicant started
+    mac: Option<String>,
+    // the bss it settled on
+    current: Option<String>,
+    started: time::Instant,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hosted {
+    pub essid: Ssid,
+    pub frequency: u32,
+    pub psk: Option<String>,
+}
+
+#[derive(Default)]
+struct State {
+    ports: BTreeMap<String, Port>,
+    access_points: Vec<AccessPoint>,
+    rejected: HashSet<Ssid>,
+    // networks with known security accept only the matching psk, None for open ones
+    passwords: HashMap<Ssid, Option<String>>,
+    // servers offered with a lease and servers applied to the resolver
+    offered_dns: Vec<String>,
+    resolver: Vec<String>,
+    // ip to mac
+    neighbors: HashMap<String, String>,
+    // downstream, subnet and uplink
+    shared: Option<(String, String, String)>,
+    // the firewall rejects the sharing rules
+    sharing_refused: bool,
+    forwarding: bool,
+    // mac and host name of clients about to join a hotspot
+    joining: Vec<(String, String)>,
+}
+
+impl State {
+    fn port(&self, iface: &str) -> Result<&Port> {
+        self.ports
+            .get(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))
+    }
+
+    // the bss the configuration allows
+    fn candidates(&self, iface: &str) -> Vec<&AccessPoint> {
+        let association = match self
+            .ports
+            .get(iface)
+            .and_then(|port| port.supplicant.as_ref())
+        {
+            Some(association) => association,
+            None => return Vec::new(),
+        };
+        let essid = &association.essid;
+        if self.rejected.contains(essid) {
+            return Vec::new();
+        }
+        if let Some(password) = self.passwords.get(essid) {
+            let expected = password
+                .as_ref()
+                .map(|password| support::psk(essid.as_bytes(), password));
+            if association.psk != expected {
+                return Vec::new();
+            }
+        }
+        self.access_points
+            .iter()
+            .filter(|ap| {
+                ap.essid == *essid
+                    && association
+                        .bssid
+                        .as_ref()
+                        .is_none_or(|bssid| *bssid == ap.bssid)
+                    && (association.freq_list.is_empty()
+                        || association.freq_list.contains(&ap.frequency))
+            })
+            .collect()
+    }
+
+    // stays on the current bss while it is around, then the hinted one, otherwise the strongest
+    fn associated(&self, iface: &str) -> Option<&AccessPoint> {
+        let candidates = self.candidates(iface);
+        let association = self.ports.get(iface)?.supplicant.as_ref()?;
+        let (current, hint) = (
+            association.current.as_ref(),
+            association.bssid_hint.as_ref(),
+        );
+        candidates
+            .iter()
+            .find(|ap| current == Some(&ap.bssid))
+            .or_else(|| candidates.iter().find(|ap| hint == Some(&ap.bssid)))
+            .or_else(|| candidates.iter().max_by_key(|ap| ap.signal))
+            .copied()
+    }
+}
+
+// in-memory stand-in for all the hardware traits
+#[derive(Clone, Default)]
+pub struct Fake {
+    state: Arc<Mutex<State>>,
+}
+
+impl Fake {
+    pub fn new() -> Self {
+        Fake::default()
+    }
+
+    // every port has an address burnt in, it only shows once set
+    pub fn add_port(&self, iface: &str) {
+        let mut state = self.state.lock().unwrap();
+        let count = state.ports.len();
+        state.ports.entry(iface.to_owned()).or_insert_with(|| Port {
+            permanent_mac: Some(format!("02:00:00:00:01:{:02x}", count)),
+            ..Port::default()
+        });
+    }
+
+    pub fn clear_permanent_mac(&self, iface: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.permanent_mac = None;
+        }
+    }
+
+    pub fn fix_mac(&self, iface: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.fixed_mac = true;
+        }
+    }
+
+    pub fn remove_port(&self, iface: &str) {
+        self.state.lock().unwrap().ports.remove(iface);
+    }
+
+    pub fn plug(&self, iface: &str, plugged: bool) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.plugged = plugged;
+        }
+    }
+
+    pub fn set_access_points(&self, access_points: Vec<AccessPoint>) {
+        self.state.lock().unwrap().access_points = access_points;
+    }
+
+    pub fn set_password<S: Into<Ssid>>(&self, essid: S, password: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .passwords
+            .insert(essid.into(), password.map(str::to_owned));
+    }
+
+    pub fn reject<S: Into<Ssid>>(&self, essid: S) {
+        self.state.lock().unwrap().rejected.insert(essid.into());
+    }
+
+    pub fn set_lease(&self, iface: &str, address: Option<&str>) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.lease = address.map(str::to_owned);
+        }
+    }
+
+    pub fn set_metered(&self, iface: &str, metered: bool) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.metered = metered;
+        }
+    }
+
+    pub fn set_lease_mtu(&self, iface: &str, mtu: Option<u32>) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.lease_mtu = mtu;
+        }
+    }
+
+    pub fn set_mac(&self, iface: &str, mac: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.mac = Some(mac.to_owned());
+            port.permanent_mac = Some(mac.to_owned());
+        }
+    }
+
+    pub fn associated_mac(&self, iface: &str) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let port = state.ports.get(iface)?;
+        port.supplicant.as_ref()?.mac.clone()
+    }
+
+    pub fn require_8021x(&self, iface: &str, identity: &str, password: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.dot1x = Some((identity.to_owned(), password.to_owned()));
+        }
+    }
+
+    pub fn set_bitrates(&self, iface: &str, tx: u32, rx: u32) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.bitrates = (tx, rx);
+        }
+    }
+
+    pub fn add_traffic(&self, iface: &str, rx: u64, tx: u64) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.counters.rx_bytes += rx;
+            port.counters.tx_bytes += tx;
+            port.counters.rx_packets += 1;
+            port.counters.tx_packets += 1;
+        }
+    }
+
+    pub fn set_neighbor(&self, ip: &str, mac: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.neighbors.insert(ip.to_owned(), mac.to_owned());
+    }
+
+    pub fn gateway(&self, iface: &str) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.ports.get(iface).and_then(|port| port.gateway.clone())
+    }
+
+    pub fn link_mode(&self, iface: &str) -> LinkMode {
+        let state = self.state.lock().unwrap();
+        state
+            .ports
+            .get(iface)
+            .map(|port| port.link_mode)
+            .unwrap_or_default()
+    }
+
+    pub fn wake_on_lan(&self, iface: &str) -> Option<bool> {
+        let state = self.state.lock().unwrap();
+        state.ports.get(iface).and_then(|port| port.wake_on_lan)
+    }
+
+    pub fn set_dns(&self, servers: &[&str]) {
+        self.state.lock().unwrap().offered_dns = servers.iter().map(|s| s.to_string()).collect();
+    }
+
+    pub fn address(&self, iface: &str) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.ports.get(iface).and_then(|port| port.ip.clone())
+    }
+
+    pub fn is_port_up(&self, iface: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state.ports.get(iface).is_some_and(|port| port.up)
+    }
+
+    pub fn applied_dns(&self) -> Vec<String> {
+        self.state.lock().unwrap().resolver.clone()
+    }
+
+    pub fn hosted(&self, iface: &str) -> Option<Hosted> {
+        let state = self.state.lock().unwrap();
+        state.ports.get(iface).and_then(|port| port.hosting.clone())
+    }
+
+    pub fn shared(&self) -> Option<(String, String, String)> {
+        self.state.lock().unwrap().shared.clone()
+    }
+
+    pub fn refuse_sharing(&self, refused: bool) {
+        self.state.lock().unwrap().sharing_refused = refused;
+    }
+
+    pub fn set_forwarding(&self, enabled: bool) {
+        self.state.lock().unwrap().forwarding = enabled;
+    }
+
+    pub fn forwarding(&self) -> bool {
+        self.state.lock().unwrap().forwarding
+    }
+
+    pub fn join_hotspot(&self, mac: &str, hostname: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.joining.push((mac.to_owned(), hostname.to_owned()));
+    }
+}
+
+impl super::Link for Fake {
+    fn interfaces(&self) -> Result<Vec<String>> {
+        Ok(self.state.lock().unwrap().ports.keys().cloned().collect())
+    }
+
+    fn up(&self, iface: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.up = true;
+        }
+    }
+
+    fn down(&self, iface: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.up = false;
+            port.ip = None;
+        }
+    }
+
+    fn is_plugged_in(&self, iface: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state.port(iface).is_ok_and(|port| port.plugged) || state.associated(iface).is_some()
+    }
+
+    fn is_up(&self, iface: &str) -> bool {
+        self.is_port_up(iface)
+    }
+
+    fn flush(&self, iface: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.ip = None;
+            port.gateway = None;
+        }
+    }
+
+    fn ip(&self, iface: &str) -> Option<String> {
+        self.address(iface)
+    }
+
+    fn has_global_ipv6(&self, _iface: &str) -> bool {
+        false
+    }
+
+    fn statistics(&self, iface: &str) -> Option<Statistics> {
+        let state = self.state.lock().unwrap();
+        state.port(iface).ok().map(|port| port.counters.clone())
+    }
+
+    fn mac(&self, iface: &str) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.port(iface).ok().and_then(|port| port.mac.clone())
+    }
+
+    fn permanent_mac(&self, iface: &str) -> Result<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .port(iface)?
+            .permanent_mac
+            .clone()
+            .ok_or_else(|| Error::NoHwAddr(iface.to_owned()))
+    }
+
+    fn set_mac(&self, iface: &str, mac: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let port = state
+            .ports
+            .get_mut(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+        if port.fixed_mac {
+            return Err(Error::NotAllowed(format!(
+                "changing the address of {}",
+                iface
+            )));
+        }
+        port.mac = Some(mac.to_owned());
+        Ok(())
+    }
+
+    fn set_address(&self, iface: &str, address: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let port = state
+            .ports
+            .get_mut(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+        port.ip = address.split('/').next().map(str::to_owned);
+        Ok(())
+    }
+
+    fn set_gateway(&self, iface: &str, gateway: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let port = state
+            .ports
+            .get_mut(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+        port.gateway = Some(gateway.to_owned());
+        Ok(())
+    }
+
+    fn set_mtu(&self, iface: &str, mtu: u32) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let port = state
+            .ports
+            .get_mut(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+        port.mtu = Some(mtu);
+        Ok(())
+    }
+
+    // ports start out with the ethernet default
+    fn mtu(&self, iface: &str) -> Option<u32> {
+        let state = self.state.lock().unwrap();
+        state.port(iface).ok().map(|port| port.mtu.unwrap_or(1500))
+    }
+
+    fn set_link_mode(&self, iface: &str, mode: LinkMode) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let port = state
+            .ports
+            .get_mut(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+        port.link_mode = mode;
+        Ok(())
+    }
+
+    fn set_wake_on_lan(&self, iface: &str, enabled: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let port = state
+            .ports
+            .get_mut(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+        port.wake_on_lan = Some(enabled);
+        Ok(())
+    }
+
+    fn neighbor(&self, iface: &str, ip: &str) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.port(iface).ok()?;
+        state.neighbors.get(ip).cloned()
+    }
+}
+
+impl super::Scanner for Fake {
+    fn scan(&self, iface: &str) -> Result<Vec<AccessPoint>> {
+        let state = self.state.lock().unwrap();
+        state.port(iface)?;
+        Ok(state.access_points.clone())
+    }
+
+    fn link(&self, iface: &str) -> Option<Station> {
+        let state = self.state.lock().unwrap();
+        let ap = state.associated(iface)?;
+        Some(Station {
+            essid: ap.essid.clone(),
+            bssid: ap.bssid.clone(),
+            frequency: ap.frequency,
+            signal: Some(ap.signal),
+        })
+    }
+
+    fn link_stats(&self, iface: &str) -> Option<LinkStats> {
+        let state = self.state.lock().unwrap();
+        let ap = state.associated(iface)?;
+        let port = state.port(iface).ok()?;
+        let started = port.supplicant.as_ref()?.started;
+        Some(LinkStats {
+            signal: ap.signal,
+            tx_bitrate: port.bitrates.0,
+            rx_bitrate: port.bitrates.1,
+            connected_time: started.elapsed().as_secs() as u32,
+        })
+    }
+}
+
+impl super::Supplicant for Fake {
+    fn start(&self, iface: &str, driver: super::Driver, config: &str) -> Result<()> {
+        let config = fs::read_to_string(config)?;
+        let value = |key: &str| {
+            config
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix(key))
+                .map(str::to_owned)
+                .next()
+        };
+        if driver == super::Driver::Wired {
+            let text = |key: &str| value(key).map(|text| text.trim_matches('"').to_owned());
+            let login = text("identity=").zip(text("password="));
+            let mut state = self.state.lock().unwrap();
+            let port = state
+                .ports
+                .get_mut(iface)
+                .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+            port.authorized = port.dot1x.is_none() || port.dot1x == login;
+            return Ok(());
+        }
+        let essid = value("ssid=").and_then(|essid| match essid.strip_prefix('"') {
+            Some(text) => Some(Ssid::from(text.trim_end_matches('"'))),
+            None => Ssid::from_hex(&essid),
+        });
+        if value("mode=").as_deref() == Some("2") {
+            let hosting = essid.map(|essid| Hosted {
+                essid,
+                frequency: value("frequency=")
+                    .and_then(|frequency| frequency.parse().ok())
+                    .unwrap_or_default(),
+                psk: value("psk="),
+            });
+            let mut state = self.state.lock().unwrap();
+            let port = state
+                .ports
+                .get_mut(iface)
+                .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+            port.hosting = hosting;
+            return Ok(());
+        }
+        let association = essid.map(|essid| Association {
+            essid,
+            bssid: value("bssid="),
+            bssid_hint: value("bssid_hint="),
+            freq_list: value("freq_list=")
+                .map(|list| {
+                    list.split_whitespace()
+                        .filter_map(|frequency| frequency.parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            psk: value("psk="),
+            mac: None,
+            current: None,
+            started: time::Instant::now(),
+        });
+        let mut state = self.state.lock().unwrap();
+        let port = state
+            .ports
+            .get_mut(iface)
+            .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+        port.supplicant = association.map(|association| Association {
+            mac: port.mac.clone(),
+            ..association
+        });
+        let current = state.associated(iface).map(|ap| ap.bssid.clone());
+        if let Some(association) = state
+            .ports
+            .get_mut(iface)
+            .and_then(|port| port.supplicant.as_mut())
+        {
+            association.current = current;
+        }
+        Ok(())
+    }
+
+    fn state(&self, iface: &str) -> Result<String> {
+        let state = self.state.lock().unwrap();
+        state.port(iface)?;
+        Ok(if state.associated(iface).is_some()
+            || state.port(iface)?.authorized
+            || state.port(iface)?.hosting.is_some()
+        {
+            "COMPLETED"
+        } else {
+            "SCANNING"
+        }
+        .to_owned())
+    }
+
+    fn roam(&self, iface: &str, bssid: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.candidates(iface).iter().any(|ap| ap.bssid == bssid) {
+            return Err(Error::UnknownNetwork(bssid.to_owned()));
+        }
+        if let Some(association) = state
+            .ports
+            .get_mut(iface)
+            .and_then(|port| port.supplicant.as_mut())
+        {
+            association.current = Some(bssid.to_owned());
+        }
+        Ok(())
+    }
+
+    fn stop(&self, iface: &str) {
+        if let Some(port) = self.state.lock().unwrap().ports.get_mut(iface) {
+            port.supplicant = None;
+            port.authorized = false;
+            port.hosting = None;
+        }
+    }
+
+    fn poll_interval(&self) -> time::Duration {
+        time::Duration::from_millis(1)
+    }
+}
+
+impl super::Dhcp for Fake {
+    fn run(
+        &self,
+        iface: &str,
+        _runflag: Arc<AtomicBool>,
+        on_event: &mut dyn FnMut(DhcpEvent),
+    ) -> Result<()> {
+        let (address, dns, metered, mtu) = {
+            let mut state = self.state.lock().unwrap();
+            let dns = state.offered_dns.clone();
+            let port = state
+                .ports
+                .get_mut(iface)
+                .ok_or_else(|| Error::NoDevice(iface.to_owned()))?;
+            let address = port
+                .lease
+                .clone()
+                .filter(|_| port.dot1x.is_none() || port.authorized)
+                .ok_or_else(|| Error::DhcpTimeout(iface.to_owned()))?;
+            port.ip = address.split('/').next().map(str::to_owned);
+            (address, dns, port.metered, port.lease_mtu)
+        };
+        on_event(DhcpEvent::Bound {
+            iface: iface.to_owned(),
+            address,
+            router: dns.first().cloned(),
+            dns,
+            metered,
+            mtu,
+        });
+        Ok(())
+    }
+}
+
+impl super::Dns for Fake {
+    fn apply(&self, servers: &[String]) -> io::Result<()> {
+        self.state.lock().unwrap().resolver = servers.to_vec();
+        Ok(())
+    }
+}
+
+impl super::Sharing for Fake {
+    fn share(&self, downstream: &str, subnet: &str, uplink: &str) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        state.port(uplink)?;
+        if state.sharing_refused {
+            return Err(Error::CommandFailed(
+                format!("iptables -t nat -A POSTROUTING -o {} -j MASQUERADE", uplink),
+                4,
+                "iptables: Resource temporarily unavailable.".to_owned(),
+            ));
+        }
+        state.shared = Some((downstream.to_owned(), subnet.to_owned(), uplink.to_owned()));
+        Ok(std::mem::replace(&mut state.forwarding, true))
+    }
+
+    fn unshare(&self, _downstream: &str, _subnet: &str, _uplink: &str, forwarding: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.shared = None;
+        state.forwarding = forwarding;
+    }
+}
+
+impl super::DhcpServer for Fake {
+    fn serve(
+        &self,
+        iface: &str,
+        address: &str,
+        runflag: Arc<AtomicBool>,
+        on_change: &mut dyn FnMut(Vec<HotspotClient>),
+    ) -> Result<()> {
+        let mut pool = LeasePool::new(address)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, address.to_owned()))?;
+        while runflag.load(Ordering::SeqCst) {
+            let joining = {
+                let mut state = self.state.lock().unwrap();
+                state.port(iface)?;
+                std::mem::take(&mut state.joining)
+            };
+            if !joining.is_empty() {
+                for (mac, hostname) in joining {
+                    let mac = mac.parse().map_err(|_| Error::NoHwAddr(mac.to_owned()))?;
+                    if let Some(ip) = pool.lease(mac, None, &hostname) {
+                        pool.bind(mac, ip, &hostname);
+                    }
+                }
+                on_change(pool.clients());
+            }
+            std::thread::sleep(time::Duration::from_millis(1));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+mod dhcpd;
+mod fake;
+mod sim;
+mod sniffer;
+mod system;
+
+use super::types::{
+    AccessPoint, DhcpEvent, HotspotClient, LinkMode, LinkStats, Station, Statistics,
+};
+use crate::error::Result;
+pub use fake::Fake;
+pub use sim::{Scenario, Simulation};
+pub use system::Iw;
+
+use std::sync::{atomic::AtomicBool, Arc};
+use std::{io, time};
+
+pub trait Link: Send + Sync {
+    fn interfaces(&self) -> Result<Vec<String>>;
+    fn up(&self, iface: &str);
+    fn down(&self, iface: &str);
+    fn is_plugged_in(&self, iface: &str) -> bool;
+    fn is_up(&self, iface: &str) -> bool;
+    fn flush(&self, iface: &str);
+    fn ip(&self, iface: &str) -> Option<String>;
+    fn has_global_ipv6(&self, iface: &str) -> bool;
+    // counters only, the rates are left at zero
+    fn statistics(&self, iface: &str) -> Option<Statistics>;
+    // lowercase colon separated
+    fn mac(&self, iface: &str) -> Option<String>;
+    // the address burnt into the device
+    fn permanent_mac(&self, iface: &str) -> Result<String>;
+    fn set_mac(&self, iface: &str, mac: &str) -> Result<()>;
+    // address in CIDR notation
+    fn set_address(&self, iface: &str, address: &str) -> Result<()>;
+    fn set_gateway(&self, iface: &str, gateway: &str) -> Result<()>;
+    fn mtu(&self, iface: &str) -> Option<u32>;
+    fn set_mtu(&self, iface: &str, mtu: u32) -> Result<()>;
+    fn set_link_mode(&self, iface: &str, mode: LinkMode) -> Result<()>;
+    fn set_wake_on_lan(&self, iface: &str, enabled: bool) -> Result<()>;
+    // MAC of a host on the link
+    fn neighbor(&self, iface: &str, ip: &str) -> Option<String>;
+}
+
+pub trait Scanner: Send + Sync {
+    fn scan(&self, iface: &str) -> Result<Vec<AccessPoint>>;
+    // the bss the interface is associated with
+    fn link(&self, iface: &str) -> Option<Station>;
+    fn link_stats(&self, iface: &str) -> Option<LinkStats>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Driver {
+    Nl80211,
+    // 802.1X on an ethernet port
+    Wired,
+}
+
+impl Driver {
+    pub fn name(self) -> &'static str {
+        match self {
+            Driver::Nl80211 => "nl80211",
+            Driver::Wired => "wired",
+        }
+    }
+}
+
+pub trait Supplicant: Send + Sync {
+    fn start(&self, iface: &str, driver: Driver, config: &str) -> Result<()>;
+    fn state(&self, iface: &str) -> Result<String>;
+    // move the association to another bss of the same network
+    fn roam(&self, iface: &str, bssid: &str) -> Result<()>;
+    fn stop(&self, iface: &str);
+
+    fn poll_interval(&self) -> time::Duration {
+        time::Duration::from_secs(1)
+    }
+}
+
+pub trait Dhcp: Send + Sync {
+    // runs the client until runflag is cleared
+    fn run(
+        &self,
+        iface: &str,
+        runflag: Arc<AtomicBool>,
+        on_event: &mut dyn FnMut(DhcpEvent),
+    ) -> Result<()>;
+}
+
+pub trait Dns: Send + Sync {
+    fn apply(&self, servers: &[String]) -> io::Result<()>;
+}
+
+// forwarding and NAT from a local network out through an uplink
+pub trait Sharing: Send + Sync {
+    // whether forwarding was already enabled
+    fn share(&self, downstream: &str, subnet: &str, uplink: &str) -> Result<bool>;
+    fn unshare(&self, downstream: &str, subnet: &str, uplink: &str, forwarding: bool);
+}
+
+pub trait DhcpServer: Send + Sync {
+    // serves the network of address until runflag is cleared
+    fn serve(
+        &self,
+        iface: &str,
+        address: &str,
+        runflag: Arc<AtomicBool>,
+        on_change: &mut dyn FnMut(Vec<HotspotClient>),
+    ) -> Result<()>;
+}
+
+#[derive(Clone)]
+pub struct Hardware {
+    pub link: Arc<dyn Link>,
+    pub scanner: Arc<dyn Scanner>,
+    pub supplicant: Arc<dyn Supplicant>,
+    pub dhcp: Arc<dyn Dhcp>,
+    pub dns: Arc<dyn Dns>,
+    pub sharing: Arc<dyn Sharing>,
+    pub dhcp_server: Arc<dyn DhcpServer>,
+}
+
+impl Hardware {
+    pub fn system() -> Self {
+        Hardware {
+            link: Arc::new(system::IpLink),
+            scanner: Arc::new(system::Iw),
+            supplicant: Arc::new(system::WpaSupplicant),
+            dhcp: Arc::new(system::SmoltcpDhcp),
+            dns: Arc::new(system::ResolvConf),
+            sharing: Arc::new(system::Iptables),
+            dhcp_server: Arc::new(system::UdpDhcpServer),
+        }
+    }
+
+    pub fn fake(fake: &Fake) -> Self {
+        Hardware {
+            link: Arc::new(fake.clone()),
+            scanner: Arc::new(fake.clone()),
+            supplicant: Arc::new(fake.clone()),
+            dhcp: Arc::new(fake.clone()),
+            dns: Arc::new(fake.clone()),
+            sharing: Arc::new(fake.clone()),
+            dhcp_server: Arc::new(fake.clone()),
+        }
+    }
+}
+
+impl Default for Hardware {
+    fn default() -> Self {
+        Hardware::system()
+    }
+}
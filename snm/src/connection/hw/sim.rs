@@ -0,0 +1,312 @@
+use super::super::types::{self, Security, Ssid};
+use super::{Fake, Hardware};
+use crate::error::{Error, Result};
+
+use std::sync::{Arc, Mutex};
+use std::{fs, io, thread, time};
+
+#[derive(Clone, Deserialize)]
+pub struct Port {
+    pub name: String,
+    #[serde(default = "Scenario::none")]
+    pub lease: Option<String>,
+    #[serde(default = "Scenario::no")]
+    pub plugged: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AccessPoint {
+    pub ssid: String,
+    // derived from the position in the scenario if not set
+    #[serde(default = "Scenario::none")]
+    pub bssid: Option<String>,
+    #[serde(default = "AccessPoint::default_frequency")]
+    pub frequency: u32,
+    #[serde(default = "AccessPoint::default_signal")]
+    pub signal: i32,
+    // open network if not set
+    #[serde(default = "Scenario::none")]
+    pub password: Option<String>,
+    #[serde(default = "Scenario::yes")]
+    pub present: bool,
+}
+
+impl AccessPoint {
+    fn default_signal() -> i32 {
+        -60
+    }
+
+    fn default_frequency() -> u32 {
+        2437
+    }
+
+    fn new(ssid: &str) -> Self {
+        AccessPoint {
+            ssid: ssid.to_owned(),
+            bssid: None,
+            frequency: Self::default_frequency(),
+            signal: Self::default_signal(),
+            password: None,
+            present: true,
+        }
+    }
+}
+
+// changes applied `at` seconds after the simulation started
+#[derive(Clone, Deserialize)]
+pub struct Event {
+    pub at: u64,
+    #[serde(default = "Scenario::none")]
+    pub ssid: Option<String>,
+    #[serde(default = "Scenario::none")]
+    pub signal: Option<i32>,
+    #[serde(default = "Scenario::none")]
+    pub password: Option<String>,
+    #[serde(default = "Scenario::none")]
+    pub present: Option<bool>,
+    #[serde(default = "Scenario::none")]
+    pub interface: Option<String>,
+    #[serde(default = "Scenario::none")]
+    pub plugged: Option<bool>,
+    #[serde(default = "Scenario::none")]
+    pub lease: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Scenario {
+    #[serde(default = "Vec::new")]
+    pub interface: Vec<Port>,
+    #[serde(default = "Vec::new")]
+    pub dns: Vec<String>,
+    #[serde(default = "Vec::new")]
+    pub ap: Vec<AccessPoint>,
+    #[serde(default = "Vec::new")]
+    pub event: Vec<Event>,
+}
+
+impl Scenario {
+    fn none<T>() -> Option<T> {
+        None
+    }
+
+    fn no() -> bool {
+        false
+    }
+
+    fn yes() -> bool {
+        true
+    }
+
+    pub fn parse(data: &str) -> Option<Self> {
+        toml::decode_str(data)
+    }
+}
+
+struct State {
+    aps: Vec<AccessPoint>,
+    events: Vec<Event>,
+    played: usize,
+}
+
+// emulates access points and wired ports on top of the in-memory hardware
+#[derive(Clone)]
+pub struct Simulation {
+    fake: Fake,
+    state: Arc<Mutex<State>>,
+}
+
+impl Simulation {
+    pub fn new(scenario: Scenario) -> Self {
+        let fake = Fake::new();
+        for port in scenario.interface.iter() {
+            fake.add_port(&port.name);
+            fake.plug(&port.name, port.plugged);
+            fake.set_lease(&port.name, port.lease.as_deref());
+        }
+        let dns: Vec<&str> = scenario.dns.iter().map(String::as_str).collect();
+        fake.set_dns(&dns);
+        let mut events = scenario.event;
+        events.sort_by_key(|event| event.at);
+        let simulation = Simulation {
+            fake,
+            state: Arc::new(Mutex::new(State {
+                aps: scenario.ap,
+                events,
+                played: 0,
+            })),
+        };
+        simulation.publish(&simulation.state.lock().unwrap().aps);
+        simulation
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let scenario = Scenario::parse(&data).ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("cannot parse scenario {}", path),
+            ))
+        })?;
+        Ok(Self::new(scenario))
+    }
+
+    pub fn hardware(&self) -> Hardware {
+        Hardware::fake(&self.fake)
+    }
+
+    pub fn fake(&self) -> &Fake {
+        &self.fake
+    }
+
+    // plays the events in real time
+    pub fn start(&self) {
+        let simulation = self.clone();
+        thread::spawn(move || {
+            let started = time::Instant::now();
+            while let Some(at) = simulation.next_event() {
+                let due = started + time::Duration::from_secs(at);
+                if let Some(wait) = due.checked_duration_since(time::Instant::now()) {
+                    thread::sleep(wait);
+                }
+                simulation.advance(at);
+            }
+        });
+    }
+
+    fn next_event(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state.events.get(state.played).map(|event| event.at)
+    }
+
+    // applies all events due by the given second of the scenario
+    pub fn advance(&self, secs: u64) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(event) = state.events.get(state.played).cloned() {
+            if event.at > secs {
+                break;
+            }
+            state.played += 1;
+            self.apply(&mut state.aps, &event);
+        }
+        self.publish(&state.aps);
+    }
+
+    fn apply(&self, aps: &mut Vec<AccessPoint>, event: &Event) {
+        if let Some(ref ssid) = event.ssid {
+            let index = match aps.iter().position(|ap| ap.ssid == *ssid) {
+                Some(index) => index,
+                None => {
+                    aps.push(AccessPoint::new(ssid));
+                    aps.len() - 1
+                }
+            };
+            let ap = &mut aps[index];
+            if let Some(signal) = event.signal {
+                ap.signal = signal;
+            }
+            if event.password.is_some() {
+                ap.password = event.password.clone();
+            }
+            if let Some(present) = event.present {
+                ap.present = present;
+            }
+            info!(ssid = ssid.as_str(); "simulation: {} at {} dBm, {}", ssid, ap.signal,
+                  if ap.present { "in range" } else { "gone" });
+        }
+        if let Some(ref iface) = event.interface {
+            if let Some(plugged) = event.plugged {
+                self.fake.plug(iface, plugged);
+                info!(interface = iface.as_str(); "simulation: {} plugged {}", iface, plugged);
+            }
+            if event.lease.is_some() {
+                self.fake.set_lease(iface, event.lease.as_deref());
+            }
+        }
+    }
+
+    fn publish(&self, aps: &[AccessPoint]) {
+        let access_points = aps
+            .iter()
+            .enumerate()
+            .filter(|(_, ap)| ap.present)
+            .map(|(i, ap)| types::AccessPoint {
+                bssid: ap
+                    .bssid
+                    .clone()
+                    .unwrap_or_else(|| format!("02:00:00:00:00:{:02x}", i)),
+                essid: Ssid::from(ap.ssid.as_str()),
+                frequency: ap.frequency,
+                signal: ap.signal,
+                last_seen: 0,
+                security: if ap.password.is_some() {
+                    Security::Wpa2
+                } else {
+                    Security::Open
+                },
+                capabilities: String::new(),
+            })
+            .collect();
+        self.fake.set_access_points(access_points);
+        for ap in aps {
+            self.fake
+                .set_password(ap.ssid.as_str(), ap.password.as_deref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIO: &str = r#"
+dns = ["192.168.50.1"]
+
+[[interface]]
+name = "wlan0"
+lease = "192.168.50.10/24"
+
+[[ap]]
+ssid = "home"
+signal = -50
+password = "secret"
+
+[[ap]]
+ssid = "cafe"
+
+[[event]]
+at = 30
+ssid = "home"
+signal = -90
+
+[[event]]
+at = 10
+ssid = "cafe"
+present = false
+"#;
+
+    #[test]
+    fn parses_and_plays_scenario() {
+        let scenario = Scenario::parse(SCENARIO).expect("valid scenario");
+        assert_eq!(
+            scenario.interface[0].lease.as_deref(),
+            Some("192.168.50.10/24")
+        );
+        assert_eq!(scenario.ap[1].signal, -60);
+        assert!(scenario.ap[1].password.is_none());
+
+        let simulation = Simulation::new(scenario);
+        assert_eq!(simulation.next_event(), Some(10));
+        simulation.advance(10);
+        assert_eq!(simulation.next_event(), Some(30));
+        simulation.advance(60);
+        assert_eq!(simulation.next_event(), None);
+        let state = simulation.state.lock().unwrap();
+        assert!(!state.aps[1].present);
+        assert_eq!(state.aps[0].signal, -90);
+    }
+
+    #[test]
+    fn rejects_ill_formed_scenario() {
+        assert!(Scenario::parse("[[ap]]\nssid = \"home\"\nsignal = \"strong\"\n").is_none());
+    }
+}
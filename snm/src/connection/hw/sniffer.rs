@@ -0,0 +1,280 @@
+use super::super::types::MIN_MTU;
+use smoltcp::phy::{Device, DeviceCapabilities, RxToken, TxToken};
+use smoltcp::time::Instant;
+use smoltcp::wire::{
+    DhcpPacket, EthernetFrame, EthernetProtocol, IpProtocol, Ipv4Packet, UdpPacket,
+    DHCP_SERVER_PORT,
+};
+use std::cell::Cell;
+use std::rc::Rc;
+
+const OPT_PAD: u8 = 0;
+const OPT_INTERFACE_MTU: u8 = 26;
+const OPT_VENDOR_SPECIFIC: u8 = 43;
+const OPT_PARAMETER_REQUEST_LIST: u8 = 55;
+const OPT_END: u8 = 255;
+// android tethering sends it in the vendor specific option
+const ANDROID_METERED: &[u8] = b"ANDROID_METERED";
+
+// what the last dhcp server reply said beyond the lease
+#[derive(Clone, Copy, Default)]
+pub struct ServerOptions {
+    pub metered: bool,
+    pub mtu: Option<u32>,
+}
+
+impl ServerOptions {
+    // None for frames that are not a dhcp server reply
+    pub fn parse(frame: &[u8]) -> Option<ServerOptions> {
+        let frame = EthernetFrame::new_checked(frame).ok()?;
+        if frame.ethertype() != EthernetProtocol::Ipv4 {
+            return None;
+        }
+        let packet = Ipv4Packet::new_checked(frame.payload()).ok()?;
+        if packet.protocol() != IpProtocol::Udp {
+            return None;
+        }
+        let datagram = UdpPacket::new_checked(packet.payload()).ok()?;
+        if datagram.src_port() != DHCP_SERVER_PORT {
+            return None;
+        }
+        let dhcp = DhcpPacket::new_checked(datagram.payload()).ok()?;
+        let mut options = dhcp.options().ok()?;
+        let mut result = ServerOptions::default();
+        while let Some((&kind, rest)) = options.split_first() {
+            if kind == OPT_END {
+                break;
+            }
+            if kind == OPT_PAD {
+                options = rest;
+                continue;
+            }
+            let (&length, rest) = rest.split_first()?;
+            let data = rest.get(..length as usize)?;
+            if kind == OPT_VENDOR_SPECIFIC {
+                result.metered = data
+                    .windows(ANDROID_METERED.len())
+                    .any(|window| window == ANDROID_METERED);
+            }
+            if kind == OPT_INTERFACE_MTU && length == 2 {
+                result.mtu = Some(u16::from_be_bytes([data[0], data[1]]) as u32)
+                    .filter(|mtu| *mtu >= MIN_MTU);
+            }
+            options = &rest[length as usize..];
+        }
+        Some(result)
+    }
+}
+
+// smoltcp asks for the mask, router and dns only, servers leave out the rest
+fn request_mtu(frame: &[u8]) -> Option<Vec<u8>> {
+    let ethernet = EthernetFrame::new_checked(frame).ok()?;
+    if ethernet.ethertype() != EthernetProtocol::Ipv4 {
+        return None;
+    }
+    let packet = Ipv4Packet::new_checked(ethernet.payload()).ok()?;
+    if packet.protocol() != IpProtocol::Udp {
+        return None;
+    }
+    let datagram = UdpPacket::new_checked(packet.payload()).ok()?;
+    if datagram.dst_port() != DHCP_SERVER_PORT {
+        return None;
+    }
+    let dhcp = DhcpPacket::new_checked(datagram.payload()).ok()?;
+    let options = dhcp.options().ok()?;
+    let ip_start = frame.len() - ethernet.payload().len();
+    let udp_start = ip_start + packet.header_len() as usize;
+    let udp_end = udp_start + datagram.len() as usize;
+    let mut at = udp_end - options.len();
+    while at < udp_end {
+        let kind = frame[at];
+        if kind == OPT_END {
+            break;
+        }
+        if kind == OPT_PAD {
+            at += 1;
+            continue;
+        }
+        let length = *frame.get(at + 1)? as usize;
+        let next = at + 2 + length;
+        if kind == OPT_PARAMETER_REQUEST_LIST {
+            let list = frame.get(at + 2..next)?;
+            if list.contains(&OPT_INTERFACE_MTU) || length == u8::MAX as usize {
+                return None;
+            }
+            let mut patched = frame[..next].to_vec();
+            patched.push(OPT_INTERFACE_MTU);
+            patched.extend_from_slice(&frame[next..]);
+            patched[at + 1] += 1;
+            let mut packet = Ipv4Packet::new_unchecked(&mut patched[ip_start..]);
+            packet.set_total_len(packet.total_len() + 1);
+            packet.fill_checksum();
+            let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+            let mut datagram = UdpPacket::new_unchecked(&mut patched[udp_start..]);
+            datagram.set_len(datagram.len() + 1);
+            datagram.fill_checksum(&src, &dst);
+            return Some(patched);
+        }
+        at = next;
+    }
+    None
+}
+
+// passes frames through and keeps the options of dhcp server replies
+pub struct Sniffer<D> {
+    inner: D,
+    seen: Rc<Cell<ServerOptions>>,
+}
+
+impl<D> Sniffer<D> {
+    pub fn new(inner: D) -> (Self, Rc<Cell<ServerOptions>>) {
+        let seen = Rc::new(Cell::new(ServerOptions::default()));
+        (
+            Sniffer {
+                inner,
+                seen: seen.clone(),
+            },
+            seen,
+        )
+    }
+}
+
+pub struct SniffedRx<R> {
+    inner: R,
+    seen: Rc<Cell<ServerOptions>>,
+}
+
+impl<R: RxToken> RxToken for SniffedRx<R> {
+    fn consume<T, F>(self, timestamp: Instant, f: F) -> smoltcp::Result<T>
+    where
+        F: FnOnce(&mut [u8]) -> smoltcp::Result<T>,
+    {
+        let seen = self.seen;
+        self.inner.consume(timestamp, |frame| {
+            if let Some(options) = ServerOptions::parse(frame) {
+                seen.set(options);
+            }
+            f(frame)
+        })
+    }
+}
+
+// asks dhcp servers for the interface mtu as well
+pub struct SniffedTx<T> {
+    inner: T,
+}
+
+impl<T: TxToken> TxToken for SniffedTx<T> {
+    fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> smoltcp::Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> smoltcp::Result<R>,
+    {
+        let mut frame = vec![0; len];
+        let result = f(&mut frame)?;
+        let frame = request_mtu(&frame).unwrap_or(frame);
+        self.inner.consume(timestamp, frame.len(), |buffer| {
+            buffer.copy_from_slice(&frame);
+            Ok(())
+        })?;
+        Ok(result)
+    }
+}
+
+impl<'a, D: Device<'a>> Device<'a> for Sniffer<D> {
+    type RxToken = SniffedRx<D::RxToken>;
+    type TxToken = SniffedTx<D::TxToken>;
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let seen = self.seen.clone();
+        self.inner
+            .receive()
+            .map(|(inner, tx)| (SniffedRx { inner, seen }, SniffedTx { inner: tx }))
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        self.inner.transmit().map(|inner| SniffedTx { inner })
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an ethernet/ipv4/udp dhcp frame carrying the given options
+    fn frame(op: u8, src_port: u8, dst_port: u8, options: &[u8]) -> Vec<u8> {
+        let mut dhcp = vec![0u8; 240];
+        dhcp[0] = op;
+        dhcp[236..240].copy_from_slice(&[99, 130, 83, 99]);
+        dhcp.extend_from_slice(options);
+        let mut udp = vec![0, src_port, 0, dst_port];
+        udp.extend_from_slice(&((8 + dhcp.len()) as u16).to_be_bytes());
+        udp.extend_from_slice(&[0, 0]);
+        udp.extend_from_slice(&dhcp);
+        let mut ip = vec![0x45, 0];
+        ip.extend_from_slice(&((20 + udp.len()) as u16).to_be_bytes());
+        ip.extend_from_slice(&[
+            0, 0, 0, 0, 64, 17, 0, 0, 192, 168, 43, 1, 255, 255, 255, 255,
+        ]);
+        ip.extend_from_slice(&udp);
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&[2, 0, 0, 0, 0, 1, 0x08, 0x00]);
+        frame.extend_from_slice(&ip);
+        frame
+    }
+
+    fn reply(options: &[u8]) -> Vec<u8> {
+        frame(2, 67, 68, options)
+    }
+
+    #[test]
+    fn detects_android_metered() {
+        let mut options = vec![53, 1, 5, 0, 43, 15];
+        options.extend_from_slice(b"ANDROID_METERED");
+        options.push(255);
+        assert!(ServerOptions::parse(&reply(&options)).unwrap().metered);
+        assert!(
+            !ServerOptions::parse(&reply(&[53, 1, 5, 255]))
+                .unwrap()
+                .metered
+        );
+        // truncated options are not trusted
+        assert!(ServerOptions::parse(&reply(&[43, 15, b'A'])).is_none());
+        assert!(ServerOptions::parse(&[0u8; 10]).is_none());
+    }
+
+    #[test]
+    fn reads_interface_mtu() {
+        let options = [53, 1, 5, 26, 2, 0x05, 0x78, 255];
+        assert_eq!(
+            ServerOptions::parse(&reply(&options)).unwrap().mtu,
+            Some(1400)
+        );
+        let options = [53, 1, 5, 26, 2, 0, 60, 255];
+        assert_eq!(ServerOptions::parse(&reply(&options)).unwrap().mtu, None);
+    }
+
+    #[test]
+    fn asks_for_interface_mtu() {
+        let request = frame(1, 68, 67, &[53, 1, 1, 55, 3, 1, 3, 6, 255]);
+        let patched = request_mtu(&request).expect("patched request");
+        assert_eq!(patched.len(), request.len() + 1);
+        let ethernet = EthernetFrame::new_checked(&patched[..]).unwrap();
+        let packet = Ipv4Packet::new_checked(ethernet.payload()).unwrap();
+        assert!(packet.verify_checksum());
+        let datagram = UdpPacket::new_checked(packet.payload()).unwrap();
+        let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+        assert!(datagram.verify_checksum(&src, &dst));
+        let dhcp = DhcpPacket::new_checked(datagram.payload()).unwrap();
+        assert_eq!(
+            dhcp.options().unwrap(),
+            &[53, 1, 1, 55, 4, 1, 3, 6, 26, 255]
+        );
+        // already asked, and replies are left alone
+        assert!(request_mtu(&patched).is_none());
+        assert!(request_mtu(&reply(&[53, 1, 5, 55, 1, 1, 255])).is_none());
+    }
+}
@@ -0,0 +1,1055 @@
+use super::super::parsers::{parse, Parsers};
+use super::super::support;
+use super::super::types::{
+    AccessPoint, DhcpEvent, HotspotClient, LinkMode, LinkStats, Security, Ssid, Station, Statistics,
+};
+use super::dhcpd::LeasePool;
+use super::sniffer::Sniffer;
+use crate::error::{Error, Result};
+use nix::libc;
+use smoltcp::phy::{wait, Device, Medium, RawSocket};
+use smoltcp::socket::{Dhcpv4Event, Dhcpv4Socket};
+use smoltcp::wire::{EthernetAddress, IpCidr, Ipv4Address, DHCP_CLIENT_PORT, DHCP_SERVER_PORT};
+use smoltcp::{iface, time::Instant};
+
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::os::unix::io::AsRawFd;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::{fs, io, path::Path, time};
+
+const IPV6_SCOPE_GLOBAL: &str = "00";
+const IFA_F_TENTATIVE: u32 = 0x40;
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const IP_FORWARD: &str = "/proc/sys/net/ipv4/ip_forward";
+const WPA_CTRL: &str = "/var/run/wpa";
+const NO_SIGNAL: i32 = -100;
+
+const ETHTOOL_GSET: u32 = 1;
+const ETHTOOL_SSET: u32 = 2;
+const ETHTOOL_GWOL: u32 = 5;
+const ETHTOOL_SWOL: u32 = 6;
+const ETHTOOL_GPERMADDR: u32 = 0x20;
+const MAX_ADDR_LEN: usize = 32;
+const NLMSG_HDRLEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+const RTA_HDRLEN: usize = 4;
+const AUTONEG_ENABLE: u8 = 1;
+const WAKE_MAGIC: u32 = 1 << 5;
+// speed, full duplex and the advertising bit of the legacy link modes
+const LINK_MODES: [(u32, bool, u32); 8] = [
+    (10, false, 1 << 0),
+    (10, true, 1 << 1),
+    (100, false, 1 << 2),
+    (100, true, 1 << 3),
+    (1000, false, 1 << 4),
+    (1000, true, 1 << 5),
+    (10000, true, 1 << 12),
+    (2500, true, 1 << 15),
+];
+
+pub struct IpLink;
+pub struct Iw;
+pub struct WpaSupplicant;
+pub struct SmoltcpDhcp;
+pub struct ResolvConf;
+pub struct Iptables;
+pub struct UdpDhcpServer;
+
+impl IpLink {
+    pub fn read_statistics(dir: &Path) -> Option<Statistics> {
+        let counter = |name: &str| -> Option<u64> {
+            fs::read_to_string(dir.join(name)).ok()?.trim().parse().ok()
+        };
+        Some(Statistics {
+            rx_bytes: counter("rx_bytes")?,
+            tx_bytes: counter("tx_bytes")?,
+            rx_packets: counter("rx_packets")?,
+            tx_packets: counter("tx_packets")?,
+            rx_errors: counter("rx_errors")?,
+            tx_errors: counter("tx_errors")?,
+            rx_dropped: counter("rx_dropped")?,
+            tx_dropped: counter("tx_dropped")?,
+            ..Statistics::default()
+        })
+    }
+}
+
+impl super::Link for IpLink {
+    fn interfaces(&self) -> Result<Vec<String>> {
+        let mut result = vec![];
+        for entry in fs::read_dir(Path::new("/sys/class/net"))?.flatten() {
+            if let Some(name) = entry.file_name().to_str() {
+                result.push(name.to_owned());
+            }
+        }
+        Ok(result)
+    }
+
+    fn up(&self, iface: &str) {
+        support::run(&format!("ip l set {} up", iface), false).unwrap_or_default();
+    }
+
+    fn down(&self, iface: &str) {
+        support::run(&format!("ip l set {} down", iface), false).unwrap_or_default();
+    }
+
+    fn is_plugged_in(&self, iface: &str) -> bool {
+        let filename = format!("/sys/class/net/{}/carrier", iface);
+        fs::read_to_string(&filename).is_ok_and(|value| value == "1\n")
+    }
+
+    fn is_up(&self, iface: &str) -> bool {
+        let filename = format!("/sys/class/net/{}/operstate", iface);
+        fs::read_to_string(&filename).is_ok_and(|value| value == "up\n")
+    }
+
+    fn flush(&self, iface: &str) {
+        support::run(&format!("ip addr flush dev {}", iface), false).unwrap_or_default();
+    }
+
+    fn ip(&self, iface: &str) -> Option<String> {
+        let ok: bool;
+        let ifreq = ifreq_ip::new(iface);
+        unsafe {
+            let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+            ok = libc::ioctl(fd, libc::SIOCGIFADDR, &ifreq) != -1;
+            libc::close(fd);
+        }
+        if ok {
+            let addr = ifreq.ifr_addr.sin_addr;
+            Some(format!("{}.{}.{}.{}", addr[0], addr[1], addr[2], addr[3]))
+        } else {
+            None
+        }
+    }
+
+    fn has_global_ipv6(&self, iface: &str) -> bool {
+        if let Ok(table) = fs::read_to_string("/proc/net/if_inet6") {
+            return table.lines().any(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                fields.len() == 6
+                    && fields[5] == iface
+                    && fields[3] == IPV6_SCOPE_GLOBAL
+                    && u32::from_str_radix(fields[4], 16)
+                        .is_ok_and(|flags| flags & IFA_F_TENTATIVE == 0)
+            });
+        }
+        false
+    }
+
+    fn statistics(&self, iface: &str) -> Option<Statistics> {
+        Self::read_statistics(&Path::new("/sys/class/net").join(iface).join("statistics"))
+    }
+
+    fn mac(&self, iface: &str) -> Option<String> {
+        let filename = format!("/sys/class/net/{}/address", iface);
+        fs::read_to_string(&filename)
+            .ok()
+            .map(|address| address.trim().to_ascii_lowercase())
+            .filter(|address| !address.is_empty())
+    }
+
+    fn permanent_mac(&self, iface: &str) -> Result<String> {
+        let mut address = ethtool_perm_addr {
+            cmd: ETHTOOL_GPERMADDR,
+            size: MAX_ADDR_LEN as u32,
+            ..ethtool_perm_addr::default()
+        };
+        ethtool(iface, &mut address)?;
+        let mac = &address.data[..(address.size as usize).min(MAX_ADDR_LEN)];
+        // virtual devices report none or zeroes
+        if mac.len() != 6 || mac.iter().all(|byte| *byte == 0) {
+            return Err(Error::NoHwAddr(iface.to_owned()));
+        }
+        Ok(mac
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(":"))
+    }
+
+    fn set_mac(&self, iface: &str, mac: &str) -> Result<()> {
+        let address = parse_mac(mac).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("bad address {}", mac))
+        })?;
+        let name = std::ffi::CString::new(iface).map_err(|_| Error::NoDevice(iface.to_owned()))?;
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index == 0 {
+            return Err(Error::NoDevice(iface.to_owned()));
+        }
+        // most drivers take a new address only while the link is down
+        let up = libc::IFF_UP as u32;
+        set_link(newlink_request(index, 0, up, None))?;
+        let changed = set_link(newlink_request(index, 0, 0, Some(&address)));
+        set_link(newlink_request(index, up, up, None))?;
+        changed.map_err(|e| match e.raw_os_error() {
+            Some(libc::EOPNOTSUPP) | Some(libc::EADDRNOTAVAIL) | Some(libc::EBUSY) => {
+                Error::NotAllowed(format!("changing the address of {}", iface))
+            }
+            _ => e.into(),
+        })
+    }
+
+    fn set_address(&self, iface: &str, address: &str) -> Result<()> {
+        support::run(&format!("ip addr replace {} dev {}", address, iface), false).map(|_| ())
+    }
+
+    fn set_gateway(&self, iface: &str, gateway: &str) -> Result<()> {
+        support::run(
+            &format!("ip route replace default via {} dev {}", gateway, iface),
+            false,
+        )
+        .map(|_| ())
+    }
+
+    fn mtu(&self, iface: &str) -> Option<u32> {
+        let filename = format!("/sys/class/net/{}/mtu", iface);
+        fs::read_to_string(&filename).ok()?.trim().parse().ok()
+    }
+
+    fn set_mtu(&self, iface: &str, mtu: u32) -> Result<()> {
+        support::run(&format!("ip l set {} mtu {}", iface, mtu), false).map(|_| ())
+    }
+
+    fn set_link_mode(&self, iface: &str, mode: LinkMode) -> Result<()> {
+        let mut settings = ethtool_cmd {
+            cmd: ETHTOOL_GSET,
+            ..ethtool_cmd::default()
+        };
+        ethtool(iface, &mut settings)?;
+        if let Some(autoneg) = mode.autoneg {
+            settings.autoneg = autoneg as u8;
+        }
+        if settings.autoneg == AUTONEG_ENABLE {
+            // negotiation picks the speed, so only offer the wanted modes
+            if mode.speed.is_some() || mode.full_duplex.is_some() {
+                let wanted = LINK_MODES
+                    .iter()
+                    .filter(|(speed, full, _)| {
+                        mode.speed.is_none_or(|wanted| wanted == *speed)
+                            && mode.full_duplex.is_none_or(|wanted| wanted == *full)
+                    })
+                    .fold(0, |bits, (_, _, bit)| bits | bit);
+                if settings.supported & wanted == 0 {
+                    return Err(Error::NotAllowed(format!("this link mode on {}", iface)));
+                }
+                settings.advertising = settings.supported & wanted;
+            }
+        } else {
+            if let Some(speed) = mode.speed {
+                settings.speed = speed as u16;
+                settings.speed_hi = (speed >> 16) as u16;
+            }
+            if let Some(full_duplex) = mode.full_duplex {
+                settings.duplex = full_duplex as u8;
+            }
+        }
+        settings.cmd = ETHTOOL_SSET;
+        ethtool(iface, &mut settings)
+    }
+
+    fn set_wake_on_lan(&self, iface: &str, enabled: bool) -> Result<()> {
+        let mut wol = ethtool_wolinfo {
+            cmd: ETHTOOL_GWOL,
+            ..ethtool_wolinfo::default()
+        };
+        ethtool(iface, &mut wol)?;
+        if enabled && wol.supported & WAKE_MAGIC == 0 {
+            return Err(Error::NotAllowed(format!("wake-on-lan on {}", iface)));
+        }
+        wol.cmd = ETHTOOL_SWOL;
+        wol.wolopts = if enabled { WAKE_MAGIC } else { 0 };
+        ethtool(iface, &mut wol)
+    }
+
+    fn neighbor(&self, iface: &str, ip: &str) -> Option<String> {
+        // the dhcp client talks over a raw socket, so the kernel may not know the host yet
+        support::run(&format!("ping -c 1 -W 1 -I {} {}", iface, ip), false).ok();
+        let output = support::run(&format!("ip neigh show {} dev {}", ip, iface), false).ok()?;
+        output
+            .split_whitespace()
+            .skip_while(|word| *word != "lladdr")
+            .nth(1)
+            .map(str::to_ascii_lowercase)
+    }
+}
+
+impl Iw {
+    fn security(chunk: &str, privacy: bool) -> Security {
+        if !privacy {
+            Security::Open
+        } else if chunk.contains("\tRSN:") {
+            if chunk.contains("SAE") {
+                Security::Wpa3
+            } else {
+                Security::Wpa2
+            }
+        } else if chunk.contains("\tWPA:") {
+            Security::Wpa
+        } else {
+            Security::Wep
+        }
+    }
+
+    pub fn parse_link(output: &str) -> Option<Station> {
+        let ecaps = parse(Parsers::NetworkEssid, output)?;
+        let essid = support::parse_essid(&ecaps[1]).ok()?;
+        Some(Station {
+            essid: Ssid::new(essid),
+            bssid: parse(Parsers::ConnectedTo, output)
+                .map_or_else(String::new, |caps| caps[1].to_lowercase()),
+            frequency: parse(Parsers::Frequency, output)
+                .and_then(|caps| caps[1].parse().ok())
+                .unwrap_or_default(),
+            signal: parse(Parsers::NetworkQuality, output).and_then(|caps| caps[1].parse().ok()),
+        })
+    }
+
+    pub fn parse_station(output: &str) -> Option<LinkStats> {
+        let bitrate = |parser| {
+            parse(parser, output)
+                .and_then(|caps| caps[1].parse::<f64>().ok())
+                .map_or(0, |mbits| (mbits * 1000.0).round() as u32)
+        };
+        Some(LinkStats {
+            signal: parse(Parsers::NetworkQuality, output)?[1].parse().ok()?,
+            tx_bitrate: bitrate(Parsers::TxBitrate),
+            rx_bitrate: bitrate(Parsers::RxBitrate),
+            connected_time: parse(Parsers::ConnectedTime, output)
+                .and_then(|caps| caps[1].parse().ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    pub fn parse_scan(iface: &str, output: &str) -> Vec<AccessPoint> {
+        let mut aps = vec![];
+        // every chunk holds the fields of one bss and ends with the address of the next
+        let mut bssid = String::new();
+        for chunk in output.split(&format!("(on {})", iface)) {
+            let mut ap = AccessPoint {
+                bssid,
+                essid: Ssid::default(),
+                frequency: 0,
+                signal: NO_SIGNAL,
+                last_seen: 0,
+                security: Security::Wep,
+                capabilities: String::new(),
+            };
+            bssid = parse(Parsers::Bssid, chunk)
+                .map_or_else(String::new, |caps| caps[1].to_lowercase());
+
+            if let Some(ref caps) = parse(Parsers::NetworkQuality, chunk) {
+                if let Ok(dbm) = caps[1].parse::<i32>() {
+                    ap.signal = dbm;
+                }
+            }
+
+            if let Some(ref caps) = parse(Parsers::NetworkEssid, chunk) {
+                match support::parse_essid(&caps[1]) {
+                    Ok(parsed) => ap.essid = Ssid::new(parsed),
+                    Err(e) => warn!("Skipping scan result: {}", e),
+                }
+            }
+
+            if let Some(ref caps) = parse(Parsers::NetworkEnc, chunk) {
+                ap.capabilities = caps[1].trim().to_owned();
+                ap.security = Self::security(chunk, ap.capabilities.contains("Privacy"));
+            }
+
+            if let Some(ref caps) = parse(Parsers::Frequency, chunk) {
+                ap.frequency = caps[1].parse().unwrap_or_default();
+            }
+
+            if let Some(ref caps) = parse(Parsers::LastSeen, chunk) {
+                ap.last_seen = caps[1].parse().unwrap_or_default();
+            }
+
+            if !ap.essid.is_empty() {
+                aps.push(ap);
+            }
+        }
+        aps
+    }
+}
+
+impl super::Scanner for Iw {
+    fn scan(&self, iface: &str) -> Result<Vec<AccessPoint>> {
+        let output = support::run(&format!("iw dev {} scan", iface), false)?;
+        Ok(Self::parse_scan(iface, &output))
+    }
+
+    fn link(&self, iface: &str) -> Option<Station> {
+        let output = support::run(&format!("iw dev {} link", iface), false).unwrap_or_default();
+        Self::parse_link(&output)
+    }
+
+    fn link_stats(&self, iface: &str) -> Option<LinkStats> {
+        let output =
+            support::run(&format!("iw dev {} station dump", iface), false).unwrap_or_default();
+        Self::parse_station(&output)
+    }
+}
+
+impl super::Supplicant for WpaSupplicant {
+    fn start(&self, iface: &str, driver: super::Driver, config: &str) -> Result<()> {
+        support::run(
+            &format!(
+                "wpa_supplicant -B -i{} -c{} -D{} -C{}",
+                iface,
+                config,
+                driver.name(),
+                WPA_CTRL
+            ),
+            false,
+        )
+        .map(|_| ())
+    }
+
+    fn state(&self, iface: &str) -> Result<String> {
+        let output = support::run(
+            &format!("wpa_cli -i {} -p {} status", iface, WPA_CTRL),
+            false,
+        )?;
+        Ok(parse(Parsers::WpaState, &output)
+            .map(|caps| caps[1].to_owned())
+            .unwrap_or_default())
+    }
+
+    fn roam(&self, iface: &str, bssid: &str) -> Result<()> {
+        support::run(
+            &format!("wpa_cli -i {} -p {} roam {}", iface, WPA_CTRL, bssid),
+            false,
+        )
+        .map(|_| ())
+    }
+
+    fn stop(&self, iface: &str) {
+        support::run(
+            &format!("wpa_cli -i {} -p {} disconnect", iface, WPA_CTRL),
+            false,
+        )
+        .unwrap_or_default();
+        support::run(
+            &format!("wpa_cli -i {} -p {} terminate", iface, WPA_CTRL),
+            false,
+        )
+        .unwrap_or_default();
+    }
+}
+
+impl SmoltcpDhcp {
+    fn detect_mac(ifname: &str) -> Result<EthernetAddress> {
+        use nix::{ifaddrs::getifaddrs, sys::socket::SockAddr};
+        let no_hw_addr = || Error::NoHwAddr(ifname.to_owned());
+        let ifaces = getifaddrs().map_err(|_| no_hw_addr())?;
+
+        for iface in ifaces {
+            if iface.interface_name == ifname {
+                if let Some(SockAddr::Link(link)) = iface.address {
+                    return Ok(EthernetAddress(link.addr()));
+                }
+            }
+        }
+        Err(no_hw_addr())
+    }
+}
+
+impl super::Dhcp for SmoltcpDhcp {
+    fn run(
+        &self,
+        ifname: &str,
+        runflag: Arc<AtomicBool>,
+        on_event: &mut dyn FnMut(DhcpEvent),
+    ) -> Result<()> {
+        let mac = Self::detect_mac(ifname)?;
+        let device = RawSocket::new(ifname, Medium::Ethernet).map_err(|e| {
+            if e.raw_os_error() == Some(libc::ENODEV) {
+                Error::NoDevice(ifname.to_owned())
+            } else {
+                Error::Io(e)
+            }
+        })?;
+        let fd = device.as_raw_fd();
+        let (device, server_options) = Sniffer::new(device);
+        let neighbor_cache = iface::NeighborCache::new(BTreeMap::new());
+        let ip_addrs = [IpCidr::new(Ipv4Address::UNSPECIFIED.into(), 0)];
+        let mut routes_storage = [None; 1];
+        let routes = iface::Routes::new(&mut routes_storage[..]);
+        let medium = device.capabilities().medium;
+        let mut builder = iface::InterfaceBuilder::new(device, vec![])
+            .ip_addrs(ip_addrs)
+            .routes(routes);
+        if medium == Medium::Ethernet {
+            builder = builder
+                .hardware_addr(mac.into())
+                .neighbor_cache(neighbor_cache);
+        }
+        let mut iface = builder.finalize();
+        let dhcp_handle = iface.add_socket(Dhcpv4Socket::new()); //check
+        let mut ip = String::new();
+        while runflag.load(Ordering::SeqCst) {
+            let timestamp = Instant::now();
+            if iface.poll(timestamp).is_err() {
+                return Ok(());
+            }
+
+            let event = iface.get_socket::<Dhcpv4Socket>(dhcp_handle).poll();
+            match event {
+                None => {}
+                Some(Dhcpv4Event::Configured(config)) => {
+                    if let Some(router) = config.router {
+                        support::run(
+                            &format!("ip route add {} dev {}", config.address.network(), ifname),
+                            false,
+                        )
+                        .unwrap_or_default();
+                        support::run(
+                            &format!("ip route add default via {} dev {}", router, ifname),
+                            false,
+                        )
+                        .unwrap_or_default();
+                    }
+
+                    let new_ip = config.address.address().to_string();
+                    if new_ip != "0.0.0.0" && ip != new_ip {
+                        ip = new_ip;
+                        support::run(&format!("ip addr flush dev {}", ifname), false)
+                            .unwrap_or_default();
+                        support::run(&format!("ip addr add {} dev {}", ip, ifname), false)
+                            .unwrap_or_default();
+                    }
+                    on_event(DhcpEvent::Bound {
+                        iface: ifname.to_owned(),
+                        address: config.address.to_string(),
+                        router: config.router.map(|router| router.to_string()),
+                        dns: config
+                            .dns_servers
+                            .iter()
+                            .filter_map(|s| s.map(|s| s.to_string()))
+                            .collect(),
+                        metered: server_options.get().metered,
+                        mtu: server_options.get().mtu,
+                    });
+                }
+                Some(Dhcpv4Event::Deconfigured) => {
+                    ip.clear();
+                    support::run(&format!("ip addr flush dev {}", ifname), false)
+                        .unwrap_or_default();
+                    support::run(&format!("ip route flush dev {}", ifname), false)
+                        .unwrap_or_default();
+                    on_event(DhcpEvent::Lost {
+                        iface: ifname.to_owned(),
+                    });
+                }
+            }
+            wait(fd, iface.poll_delay(timestamp))?;
+        }
+        Ok(())
+    }
+}
+
+impl super::Dns for ResolvConf {
+    fn apply(&self, servers: &[String]) -> io::Result<()> {
+        use std::io::Write;
+        let mut resolv = fs::File::create(RESOLV_CONF)?;
+        for dns_server in servers {
+            writeln!(resolv, "nameserver {}\n", dns_server)?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolvConf {
+    // the upstream servers hotspot clients can be pointed at
+    fn nameservers() -> Vec<Ipv4Address> {
+        fs::read_to_string(RESOLV_CONF)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.strip_prefix("nameserver"))
+            .filter_map(|server| server.trim().parse::<Ipv4Addr>().ok())
+            .filter(|server| !server.is_loopback())
+            .map(|server| Ipv4Address::from_bytes(&server.octets()))
+            .collect()
+    }
+}
+
+impl Iptables {
+    fn rules(downstream: &str, subnet: &str, uplink: &str) -> [(&'static str, String); 3] {
+        [
+            (
+                "nat",
+                format!("POSTROUTING -s {} -o {} -j MASQUERADE", subnet, uplink),
+            ),
+            (
+                "filter",
+                format!("FORWARD -i {} -o {} -j ACCEPT", downstream, uplink),
+            ),
+            (
+                "filter",
+                format!(
+                    "FORWARD -i {} -o {} -m conntrack --ctstate RELATED,ESTABLISHED -j ACCEPT",
+                    uplink, downstream
+                ),
+            ),
+        ]
+    }
+
+    fn remove(rules: &[(&str, String)], forwarding: bool) {
+        for (table, rule) in rules {
+            support::run(&format!("iptables -t {} -D {}", table, rule), false).unwrap_or_default();
+        }
+        if !forwarding {
+            fs::write(IP_FORWARD, "0").unwrap_or_default();
+        }
+    }
+}
+
+impl super::Sharing for Iptables {
+    fn share(&self, downstream: &str, subnet: &str, uplink: &str) -> Result<bool> {
+        let forwarding = fs::read_to_string(IP_FORWARD)?.trim() == "1";
+        if !forwarding {
+            fs::write(IP_FORWARD, "1")?;
+        }
+        let rules = Self::rules(downstream, subnet, uplink);
+        for (added, (table, rule)) in rules.iter().enumerate() {
+            if let Err(e) = support::run_checked(&format!("iptables -t {} -A {}", table, rule)) {
+                // a half shared uplink is worse than none
+                Self::remove(&rules[..added], forwarding);
+                return Err(e);
+            }
+        }
+        Ok(forwarding)
+    }
+
+    fn unshare(&self, downstream: &str, subnet: &str, uplink: &str, forwarding: bool) {
+        Self::remove(&Self::rules(downstream, subnet, uplink), forwarding);
+    }
+}
+
+impl super::DhcpServer for UdpDhcpServer {
+    fn serve(
+        &self,
+        iface: &str,
+        address: &str,
+        runflag: Arc<AtomicBool>,
+        on_change: &mut dyn FnMut(Vec<HotspotClient>),
+    ) -> Result<()> {
+        let mut pool = LeasePool::new(address)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, address.to_owned()))?;
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DHCP_SERVER_PORT))?;
+        bind_to_device(&socket, iface)?;
+        socket.set_broadcast(true)?;
+        // wake up now and then to notice runflag
+        socket.set_read_timeout(Some(time::Duration::from_millis(500)))?;
+        let dns = ResolvConf::nameservers();
+        let mut buffer = [0; 1500];
+        let mut clients = vec![];
+        while runflag.load(Ordering::SeqCst) {
+            let len = match socket.recv_from(&mut buffer) {
+                Ok((len, _)) => len,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::Interrupted
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(reply) = pool.answer(&buffer[..len], &dns) {
+                socket.send_to(&reply, (Ipv4Addr::BROADCAST, DHCP_CLIENT_PORT))?;
+            }
+            let current = pool.clients();
+            if current != clients {
+                clients = current;
+                on_change(clients.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+// clients have no address yet, so replies must leave through the right port
+fn bind_to_device(socket: &UdpSocket, iface: &str) -> Result<()> {
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            iface.as_ptr() as *const libc::c_void,
+            iface.len() as libc::socklen_t,
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[repr(C)]
+struct sockaddr_in {
+    pub sin_family: libc::sa_family_t,
+    pub sin_port: libc::in_port_t,
+    pub sin_addr: [u8; 4],
+    pub sin_zero: [u8; 8],
+}
+
+#[repr(C)]
+struct ifreq_ip {
+    ifr_name: [libc::c_uchar; libc::IF_NAMESIZE],
+    pub ifr_addr: sockaddr_in,
+}
+
+impl ifreq_ip {
+    fn new(ifname: &str) -> Self {
+        let mut ifr_name = [0; libc::IF_NAMESIZE];
+        ifr_name[..ifname.len()].clone_from_slice(ifname.as_bytes());
+        ifreq_ip {
+            ifr_name,
+            ifr_addr: sockaddr_in {
+                sin_family: libc::AF_INET as libc::sa_family_t,
+                sin_port: 0,
+                sin_addr: [0xff; 4],
+                sin_zero: [0; 8],
+            },
+        }
+    }
+}
+
+#[repr(C)]
+struct ifreq_data {
+    ifr_name: [libc::c_uchar; libc::IF_NAMESIZE],
+    ifr_data: *mut libc::c_void,
+    // rest of the request union
+    ifr_pad: [u8; 16],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ethtool_cmd {
+    cmd: u32,
+    supported: u32,
+    advertising: u32,
+    speed: u16,
+    duplex: u8,
+    port: u8,
+    phy_address: u8,
+    transceiver: u8,
+    autoneg: u8,
+    mdio_support: u8,
+    maxtxpkt: u32,
+    maxrxpkt: u32,
+    speed_hi: u16,
+    eth_tp_mdix: u8,
+    eth_tp_mdix_ctrl: u8,
+    lp_advertising: u32,
+    reserved: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ethtool_wolinfo {
+    cmd: u32,
+    supported: u32,
+    wolopts: u32,
+    sopass: [u8; 6],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ethtool_perm_addr {
+    cmd: u32,
+    size: u32,
+    data: [u8; MAX_ADDR_LEN],
+}
+
+fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let mut address = [0; 6];
+    let mut parts = mac.split(':');
+    for byte in address.iter_mut() {
+        *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+    }
+    parts.next().is_none().then_some(address)
+}
+
+// an RTM_NEWLINK message changing the flags in `change` and maybe the address
+fn newlink_request(index: u32, flags: u32, change: u32, address: Option<&[u8; 6]>) -> Vec<u8> {
+    let mut message = Vec::with_capacity(NLMSG_HDRLEN + IFINFOMSG_LEN + 12);
+    // nlmsghdr, its length is filled in at the end
+    message.extend_from_slice(&0u32.to_ne_bytes());
+    message.extend_from_slice(&libc::RTM_NEWLINK.to_ne_bytes());
+    message.extend_from_slice(&((libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16).to_ne_bytes());
+    message.extend_from_slice(&1u32.to_ne_bytes());
+    message.extend_from_slice(&0u32.to_ne_bytes());
+    // ifinfomsg
+    message.extend_from_slice(&[libc::AF_UNSPEC as u8, 0, 0, 0]);
+    message.extend_from_slice(&(index as i32).to_ne_bytes());
+    message.extend_from_slice(&flags.to_ne_bytes());
+    message.extend_from_slice(&change.to_ne_bytes());
+    if let Some(address) = address {
+        let len = (RTA_HDRLEN + address.len()) as u16;
+        message.extend_from_slice(&len.to_ne_bytes());
+        message.extend_from_slice(&libc::IFLA_ADDRESS.to_ne_bytes());
+        message.extend_from_slice(address);
+        message.resize((message.len() + 3) & !3, 0);
+    }
+    let len = message.len() as u32;
+    message[..4].copy_from_slice(&len.to_ne_bytes());
+    message
+}
+
+// sends a request to the kernel and returns the error its ack carries
+fn set_link(request: Vec<u8>) -> io::Result<()> {
+    let mut reply = [0u8; 1024];
+    let received;
+    unsafe {
+        let fd = libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        );
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut kernel: libc::sockaddr_nl = std::mem::zeroed();
+        kernel.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        let sent = libc::sendto(
+            fd,
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+            &kernel as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        );
+        received = if sent == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            match libc::recv(fd, reply.as_mut_ptr() as *mut libc::c_void, reply.len(), 0) {
+                -1 => Err(io::Error::last_os_error()),
+                len => Ok(len as usize),
+            }
+        };
+        libc::close(fd);
+    }
+    let reply = &reply[..received?];
+    let kind = reply
+        .get(4..6)
+        .map(|kind| u16::from_ne_bytes([kind[0], kind[1]]));
+    let error = reply
+        .get(NLMSG_HDRLEN..NLMSG_HDRLEN + 4)
+        .map(|error| i32::from_ne_bytes([error[0], error[1], error[2], error[3]]));
+    match (kind, error) {
+        (Some(kind), Some(0)) if kind == libc::NLMSG_ERROR as u16 => Ok(()),
+        (Some(kind), Some(error)) if kind == libc::NLMSG_ERROR as u16 => {
+            Err(io::Error::from_raw_os_error(-error))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected netlink reply",
+        )),
+    }
+}
+
+// the request is read and filled in place, its cmd field says which one it is
+fn ethtool<T>(iface: &str, request: &mut T) -> Result<()> {
+    let mut ifr_name = [0; libc::IF_NAMESIZE];
+    ifr_name[..iface.len()].clone_from_slice(iface.as_bytes());
+    let ifreq = ifreq_data {
+        ifr_name,
+        ifr_data: request as *mut T as *mut libc::c_void,
+        ifr_pad: [0; 16],
+    };
+    let result;
+    unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+        if fd == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        result = if libc::ioctl(fd, libc::SIOCETHTOOL, &ifreq) == -1 {
+            Err(io::Error::last_os_error().into())
+        } else {
+            Ok(())
+        };
+        libc::close(fd);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::types::Band;
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+
+    fn scan(output: &str) -> Vec<(String, u32, bool)> {
+        Iw::parse_scan("wlan0", output)
+            .into_iter()
+            .map(|ap| {
+                let enc = ap.security != Security::Open;
+                (ap.essid.to_string(), ap.quality(), enc)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_link() {
+        let station = Iw::parse_link(include_str!("../../../fuzz/corpus/parsers/link.txt"))
+            .expect("associated");
+        assert_eq!(station.essid, "home");
+        assert_eq!(station.bssid, "64:66:b3:5a:1c:20");
+        assert_eq!(station.band(), Band::Ghz2);
+        assert_eq!(station.quality(), support::dbm2perc(-44));
+        assert!(Iw::parse_link("Not connected.\n").is_none());
+    }
+
+    #[test]
+    fn reads_sysfs_statistics() {
+        let dir = std::env::temp_dir().join(format!("snm-statistics-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (index, name) in [
+            "rx_bytes",
+            "tx_bytes",
+            "rx_packets",
+            "tx_packets",
+            "rx_errors",
+            "tx_errors",
+            "rx_dropped",
+        ]
+        .iter()
+        .enumerate()
+        {
+            fs::write(dir.join(name), format!("{}\n", index + 1)).unwrap();
+        }
+        assert!(IpLink::read_statistics(&dir).is_none());
+        fs::write(dir.join("tx_dropped"), "8\n").unwrap();
+        let stats = IpLink::read_statistics(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap_or_default();
+        assert_eq!((stats.rx_bytes, stats.tx_dropped), (1, 8));
+    }
+
+    #[test]
+    fn parses_station() {
+        let stats = Iw::parse_station(include_str!("../../../fuzz/corpus/parsers/station.txt"))
+            .expect("associated");
+        assert_eq!(
+            stats,
+            LinkStats {
+                signal: -47,
+                tx_bitrate: 65000,
+                rx_bitrate: 72200,
+                connected_time: 3605,
+            }
+        );
+        assert!(Iw::parse_station("").is_none());
+    }
+
+    #[test]
+    fn parses_bss_details() {
+        let aps = Iw::parse_scan(
+            "wlan0",
+            include_str!("../../../fuzz/corpus/parse_scan/home.txt"),
+        );
+        let details: Vec<_> = aps
+            .iter()
+            .map(|ap| {
+                (
+                    ap.bssid.as_str(),
+                    ap.frequency,
+                    ap.channel(),
+                    ap.security,
+                    ap.last_seen,
+                )
+            })
+            .collect();
+        assert_eq!(
+            details,
+            vec![
+                ("64:66:b3:5a:1c:20", 2437, 6, Security::Wpa2, 12),
+                ("c8:3a:35:0e:44:f1", 2462, 11, Security::Open, 632),
+                ("0a:18:d6:91:7e:02", 5180, 36, Security::Wep, 494),
+            ]
+        );
+        assert_eq!(aps[2].band(), Band::Ghz5);
+        assert_eq!(aps[0].capabilities, "ESS Privacy ShortSlotTime (0x0411)");
+    }
+
+    #[test]
+    fn parses_corpus() {
+        assert_eq!(
+            scan(include_str!("../../../fuzz/corpus/parse_scan/home.txt")),
+            vec![
+                ("home".to_owned(), support::dbm2perc(-43), true),
+                ("Cafe Guest".to_owned(), support::dbm2perc(-81), false),
+                ("Дом".to_owned(), support::dbm2perc(-67), true),
+            ]
+        );
+        let hidden = scan(include_str!("../../../fuzz/corpus/parse_scan/hidden.txt"));
+        let essids: Vec<&str> = hidden.iter().map(|(essid, ..)| essid.as_str()).collect();
+        assert_eq!(
+            essids,
+            vec![
+                "\0\0\0\0\0\0",
+                " leading and trailing ",
+                "back\\slash",
+                "caf\u{fffd}"
+            ]
+        );
+        assert_eq!(
+            scan(include_str!(
+                "../../../fuzz/corpus/parse_scan/truncated.txt"
+            )),
+            vec![("no signal value".to_owned(), 1, false)]
+        );
+    }
+
+    #[test]
+    fn newlink_carries_the_address() {
+        assert_eq!(parse_mac("02:00:00:00:01:0A"), Some([2, 0, 0, 0, 1, 10]));
+        assert_eq!(parse_mac("02:00:00:00:01"), None);
+        assert_eq!(parse_mac("02:00:00:00:01:0a:ff"), None);
+
+        let down = newlink_request(3, 0, libc::IFF_UP as u32, None);
+        assert_eq!(down.len(), NLMSG_HDRLEN + IFINFOMSG_LEN);
+        assert_eq!(down[..4], (down.len() as u32).to_ne_bytes());
+        assert_eq!(down[20..24], 3i32.to_ne_bytes());
+
+        let request = newlink_request(3, 0, 0, Some(&[2, 0, 0, 0, 1, 10]));
+        let attribute = &request[NLMSG_HDRLEN + IFINFOMSG_LEN..];
+        assert_eq!(request.len() % 4, 0);
+        assert_eq!(attribute[..2], 10u16.to_ne_bytes());
+        assert_eq!(attribute[2..4], libc::IFLA_ADDRESS.to_ne_bytes());
+        assert_eq!(attribute[4..10], [2, 0, 0, 0, 1, 10]);
+    }
+
+    proptest! {
+        #[test]
+        fn parse_scan_is_total(output in any::<String>()) {
+            Iw::parse_scan("wlan0", &output);
+        }
+
+        #[test]
+        fn parse_scan_finds_every_bss(
+            networks in vec(("[a-zA-Z0-9_-]{1,32}", -100i32..-10, any::<bool>()), 0..8)
+        ) {
+            let mut output = String::new();
+            for (i, (essid, dbm, enc)) in networks.iter().enumerate() {
+                output += &format!(
+                    "BSS 00:11:22:33:44:{:02x}(on wlan0)\n\tfreq: 2412\n\tcapability: ESS{} (0x0011)\n\tsignal: {}.00 dBm\n\tSSID: {}\n",
+                    i, if *enc { " Privacy" } else { "" }, dbm, essid
+                );
+            }
+            let expected: Vec<_> = networks
+                .into_iter()
+                .map(|(essid, dbm, enc)| (essid, support::dbm2perc(dbm), enc))
+                .collect();
+            prop_assert_eq!(scan(&output), expected);
+        }
+    }
+}
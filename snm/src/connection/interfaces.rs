@@ -1,22 +1,18 @@
-use super::parsers::{parse, Parsers};
-use super::support;
-use super::types::{ConnectionInfo, ConnectionSetting};
-use nix::libc;
-use smoltcp::phy::{wait, Device, Medium, RawSocket};
-use smoltcp::socket::{Dhcpv4Event, Dhcpv4Socket};
-use smoltcp::wire::{EthernetAddress, IpCidr, Ipv4Address};
-use smoltcp::{iface, time::Instant};
-
-use std::collections::{BTreeMap, HashSet};
-use std::os::unix::io::AsRawFd;
+use super::hw::{Driver, Hardware};
+use super::types::{
+    AccessPoint, ConnectionInfo, ConnectionSetting, DhcpEvent, HotspotClient, LinkMode, LinkStats,
+    Station, Statistics,
+};
+use crate::error::{Error, Result};
+
+use std::collections::HashSet;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
+    mpsc, Arc, Mutex,
 };
 use std::{
-    fmt, fs,
+    fmt,
     hash::{Hash, Hasher},
-    path::Path,
     thread, time,
 };
 
@@ -26,8 +22,13 @@ const DHCP_TIMEOUT_MS: u64 = 10000;
 #[derive(Clone)]
 pub struct Interface {
     name: String,
+    hw: Hardware,
     dhcp_running: Arc<AtomicBool>,
     ip: Arc<Mutex<String>>,
+    // servers used instead of those from the lease
+    dns: Arc<Mutex<Vec<String>>>,
+    // mtu to go back to on disconnect
+    original_mtu: Arc<Mutex<Option<u32>>>,
 }
 
 impl Hash for Interface {
@@ -45,227 +46,265 @@ impl PartialEq for Interface {
 impl Eq for Interface {}
 
 impl Interface {
-    pub fn new(name: &str) -> Self {
+    pub fn new(name: &str, hw: Hardware) -> Self {
         Interface {
             name: name.to_owned(),
+            hw,
             dhcp_running: Arc::new(AtomicBool::new(false)),
             ip: Arc::new(Mutex::new(String::new())),
+            dns: Arc::new(Mutex::new(Vec::new())),
+            original_mtu: Arc::new(Mutex::new(None)),
         }
     }
 
     pub fn disconnect(&self) {
         self.dhcp_running.store(false, Ordering::SeqCst);
-        support::run(&format!("ip addr flush dev {}", self.name), false);
-        support::run(
-            &format!("wpa_cli -i {} -p /var/run/wpa disconnect", self.name),
-            false,
-        );
-        support::run(
-            &format!("wpa_cli -i {} -p /var/run/wpa terminate", self.name),
-            false,
-        );
-    }
-
-    pub fn scan(&self) -> String {
+        self.ip.lock().unwrap().clear();
+        self.dns.lock().unwrap().clear();
+        self.hw.link.flush(&self.name);
+        self.hw.supplicant.stop(&self.name);
+        self.restore_mtu();
+    }
+
+    pub fn scan(&self) -> Result<Vec<AccessPoint>> {
         if self.valid() {
-            support::run(&format!("iw dev {} scan", self.name), false)
+            self.hw.scanner.scan(&self.name)
         } else {
-            "".to_owned()
+            Err(Error::NoInterface)
         }
     }
 
     pub fn up(&self) {
         if self.valid() {
-            support::run(&format!("ip l set {} up", self.name), false);
+            self.hw.link.up(&self.name);
         }
     }
 
     pub fn down(&self) {
         self.ip.lock().unwrap().clear();
         if self.valid() {
-            support::run(&format!("ip l set {} down", self.name), false);
+            self.hw.link.down(&self.name);
         }
     }
 
     pub fn is_plugged_in(&self) -> bool {
-        if self.valid() {
-            let filename = format!("/sys/class/net/{}/carrier", self.name);
-            if let Ok(value) = fs::read_to_string(&filename) {
-                return value == "1\n";
-            }
-        }
-        false
+        self.valid() && self.hw.link.is_plugged_in(&self.name)
     }
 
     pub fn is_up(&self) -> bool {
-        if self.valid() {
-            let filename = format!("/sys/class/net/{}/operstate", self.name);
-            if let Ok(value) = fs::read_to_string(&filename) {
-                return value == "up\n";
-            }
-        }
-        false
+        self.valid() && self.hw.link.is_up(&self.name)
     }
 
-    fn valid(&self) -> bool {
-        !self.name.is_empty()
+    pub fn has_global_ipv6(&self) -> bool {
+        self.hw.link.has_global_ipv6(&self.name)
     }
 
-    fn dhcp_process(
-        ifname: &str,
-        mac: EthernetAddress,
-        runflag: Arc<AtomicBool>,
-        ip: Arc<Mutex<String>>,
-    ) {
-        let device = RawSocket::new(ifname, Medium::Ethernet).unwrap();
-        let fd = device.as_raw_fd();
-        let neighbor_cache = iface::NeighborCache::new(BTreeMap::new());
-        let ip_addrs = [IpCidr::new(Ipv4Address::UNSPECIFIED.into(), 0)];
-        let mut routes_storage = [None; 1];
-        let routes = iface::Routes::new(&mut routes_storage[..]);
-        let medium = device.capabilities().medium;
-        let mut builder = iface::InterfaceBuilder::new(device, vec![])
-            .ip_addrs(ip_addrs)
-            .routes(routes);
-        if medium == Medium::Ethernet {
-            builder = builder
-                .hardware_addr(mac.into())
-                .neighbor_cache(neighbor_cache);
-        }
-        let mut iface = builder.finalize();
-        let dhcp_handle = iface.add_socket(Dhcpv4Socket::new()); //check
-        while runflag.load(Ordering::SeqCst) {
-            let timestamp = Instant::now();
-            if iface.poll(timestamp).is_err() {
-                return;
-            }
+    pub fn start_supplicant(&self, driver: Driver, config: &str) -> Result<()> {
+        self.hw.supplicant.start(&self.name, driver, config)
+    }
 
-            let event = iface.get_socket::<Dhcpv4Socket>(dhcp_handle).poll();
-            match event {
-                None => {}
-                Some(Dhcpv4Event::Configured(config)) => {
-                    if let Some(router) = config.router {
-                        support::run(
-                            &format!("ip route add {} dev {}", config.address.network(), ifname),
-                            false,
-                        );
-                        support::run(
-                            &format!("ip route add default via {} dev {}", router, ifname),
-                            false,
-                        );
-                    }
+    pub fn supplicant_state(&self) -> Result<String> {
+        self.hw.supplicant.state(&self.name)
+    }
 
-                    let new_ip = config.address.address().to_string();
-                    if new_ip != "0.0.0.0" {
-                        let mut ip_locked = ip.lock().unwrap();
-                        if *ip_locked != new_ip {
-                            *ip_locked = new_ip.clone();
-                            support::run(&format!("ip addr flush dev {}", ifname), false);
-                            support::run(&format!("ip addr add {} dev {}", new_ip, ifname), false);
-                        }
-                    }
-                    if config.dns_servers.iter().any(|s| s.is_some()) {
-                        use std::io::Write;
-                        let mut resolv = std::fs::File::create("/etc/resolv.conf")
-                            .expect("cannot rewrite /etc/resolv.conf");
-                        for dns_server in config.dns_servers.iter().filter_map(|s| *s) {
-                            writeln!(resolv, "nameserver {}\n", dns_server)
-                                .expect("cannot write /etc/resolve.conf");
-                        }
-                    }
-                }
-                Some(Dhcpv4Event::Deconfigured) => {
-                    support::run(&format!("ip addr flush dev {}", ifname), false);
-                    support::run(&format!("ip route flush dev {}", ifname), false);
-                }
-            }
-            wait(fd, iface.poll_delay(timestamp)).expect("wait error");
-        }
+    pub fn roam(&self, bssid: &str) -> Result<()> {
+        self.hw.supplicant.roam(&self.name, bssid)
+    }
+
+    pub fn supplicant_poll_interval(&self) -> time::Duration {
+        self.hw.supplicant.poll_interval()
     }
 
-    pub fn dhcp(&self) -> Result<String, ()> {
+    fn valid(&self) -> bool {
+        !self.name.is_empty()
+    }
+
+    pub fn dhcp<EventHandler: FnMut(DhcpEvent) + Send + 'static>(
+        &self,
+        mut on_event: EventHandler,
+    ) -> Result<String> {
         if !self.valid() {
-            return Err(());
+            return Err(Error::NoInterface);
         }
-        let mac = self.detect_mac().expect("cannot detect HW addr");
+        let hw = self.hw.clone();
         let ip = self.ip.clone();
+        let fixed_dns = self.dns.clone();
         let name = self.name.clone();
         let runflag = self.dhcp_running.clone();
+        let (error_sender, errors) = mpsc::channel();
         runflag.store(true, Ordering::SeqCst);
         thread::spawn(move || {
-            Interface::dhcp_process(&name, mac, runflag, ip);
+            let mut handler = |event: DhcpEvent| {
+                if let DhcpEvent::Bound {
+                    ref address,
+                    ref dns,
+                    ..
+                } = event
+                {
+                    let address = address.split('/').next().unwrap_or_default();
+                    *ip.lock().unwrap() = address.to_owned();
+                    if !dns.is_empty() && fixed_dns.lock().unwrap().is_empty() {
+                        if let Err(e) = hw.dns.apply(dns) {
+                            warn!(interface = name.as_str(); "cannot update resolver: {}", e);
+                        }
+                    }
+                }
+                on_event(event);
+            };
+            let result = hw.dhcp.run(&name, runflag.clone(), &mut handler);
+            if let Err(e) = result {
+                error!(interface = name.as_str(); "dhcp client on {} failed: {}", name, e);
+                error_sender.send(e).unwrap_or_default();
+                runflag.store(false, Ordering::SeqCst);
+            }
         });
 
         let mut tries = 0;
         let max_tries = DHCP_TIMEOUT_MS / DHCP_POLL_INTERVAL_MS;
         while tries < max_tries {
-            thread::sleep(time::Duration::from_millis(DHCP_POLL_INTERVAL_MS));
+            if let Ok(e) = errors.try_recv() {
+                return Err(e);
+            }
             let result = self.ip.lock().unwrap().clone();
             if !result.is_empty() {
                 return Ok(result);
             }
+            if !self.dhcp_running.load(Ordering::SeqCst) {
+                return Err(Error::Aborted);
+            }
+            thread::sleep(time::Duration::from_millis(DHCP_POLL_INTERVAL_MS));
             tries += 1;
         }
         self.dhcp_running.store(false, Ordering::SeqCst);
-        return Err(());
+        Err(Error::DhcpTimeout(self.name.clone()))
     }
 
-    fn detect_mac(&self) -> Result<EthernetAddress, ()> {
-        use nix::{ifaddrs::getifaddrs, sys::socket::SockAddr};
-        let ifaces = getifaddrs().map_err(|_| ())?;
+    pub fn stop_dhcp(&self) {
+        self.dhcp_running.store(false, Ordering::SeqCst);
+    }
 
-        for iface in ifaces {
-            if iface.interface_name == self.name {
-                if let Some(addr) = iface.address {
-                    if let SockAddr::Link(link) = addr {
-                        return Ok(EthernetAddress(link.addr()));
-                    }
-                }
+    // clients of the network at address are served until runflag is cleared
+    pub fn serve_dhcp<ChangeHandler: FnMut(Vec<HotspotClient>) + Send + 'static>(
+        &self,
+        address: &str,
+        runflag: Arc<AtomicBool>,
+        mut on_change: ChangeHandler,
+    ) {
+        let hw = self.hw.clone();
+        let name = self.name.clone();
+        let address = address.to_owned();
+        thread::spawn(move || {
+            let result = hw
+                .dhcp_server
+                .serve(&name, &address, runflag.clone(), &mut on_change);
+            if let Err(e) = result {
+                error!(interface = name.as_str(); "dhcp server on {} failed: {}", name, e);
+                runflag.store(false, Ordering::SeqCst);
+            }
+        });
+    }
+
+    // whether forwarding was enabled before
+    pub fn share(&self, subnet: &str, uplink: &Interface) -> Result<bool> {
+        self.hw.sharing.share(&self.name, subnet, &uplink.name)
+    }
+
+    pub fn unshare(&self, subnet: &str, uplink: &Interface, forwarding: bool) {
+        self.hw
+            .sharing
+            .unshare(&self.name, subnet, &uplink.name, forwarding);
+    }
+
+    pub fn configure(&self, address: &str, gateway: Option<&str>) -> Result<String> {
+        self.hw.link.flush(&self.name);
+        self.hw.link.set_address(&self.name, address)?;
+        if let Some(gateway) = gateway {
+            self.hw.link.set_gateway(&self.name, gateway)?;
+        }
+        let ip = address.split('/').next().unwrap_or_default().to_owned();
+        *self.ip.lock().unwrap() = ip.clone();
+        Ok(ip)
+    }
+
+    // an empty list leaves the resolver to the lease
+    pub fn set_dns(&self, servers: &[String]) {
+        *self.dns.lock().unwrap() = servers.to_vec();
+        if !servers.is_empty() {
+            if let Err(e) = self.hw.dns.apply(servers) {
+                warn!(interface = self.name.as_str(); "cannot update resolver: {}", e);
             }
         }
-        Err(())
     }
 
-    fn detect_ip(&self) -> Option<String> {
-        let ok: bool;
-        let ifreq = ifreq_ip::new(&self.name);
-        unsafe {
-            let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
-            ok = libc::ioctl(fd, libc::SIOCGIFADDR, &ifreq) != -1;
-            libc::close(fd);
+    pub fn set_mtu(&self, mtu: u32) -> Result<()> {
+        {
+            let mut original = self.original_mtu.lock().unwrap();
+            if original.is_none() {
+                *original = self.hw.link.mtu(&self.name);
+            }
         }
-        if ok {
-            let addr = ifreq.ifr_addr.sin_addr;
-            Some(format!("{}.{}.{}.{}", addr[0], addr[1], addr[2], addr[3]))
-        } else {
-            None
+        self.hw.link.set_mtu(&self.name, mtu)
+    }
+
+    fn restore_mtu(&self) {
+        if let Some(mtu) = self.original_mtu.lock().unwrap().take() {
+            if let Err(e) = self.hw.link.set_mtu(&self.name, mtu) {
+                warn!(interface = self.name.as_str(); "cannot restore mtu {}: {}", mtu, e);
+            }
         }
     }
 
+    pub fn set_link_mode(&self, mode: LinkMode) -> Result<()> {
+        self.hw.link.set_link_mode(&self.name, mode)
+    }
+
+    pub fn set_wake_on_lan(&self, enabled: bool) -> Result<()> {
+        self.hw.link.set_wake_on_lan(&self.name, enabled)
+    }
+
+    pub fn neighbor(&self, ip: &str) -> Option<String> {
+        self.hw.link.neighbor(&self.name, ip)
+    }
+
+    pub fn station(&self) -> Option<Station> {
+        self.hw.scanner.link(&self.name)
+    }
+
+    pub fn link_stats(&self) -> Option<LinkStats> {
+        self.hw.scanner.link_stats(&self.name)
+    }
+
+    pub fn statistics(&self) -> Option<Statistics> {
+        self.hw.link.statistics(&self.name)
+    }
+
+    pub fn mac(&self) -> Option<String> {
+        self.hw.link.mac(&self.name)
+    }
+
+    pub fn permanent_mac(&self) -> Result<String> {
+        self.hw.link.permanent_mac(&self.name)
+    }
+
+    pub fn set_mac(&self, mac: &str) -> Result<()> {
+        if self.mac().as_deref() == Some(mac) {
+            return Ok(());
+        }
+        self.hw.link.set_mac(&self.name, mac)
+    }
+
     pub fn wlan_info(&self) -> ConnectionInfo {
-        use std::str;
-        let output = support::run(&format!("iw dev {} link", self.name), false);
-
-        if let Some(ref ecaps) = parse(Parsers::NetworkEssid, &output) {
-            if let Some(ip) = self.detect_ip() {
-                let parsed = support::parse_essid(ecaps.get(1).unwrap().as_str());
-                if let Ok(value) = str::from_utf8(&parsed) {
-                    let mut quality = 100;
-
-                    if let Some(ref caps) = parse(Parsers::NetworkQuality, &output) {
-                        quality = support::dbm2perc(
-                            caps.get(1).unwrap().as_str().parse::<i32>().unwrap_or(100),
-                        );
-                    }
-                    return ConnectionInfo::Wifi(value.to_string(), quality, true, ip);
-                }
+        if let Some(station) = self.station() {
+            if let Some(ip) = self.hw.link.ip(&self.name) {
+                let (quality, band) = (station.quality(), station.band());
+                return ConnectionInfo::Wifi(station.essid, quality, true, ip, band);
             }
         }
         ConnectionInfo::NotConnected
     }
 
     pub fn eth_info(&self) -> ConnectionInfo {
-        if let Some(ip) = self.detect_ip() {
+        if let Some(ip) = self.hw.link.ip(&self.name) {
             ConnectionInfo::Ethernet(ip)
         } else {
             ConnectionInfo::NotConnected
@@ -281,17 +320,21 @@ impl fmt::Display for Interface {
 
 #[derive(Clone)]
 pub struct Interfaces {
+    hw: Hardware,
     eth_ifaces: HashSet<Interface>,
     wlan_ifaces: HashSet<Interface>,
 }
 
 impl Interfaces {
-    pub fn new() -> Interfaces {
+    pub fn new(hw: Hardware) -> Interfaces {
         let mut result = Interfaces {
+            hw,
             eth_ifaces: HashSet::new(),
             wlan_ifaces: HashSet::new(),
         };
-        result.detect();
+        if let Err(e) = result.detect() {
+            error!("cannot detect interfaces: {}", e);
+        }
         result
     }
 
@@ -302,44 +345,37 @@ impl Interfaces {
         }
     }
 
-    pub fn detect(&mut self) {
-        for entry in fs::read_dir(&Path::new("/sys/class/net")).expect("no sysfs entry") {
-            if let Ok(entry) = entry {
-                let path = entry.file_name();
-                if let Some(iface_name) = path.to_str() {
-                    if let Some(sym) = iface_name.chars().next() {
-                        match sym {
-                            'e' => {
-                                let iface = Interface::new(iface_name);
-                                if !self.eth_ifaces.contains(&iface) {
-                                    println!("Detected ethernet interface: {}", iface_name);
-                                    iface.up();
-                                    self.eth_ifaces.insert(iface);
-                                } else {
-                                    iface.up();
-                                }
-                            }
-                            'w' => {
-                                let iface = Interface::new(iface_name);
-                                if !self.wlan_ifaces.contains(&iface) {
-                                    println!("Detected wifi interface: {}", iface_name);
-                                    self.wlan_ifaces.insert(iface);
-                                }
-                            }
-                            _ => {}
+    pub fn detect(&mut self) -> Result<()> {
+        for iface_name in self.hw.link.interfaces()? {
+            if let Some(sym) = iface_name.chars().next() {
+                let iface = Interface::new(&iface_name, self.hw.clone());
+                match sym {
+                    'e' => {
+                        if !self.eth_ifaces.contains(&iface) {
+                            info!(interface = iface_name.as_str(); "Detected ethernet interface: {}", iface_name);
+                            iface.up();
+                            self.eth_ifaces.insert(iface);
+                        } else {
+                            iface.up();
                         }
                     }
+                    'w' if !self.wlan_ifaces.contains(&iface) => {
+                        info!(interface = iface_name.as_str(); "Detected wifi interface: {}", iface_name);
+                        self.wlan_ifaces.insert(iface);
+                    }
+                    _ => {}
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn disconnect(&self) {
-        for iface in self.eth_ifaces.iter() {
-            iface.disconnect();
-        }
-        for iface in self.wlan_ifaces.iter() {
-            iface.disconnect();
+    // all but keep, which runs the hotspot
+    pub fn disconnect(&self, keep: Option<&Interface>) {
+        for iface in self.eth_ifaces.iter().chain(self.wlan_ifaces.iter()) {
+            if keep != Some(iface) {
+                iface.disconnect();
+            }
         }
     }
 
@@ -351,6 +387,14 @@ impl Interfaces {
         Self::most_used_iface(&self.wlan_ifaces)
     }
 
+    pub fn by_name(&self, name: &str) -> Option<Interface> {
+        self.eth_ifaces
+            .iter()
+            .chain(self.wlan_ifaces.iter())
+            .find(|iface| iface.name == name)
+            .cloned()
+    }
+
     fn most_used_iface(ifaces: &HashSet<Interface>) -> Option<Interface> {
         match ifaces.len() {
             0 => None,
@@ -367,33 +411,3 @@ impl Interfaces {
         }
     }
 }
-
-#[repr(C)]
-struct sockaddr_in {
-    pub sin_family: libc::sa_family_t,
-    pub sin_port: libc::in_port_t,
-    pub sin_addr: [u8; 4],
-    pub sin_zero: [u8; 8],
-}
-
-#[repr(C)]
-struct ifreq_ip {
-    ifr_name: [libc::c_uchar; libc::IF_NAMESIZE],
-    pub ifr_addr: sockaddr_in,
-}
-
-impl ifreq_ip {
-    fn new(ifname: &str) -> Self {
-        let mut ifr_name = [0; libc::IF_NAMESIZE];
-        ifr_name[..ifname.len()].clone_from_slice(ifname.as_bytes());
-        ifreq_ip {
-            ifr_name,
-            ifr_addr: sockaddr_in {
-                sin_family: libc::AF_INET as libc::sa_family_t,
-                sin_port: 0,
-                sin_addr: [0xff; 4],
-                sin_zero: [0; 8],
-            },
-        }
-    }
-}
@@ -1,96 +1,227 @@
+mod connectivity;
+mod hw;
 mod interfaces;
-mod parsers;
+mod online;
+mod operations;
+pub mod parsers;
+mod roaming;
 mod signalmsg;
+mod state;
 mod support;
+#[cfg(test)]
+mod tests;
+mod traffic;
 mod types;
 
+use crate::error::{Error, Reason, Result};
+pub use connectivity::{Connectivity, ConnectivityChecker, ConnectivitySettings};
+pub use hw::{
+    Dhcp, Dns, Driver, Fake, Hardware, Iw, Link, Scanner, Scenario, Simulation, Supplicant,
+};
 use interfaces::{Interface, Interfaces};
-use parsers::{parse, Parsers};
+pub use online::{OnlineCriteria, OnlineWatcher};
+pub use operations::OperationId;
+use operations::{Cancel, Operations};
+use roaming::Roam;
+pub use roaming::{Roamer, RoamingSettings};
 pub use signalmsg::SignalMsg;
+pub use state::State;
+use state::StateMachine;
+pub use support::{civil_date, new_mac_secret, parse_essid};
+use traffic::Traffic;
 pub use types::*;
 
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc, Mutex, RwLock,
-};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::{fs, path::Path, thread, time};
 
 const AUTH_MAX_TRIES: usize = 30;
 const ASSOC_MAX_TRIES: usize = 12;
 const SHORT_INTERVAL: u32 = 30;
 const LONG_INTERVAL: u32 = 1800;
-const ROAMING_DB_PATH: &str = "/etc/snm/roaming.db";
+const ROAMING_DB: &str = "roaming.db";
+const CONNECTIVITY_MAX_TRIES: usize = 3;
+// changes below these are not reported
+const SIGNAL_STEP: i32 = 3;
+const BITRATE_STEP_PERCENT: u64 = 10;
+const QUALITY_STEP: u32 = 5;
+const HOTSPOT_ADDRESS: &str = "10.42.0.1/24";
+const HOTSPOT_SUBNET: &str = "10.42.0.0/24";
+
+// what the dhcp server handed out
+struct Lease {
+    ip: String,
+    router: Option<String>,
+    mtu: Option<u32>,
+}
+
+// the access point run on the wifi interface
+struct Hotspot {
+    setting: HotspotSetting,
+    iface: Interface,
+    uplink: Interface,
+    // forwarding was enabled before sharing
+    forwarding: bool,
+    serving: Arc<AtomicBool>,
+    clients: Arc<Mutex<Vec<HotspotClient>>>,
+    // the wifi network given up for it
+    previous: Option<Ssid>,
+    was_up: bool,
+}
+
+// the wifi interface is reserved from the moment a hotspot starts
+enum Hosting {
+    Starting(Interface),
+    Running(Box<Hotspot>),
+}
+
+impl Hosting {
+    fn iface(&self) -> &Interface {
+        match self {
+            Hosting::Starting(iface) => iface,
+            Hosting::Running(hotspot) => &hotspot.iface,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Connection<SignalHandler: FnMut(SignalMsg)> {
     ifaces: Arc<Mutex<Interfaces>>,
-    tries: Arc<AtomicUsize>,
+    machine: Arc<Mutex<StateMachine>>,
     current: Arc<RwLock<ConnectionInfo>>,
     networks: Arc<Mutex<NetworkList>>,
+    access_points: Arc<Mutex<Vec<AccessPoint>>>,
+    connectivity: Arc<RwLock<Connectivity>>,
+    checker: ConnectivityChecker,
+    roamer: Arc<Mutex<Roamer>>,
+    // last reported link stats
+    link: Arc<Mutex<Option<LinkStats>>>,
+    traffic: Arc<Mutex<Traffic>>,
+    // the dhcp server of the current lease marked it metered
+    detected_metered: Arc<AtomicBool>,
+    metered: Arc<RwLock<MeteredState>>,
+    wired: Arc<RwLock<WiredCredentials>>,
+    wired_profiles: Arc<RwLock<WiredProfiles>>,
+    // name of the profile the wired connection uses
+    wired_profile: Arc<RwLock<Option<String>>>,
+    // key for the stable per network addresses
+    mac_secret: Arc<RwLock<Vec<u8>>>,
+    // addresses interfaces had before snm changed them
+    original_macs: Arc<Mutex<HashMap<String, String>>>,
+    // interfaces whose driver refuses a new address
+    fixed_macs: Arc<Mutex<HashSet<String>>>,
+    hotspot: Arc<Mutex<Option<Hosting>>>,
+    // bumped on every connect, background results of an older one are stale
+    generation: Arc<AtomicU32>,
+    operations: Operations,
     signal_handler: SignalHandler,
 }
 
-impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
-    fn wait_for_auth(&self, iface: &Interface) -> bool {
+impl<SignalHandler: FnMut(SignalMsg) + Clone + Send + 'static> Connection<SignalHandler> {
+    fn wait_for_auth(&self, iface: &Interface, max_tries: usize) -> Result<bool> {
+        let waiting = self.state();
         let mut tries = 0;
-        while tries < self.tries.load(Ordering::SeqCst) {
-            let output = support::run(
-                &format!("wpa_cli -i {} -p /var/run/wpa status", iface),
-                false,
-            );
-            if let Some(ref caps) = parse(Parsers::WpaState, &output) {
-                let result = caps[1].as_ref();
-                if let "COMPLETED" = result {
-                    return true;
-                }
+        while tries < max_tries && self.state() == waiting {
+            if iface.supplicant_state()? == "COMPLETED" {
+                return Ok(true);
             }
-            thread::sleep(time::Duration::from_secs(1));
+            thread::sleep(iface.supplicant_poll_interval());
             tries += 1;
         }
-        false
+        Ok(false)
     }
 
     fn signal(&mut self, s: SignalMsg) {
         (self.signal_handler)(s);
     }
 
-    fn aborted(&mut self) -> bool {
-        let result = self.tries.load(Ordering::SeqCst) == 0;
-        if result {
-            self.signal(SignalMsg::ConnectStatusChanged(ConnectionStatus::Aborted));
+    fn change_status(
+        &mut self,
+        status: ConnectionStatus,
+        reason: Reason,
+        essid: &Ssid,
+        iface: &str,
+    ) {
+        let op = self.operations.active().unwrap_or(0);
+        self.signal(SignalMsg::ConnectStatusChanged(StatusChange {
+            status,
+            reason,
+            essid: essid.clone(),
+            iface: iface.to_owned(),
+            op,
+        }));
+    }
+
+    fn check_aborted(&self) -> Result<()> {
+        if self.state().interrupted() {
+            return Err(Error::Aborted);
         }
-        result
+        Ok(())
     }
 
-    fn generate_wpa_config(setting: &ConnectionSetting) -> Option<String> {
-        match *setting {
-            ConnectionSetting::Wifi {
-                ref essid,
-                ref password,
-                threshold,
-            } => support::gen_wpa_config(
-                essid,
-                Some(password),
-                threshold,
-                ROAMING_DB_PATH,
-                SHORT_INTERVAL,
-                LONG_INTERVAL,
-            )
-            .ok(),
-            ConnectionSetting::OpenWifi {
-                ref essid,
-                threshold,
-            } => support::gen_wpa_config(
-                essid,
-                None,
-                threshold,
-                ROAMING_DB_PATH,
-                SHORT_INTERVAL,
-                LONG_INTERVAL,
-            )
-            .ok(),
-            _ => None,
+    // all progress signals are emitted from here, so they always match the state
+    fn enter(&mut self, to: State, essid: &Ssid, iface: &str) -> Result<()> {
+        let transition = self.machine.lock().unwrap().transition(to);
+        match transition {
+            Ok(from) => debug!(interface = iface; "state {:?} -> {:?}", from, to),
+            Err(from) => {
+                debug!(interface = iface; "rejected state {:?} -> {:?}", from, to);
+                return Err(if from.interrupted() {
+                    Error::Aborted
+                } else {
+                    Error::InvalidTransition(from, to)
+                });
+            }
+        }
+        if let State::Preparing = to {
+            self.change_state(if essid.is_empty() {
+                ConnectionInfo::ConnectingEth
+            } else {
+                ConnectionInfo::ConnectingWifi(essid.clone())
+            });
+        }
+        if let Some(status) = to.status() {
+            self.change_status(status, Reason::None, essid, iface);
+        }
+        Ok(())
+    }
+
+    fn connected(&mut self, info: ConnectionInfo, iface: &str) -> Result<()> {
+        self.enter(State::Connected, &Ssid::default(), iface)?;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.change_state(info);
+        Ok(())
+    }
+
+    // an attempt torn down by someone else is reported as aborted
+    fn fail(&mut self, e: Error, essid: &Ssid, iface: &str) -> Error {
+        let transition = self.machine.lock().unwrap().transition(State::Failed);
+        let e = match transition {
+            Err(from) if from.interrupted() => Error::Aborted,
+            _ => e,
+        };
+        self.change_status(e.status(), e.reason(), essid, iface);
+        e
+    }
+
+    fn generate_wpa_config(
+        setting: &ConnectionSetting,
+        freq_list: Option<&[u32]>,
+        bssid_hint: Option<&str>,
+    ) -> Result<Option<String>> {
+        if let ConnectionSetting::Ethernet = setting {
+            return Ok(None);
         }
+        support::gen_wpa_config(
+            setting,
+            freq_list,
+            bssid_hint,
+            &crate::config::path(ROAMING_DB).to_string_lossy(),
+            SHORT_INTERVAL,
+            LONG_INTERVAL,
+        )
+        .map(Some)
     }
 
     fn change_state(&mut self, info: ConnectionInfo) {
@@ -98,7 +229,33 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
         self.signal(SignalMsg::StateChanged(info));
     }
 
-    fn get_network(&self, essid: &str) -> Result<NetworkInfo, ()> {
+    // probes in the background, the result is dropped if the connection went away meanwhile
+    fn settle_connectivity(&self) {
+        let mut connection = self.clone();
+        let generation = self.generation.load(Ordering::SeqCst);
+        thread::spawn(move || {
+            let connectivity = connection.checker.check_settled(CONNECTIVITY_MAX_TRIES);
+            if connection.state() == State::Connected
+                && connection.generation.load(Ordering::SeqCst) == generation
+            {
+                connection.change_connectivity(connectivity);
+            }
+        });
+    }
+
+    fn change_connectivity(&mut self, connectivity: Connectivity) {
+        let changed = {
+            let mut current = self.connectivity.write().unwrap();
+            let changed = *current != connectivity;
+            *current = connectivity;
+            changed
+        };
+        if changed {
+            self.signal(SignalMsg::ConnectivityChanged(connectivity));
+        }
+    }
+
+    fn get_network(&self, essid: &Ssid) -> Result<NetworkInfo> {
         if let Ok(networks) = self.networks.lock() {
             let result = networks.iter().find(|network| {
                 if let NetworkInfo::Wifi(net_essid, ..) = network {
@@ -111,7 +268,34 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
                 return Ok(network.clone());
             }
         }
-        Err(())
+        Err(Error::UnknownNetwork(essid.to_string()))
+    }
+
+    // a network pinned to a bssid is only usable while that bss is in range
+    fn bssid_seen(&self, bssid: Option<&str>) -> bool {
+        bssid.is_none_or(|bssid| {
+            self.access_points
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|ap| ap.bssid.eq_ignore_ascii_case(bssid))
+        })
+    }
+
+    fn freq_list(&self, essid: &Ssid, bands: &BandPolicy) -> Option<Vec<u32>> {
+        let seen: Vec<u32> = self
+            .get_access_points(essid)
+            .iter()
+            .map(|ap| ap.frequency)
+            .collect();
+        bands.freq_list(&seen)
+    }
+
+    fn usable(&self, setting: &ConnectionSetting) -> bool {
+        self.bssid_seen(setting.bssid())
+            && self
+                .freq_list(setting.essid(), &setting.bands())
+                .is_none_or(|frequencies| !frequencies.is_empty())
     }
 
     fn add_wifi_network(networks: &mut Vec<NetworkInfo>, new_network: NetworkInfo) {
@@ -131,124 +315,424 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
         networks.push(new_network);
     }
 
-    pub fn new(signal_handler: SignalHandler) -> Self {
+    pub fn new(
+        signal_handler: SignalHandler,
+        checker: ConnectivityChecker,
+        roamer: Roamer,
+    ) -> Self {
+        Self::with_hardware(signal_handler, checker, roamer, Hardware::system())
+    }
+
+    pub fn with_hardware(
+        signal_handler: SignalHandler,
+        checker: ConnectivityChecker,
+        roamer: Roamer,
+        hw: Hardware,
+    ) -> Self {
         Connection {
-            ifaces: Arc::new(Mutex::new(Interfaces::new())),
-            tries: Arc::new(AtomicUsize::new(0)),
+            ifaces: Arc::new(Mutex::new(Interfaces::new(hw))),
+            machine: Arc::new(Mutex::new(StateMachine::new())),
             current: Arc::new(RwLock::new(ConnectionInfo::NotConnected)),
             networks: Arc::new(Mutex::new(NetworkList::new())),
+            access_points: Arc::new(Mutex::new(Vec::new())),
+            connectivity: Arc::new(RwLock::new(Connectivity::None)),
+            checker,
+            roamer: Arc::new(Mutex::new(roamer)),
+            link: Arc::new(Mutex::new(None)),
+            traffic: Arc::new(Mutex::new(Traffic::default())),
+            detected_metered: Arc::new(AtomicBool::new(false)),
+            metered: Arc::new(RwLock::new(MeteredState::default())),
+            wired: Arc::new(RwLock::new(WiredCredentials::new())),
+            wired_profiles: Arc::new(RwLock::new(WiredProfiles::new())),
+            wired_profile: Arc::new(RwLock::new(None)),
+            mac_secret: Arc::new(RwLock::new(new_mac_secret())),
+            original_macs: Arc::new(Mutex::new(HashMap::new())),
+            fixed_macs: Arc::new(Mutex::new(HashSet::new())),
+            hotspot: Arc::new(Mutex::new(None)),
+            generation: Arc::new(AtomicU32::new(0)),
+            operations: Operations::default(),
             signal_handler,
         }
     }
 
-    pub fn connect(&mut self, setting: ConnectionSetting) -> bool {
-        self.tries.store(AUTH_MAX_TRIES, Ordering::SeqCst);
-        let mut network = NetworkInfo::Ethernet;
-        let if_iface = self.ifaces.lock().unwrap().from_setting(&setting);
-        if let Some(iface) = if_iface {
-            let connection = self.current.read().unwrap().clone();
+    pub fn check_setting(&self, setting: &ConnectionSetting) -> Result<()> {
+        self.ifaces
+            .lock()
+            .unwrap()
+            .from_setting(setting)
+            .ok_or(Error::NoInterface)?;
+        if let ConnectionSetting::Ethernet = setting {
+            return Ok(());
+        }
+        self.check_hosting()?;
+        self.get_network(setting.essid()).map(|_| ())
+    }
+
+    // the wifi interface cannot be a client while it runs the hotspot
+    fn check_hosting(&self) -> Result<()> {
+        if self.hotspot.lock().unwrap().is_some() {
+            return Err(Error::NotAllowed("wifi while the hotspot runs".to_owned()));
+        }
+        Ok(())
+    }
+
+    fn hotspot_iface(&self) -> Option<Interface> {
+        let hotspot = self.hotspot.lock().unwrap();
+        hotspot.as_ref().map(|hosting| hosting.iface().clone())
+    }
+
+    pub fn start_operation(&self) -> OperationId {
+        self.operations.start()
+    }
+
+    pub fn connect(&mut self, op: OperationId, setting: ConnectionSetting) -> Result<()> {
+        if !self.operations.begin(op) {
+            self.abandon(op);
+            return Err(Error::Aborted);
+        }
+        let iface = self
+            .ifaces
+            .lock()
+            .unwrap()
+            .from_setting(&setting)
+            .map(|iface| iface.to_string())
+            .unwrap_or_default();
+        let result = self
+            .establish(&setting, &iface)
+            .map_err(|e| self.fail(e, setting.essid(), &iface));
+        if let Err(ref e) = result {
+            warn!(op = op; "Connect failed: {}", e);
+        }
+        self.operations.finish(op);
+        let reason = result.as_ref().err().map_or(Reason::None, Error::reason);
+        self.signal(SignalMsg::ConnectFinished(op, reason));
+        result
+    }
+
+    pub fn abandon(&mut self, op: OperationId) {
+        self.operations.finish(op);
+        self.signal(SignalMsg::ConnectFinished(op, Reason::Aborted));
+    }
+
+    pub fn cancel_active(&mut self) {
+        if let Some(op) = self.operations.active() {
+            self.cancel(op).unwrap_or_default();
+        }
+    }
 
-            if connection.active() {
+    pub fn cancel(&mut self, op: OperationId) -> Result<()> {
+        match self.operations.cancel(op) {
+            Cancel::Active => {
                 self.disconnect();
+                Ok(())
             }
+            Cancel::Pending => Ok(()),
+            Cancel::Unknown => Err(Error::UnknownOperation(op)),
+        }
+    }
 
-            match setting {
-                ConnectionSetting::Wifi { ref essid, .. }
-                | ConnectionSetting::OpenWifi { ref essid, .. } => {
-                    self.change_state(ConnectionInfo::ConnectingWifi(essid.to_string()));
-                    iface.up();
-                    let network_found = self.get_network(essid);
-                    if let Ok(found) = network_found {
-                        network = found;
-                    } else {
-                        return false;
-                    }
+    fn establish(&mut self, setting: &ConnectionSetting, iface_name: &str) -> Result<()> {
+        if let ConnectionSetting::Wifi { .. } | ConnectionSetting::OpenWifi { .. } = setting {
+            self.check_hosting()?;
+        }
+        if self.state() != State::Idle {
+            self.disconnect();
+        }
+        self.enter(State::Preparing, setting.essid(), iface_name)?;
+        let iface = self
+            .ifaces
+            .lock()
+            .unwrap()
+            .from_setting(setting)
+            .ok_or(Error::NoInterface)?;
+
+        let mut freq_list = None;
+        let mut bssid_hint = None;
+        let network = match setting {
+            ConnectionSetting::Wifi { ref essid, .. }
+            | ConnectionSetting::OpenWifi { ref essid, .. } => {
+                iface.up();
+                if !self.usable(setting) {
+                    return Err(Error::UnknownNetwork(essid.to_string()));
                 }
-                ConnectionSetting::Ethernet => {
-                    self.change_state(ConnectionInfo::ConnectingEth);
-                    if !iface.is_plugged_in() {
-                        return false;
-                    }
-                    if let Some(wlan) = self.ifaces.lock().unwrap().wlan() {
+                self.apply_mac(&iface, setting)?;
+                freq_list = self.freq_list(essid, &setting.bands());
+                bssid_hint = setting
+                    .bands()
+                    .preferred(&self.get_access_points(essid))
+                    .map(|ap| ap.bssid.clone());
+                self.get_network(essid)?
+            }
+            ConnectionSetting::Ethernet => {
+                if !iface.is_plugged_in() {
+                    return Err(Error::NotPluggedIn(iface.to_string()));
+                }
+                let hotspot = self.hotspot_iface();
+                if let Some(wlan) = self.ifaces.lock().unwrap().wlan() {
+                    if hotspot.as_ref() != Some(&wlan) {
                         wlan.down();
                     }
                 }
+                self.apply_link_settings(&iface);
+                NetworkInfo::Ethernet
             }
-            self.signal(SignalMsg::ConnectStatusChanged(
-                ConnectionStatus::Initializing,
-            ));
+        };
 
-            let wpa_config = Self::generate_wpa_config(&setting);
-            let erase_wpa_config = || {
-                if let Some(ref path) = wpa_config {
-                    fs::remove_file(Path::new(path)).unwrap_or_default();
-                }
-            };
+        let (wpa_config, driver) = match setting {
+            ConnectionSetting::Ethernet => (
+                self.wired_auth(&iface)
+                    .map(|auth| support::gen_wired_config(&auth))
+                    .transpose()?,
+                Driver::Wired,
+            ),
+            _ => (
+                Self::generate_wpa_config(setting, freq_list.as_deref(), bssid_hint.as_deref())?,
+                Driver::Nl80211,
+            ),
+        };
+        let result = self
+            .associate(&iface, setting, driver, wpa_config.as_deref())
+            .and_then(|_| self.dhcp_phase(iface, network, setting.mtu()))
+            .map(|_| ());
+        if let Some(ref path) = wpa_config {
+            fs::remove_file(Path::new(path)).unwrap_or_default();
+        }
+        if result.is_ok() && driver == Driver::Nl80211 {
+            // a pinned bss is never roamed away from, only reported
+            let threshold = setting.threshold().filter(|_| setting.bssid().is_none());
+            self.roamer
+                .lock()
+                .unwrap()
+                .watch(iface_name, threshold, freq_list);
+        }
+        result
+    }
+
+    fn apply_mac(&self, iface: &Interface, setting: &ConnectionSetting) -> Result<()> {
+        let name = iface.to_string();
+        let mac = match setting.mac_policy() {
+            MacPolicy::Permanent => return self.restore_mac(iface),
+            MacPolicy::Random => support::random_mac(),
+            MacPolicy::Stable => {
+                support::stable_mac(&self.mac_secret.read().unwrap(), setting.essid())
+            }
+        };
+        debug!(interface = name.as_str(), mac = mac.as_str(); "Using address {} on {}", mac, name);
+        self.change_mac(iface, &mac)
+    }
+
+    // remembers the address it replaces, so that it can be restored
+    fn change_mac(&self, iface: &Interface, mac: &str) -> Result<()> {
+        let before = iface.mac();
+        iface.set_mac(mac)?;
+        if let Some(before) = before {
+            self.original_macs
+                .lock()
+                .unwrap()
+                .entry(iface.to_string())
+                .or_insert(before);
+        }
+        Ok(())
+    }
 
-            if self.aborted() {
-                erase_wpa_config();
-                return false;
+    // an address snm did not change is left alone
+    fn restore_mac(&self, iface: &Interface) -> Result<()> {
+        let name = iface.to_string();
+        let original = match self.original_macs.lock().unwrap().get(&name) {
+            Some(original) => original.clone(),
+            None => return Ok(()),
+        };
+        let mac = iface.permanent_mac().unwrap_or_else(|e| {
+            warn!(interface = name.as_str(); "cannot read permanent address, restoring {}: {}", original, e);
+            original
+        });
+        debug!(interface = name.as_str(), mac = mac.as_str(); "Restoring address {} on {}", mac, name);
+        match iface.set_mac(&mac) {
+            Ok(()) => {
+                self.original_macs.lock().unwrap().remove(&name);
             }
-            let need_auth = setting.need_auth();
-            if let Some(ref c) = wpa_config {
-                if need_auth {
-                    self.signal(SignalMsg::ConnectStatusChanged(
-                        ConnectionStatus::Authenticating,
-                    ));
+            Err(e) => {
+                warn!(interface = name.as_str(); "cannot restore address, keeping the current one: {}", e)
+            }
+        }
+        Ok(())
+    }
+
+    // speed and wake-on-lan come from the profile the port matches before any lease
+    fn apply_link_settings(&self, iface: &Interface) {
+        let name = iface.to_string();
+        let mac = iface.mac();
+        let profiles = self.wired_profiles.read().unwrap();
+        let profile = match match_wired(&profiles, &name, mac.as_deref(), None) {
+            Some((_, profile)) => profile,
+            None => return,
+        };
+        let mode = profile.link_mode();
+        if !mode.is_empty() {
+            iface.set_link_mode(mode).unwrap_or_else(
+                |e| warn!(interface = name.as_str(); "cannot set link mode: {}", e),
+            );
+        }
+        if let Some(enabled) = profile.wake_on_lan {
+            iface.set_wake_on_lan(enabled).unwrap_or_else(
+                |e| warn!(interface = name.as_str(); "cannot set wake-on-lan: {}", e),
+            );
+        }
+    }
+
+    fn apply_mtu(iface: &Interface, mtu: Option<u32>, dhcp_mtu: bool, offered: Option<u32>) {
+        if let Some(mtu) = offered.filter(|_| dhcp_mtu).or(mtu) {
+            let name = iface.to_string();
+            debug!(interface = name.as_str(); "Using mtu {} on {}", mtu, name);
+            iface
+                .set_mtu(mtu)
+                .unwrap_or_else(|e| warn!(interface = name.as_str(); "cannot set mtu: {}", e));
+        }
+    }
+
+    fn associate(
+        &mut self,
+        iface: &Interface,
+        setting: &ConnectionSetting,
+        driver: Driver,
+        wpa_config: Option<&str>,
+    ) -> Result<()> {
+        if let Some(c) = wpa_config {
+            let wired = driver == Driver::Wired;
+            let need_auth = setting.need_auth() || wired;
+            let (state, max_tries) = if need_auth {
+                (State::Authenticating, AUTH_MAX_TRIES)
+            } else {
+                (State::Associating, ASSOC_MAX_TRIES)
+            };
+            self.enter(state, setting.essid(), &iface.to_string())?;
+            iface.start_supplicant(driver, c)?;
+            if !self.wait_for_auth(iface, max_tries)? {
+                self.check_aborted()?;
+                let essid = if wired {
+                    iface.to_string()
+                } else {
+                    setting.essid().to_string()
+                };
+                return Err(if need_auth {
+                    Error::AuthFail(essid)
                 } else {
-                    self.tries.store(ASSOC_MAX_TRIES, Ordering::SeqCst);
-                    self.signal(SignalMsg::ConnectStatusChanged(
-                        ConnectionStatus::Connecting,
-                    ));
+                    Error::AssocFail(essid)
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn lease(&mut self, iface: &Interface) -> Result<Lease> {
+        let mut lease_handler = self.signal_handler.clone();
+        let detected = self.detected_metered.clone();
+        detected.store(false, Ordering::Relaxed);
+        let bound = Arc::new(Mutex::new((None, None)));
+        let bound_lease = bound.clone();
+        let ip = iface.dhcp(move |event| {
+            if let DhcpEvent::Bound {
+                metered,
+                ref router,
+                mtu,
+                ..
+            } = event
+            {
+                detected.store(metered, Ordering::Relaxed);
+                *bound_lease.lock().unwrap() = (router.clone(), mtu);
+            }
+            lease_handler(SignalMsg::DhcpLease(event))
+        })?;
+        let (router, mtu) = bound.lock().unwrap().clone();
+        Ok(Lease { ip, router, mtu })
+    }
+
+    fn configure_static(iface: &Interface, profile: &WiredProfile) -> Result<String> {
+        let address = profile
+            .address
+            .as_deref()
+            .ok_or_else(|| Error::NotAllowed("static addressing without address".to_owned()))?;
+        iface.configure(address, profile.gateway.as_deref())
+    }
+
+    // the gateway named by a lease can turn up a more specific profile
+    fn configure_wired(&mut self, iface: &Interface) -> Result<String> {
+        let name = iface.to_string();
+        let mac = iface.mac();
+        let profiles = self.wired_profiles.read().unwrap().clone();
+        let select = |gateway_mac: Option<&str>| {
+            match_wired(&profiles, &name, mac.as_deref(), gateway_mac)
+                .map(|(name, profile)| (name.clone(), profile.clone()))
+        };
+        let mut selected = select(None);
+        let mut offered_mtu = None;
+        let ip = match selected {
+            Some((_, ref profile)) if profile.is_static() => {
+                Self::configure_static(iface, profile)?
+            }
+            _ => {
+                let Lease { ip, router, mtu } = self.lease(iface)?;
+                offered_mtu = mtu;
+                let gateway_mac = router
+                    .filter(|_| {
+                        profiles
+                            .values()
+                            .any(|profile| profile.gateway_mac.is_some())
+                    })
+                    .and_then(|router| iface.neighbor(&router));
+                if gateway_mac.is_some() {
+                    selected = select(gateway_mac.as_deref());
                 }
-                support::run(
-                    &format!(
-                        "wpa_supplicant -B -i{} -c{} -Dnl80211 -C/var/run/wpa",
-                        iface, c
-                    ),
-                    false,
-                );
-                if !self.wait_for_auth(&iface) {
-                    if !self.aborted() {
-                        self.signal(SignalMsg::ConnectStatusChanged(if need_auth {
-                            ConnectionStatus::AuthFail
-                        } else {
-                            ConnectionStatus::ConnectFail
-                        }));
+                match selected {
+                    Some((_, ref profile)) if profile.is_static() => {
+                        iface.stop_dhcp();
+                        offered_mtu = None;
+                        Self::configure_static(iface, profile)?
                     }
-                    erase_wpa_config();
-                    return false;
+                    _ => ip,
                 }
             }
-            if self.dhcp_phase(iface, network).active() {
-                erase_wpa_config();
-                return true;
-            }
-            erase_wpa_config();
+        };
+        if let Some((ref profile_name, ref profile)) = selected {
+            info!(interface = name.as_str(), profile = profile_name.as_str(); "Using wired profile {}", profile_name);
+            iface.set_dns(&profile.dns);
+            Self::apply_mtu(iface, profile.mtu, profile.dhcp_mtu, offered_mtu);
         }
-        false
+        *self.wired_profile.write().unwrap() = selected.map(|(name, _)| name);
+        Ok(ip)
     }
 
-    fn dhcp_phase(&mut self, iface: Interface, network: NetworkInfo) -> ConnectionInfo {
-        self.signal(SignalMsg::ConnectStatusChanged(ConnectionStatus::GettingIP));
-        if let Ok(ip) = iface.dhcp() {
-            let info = match network {
-                NetworkInfo::Ethernet => ConnectionInfo::Ethernet(ip),
-                NetworkInfo::Wifi(essid, signal, enc) => {
-                    ConnectionInfo::Wifi(essid, signal, enc, ip)
-                }
-            };
-            self.change_state(info.clone());
-            return info;
-        }
-        ConnectionInfo::NotConnected
+    fn dhcp_phase(
+        &mut self,
+        iface: Interface,
+        network: NetworkInfo,
+        (mtu, dhcp_mtu): (Option<u32>, bool),
+    ) -> Result<ConnectionInfo> {
+        self.enter(State::ConfiguringIP, network.essid(), &iface.to_string())?;
+        let info = match network {
+            NetworkInfo::Ethernet => ConnectionInfo::Ethernet(self.configure_wired(&iface)?),
+            NetworkInfo::Wifi(essid, signal, enc) => {
+                let lease = self.lease(&iface)?;
+                Self::apply_mtu(&iface, mtu, dhcp_mtu, lease.mtu);
+                let ip = lease.ip;
+                let band = iface
+                    .station()
+                    .map_or(Band::Unknown, |station| station.band());
+                ConnectionInfo::Wifi(essid, signal, enc, ip, band)
+            }
+        };
+        self.connected(info.clone(), &iface.to_string())?;
+        self.settle_connectivity();
+        Ok(info)
     }
 
     pub fn acquire(&mut self) {
         let mut current = ConnectionInfo::NotConnected;
         let mut current_iface: Option<Interface> = None;
         if let Ok(mut ifaces) = self.ifaces.lock() {
-            ifaces.detect();
+            ifaces
+                .detect()
+                .unwrap_or_else(|e| error!("cannot detect interfaces: {}", e));
             if let Some(eth) = ifaces.eth() {
                 current = eth.eth_info();
                 current_iface = Some(eth);
@@ -260,7 +744,19 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
 
         if current.active() {
             if let Some(iface) = current_iface {
-                current = self.dhcp_phase(iface, current.into());
+                let network: NetworkInfo = current.into();
+                let name = iface.to_string();
+                if !network.is_eth() {
+                    self.roamer.lock().unwrap().watch(&name, None, None);
+                }
+                current = self
+                    .enter(State::Preparing, network.essid(), &name)
+                    .and_then(|_| self.dhcp_phase(iface, network.clone(), (None, false)))
+                    .unwrap_or_else(|e| {
+                        let e = self.fail(e, network.essid(), &name);
+                        warn!("Cannot acquire existing connection: {}", e);
+                        ConnectionInfo::NotConnected
+                    });
             }
         }
 
@@ -273,11 +769,20 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
     }
 
     pub fn disconnect(&mut self) {
-        self.tries.store(0, Ordering::SeqCst);
+        self.roamer.lock().unwrap().unwatch();
+        *self.wired_profile.write().unwrap() = None;
+        *self.link.lock().unwrap() = None;
+        // a concurrent disconnect is already tearing down, finish it anyway
+        self.enter(State::Disconnecting, &Ssid::default(), "")
+            .unwrap_or_default();
+        let hotspot = self.hotspot_iface();
         if let Ok(ifaces) = self.ifaces.lock() {
-            ifaces.disconnect();
+            ifaces.disconnect(hotspot.as_ref());
         }
+        self.enter(State::Idle, &Ssid::default(), "")
+            .unwrap_or_default();
         self.change_state(ConnectionInfo::NotConnected);
+        self.change_connectivity(Connectivity::None);
     }
 
     pub fn auto_connect_possible(&mut self, known_networks: &KnownNetworks) -> CouldConnect {
@@ -285,8 +790,12 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
         let mut wifi_plugged_in = false;
 
         if let Ok(mut ifaces) = self.ifaces.lock() {
-            ifaces.detect();
-            eth_plugged_in = ifaces.eth().map_or(false, |eth| eth.is_plugged_in());
+            ifaces
+                .detect()
+                .unwrap_or_else(|e| error!("cannot detect interfaces: {}", e));
+            eth_plugged_in = ifaces
+                .eth()
+                .map_or(false, |eth| eth.is_plugged_in() && self.wired_auto(&eth));
             wifi_plugged_in = ifaces.wlan().map_or(false, |wlan| wlan.is_plugged_in());
         }
 
@@ -312,16 +821,17 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
             ConnectionInfo::NotConnected => {
                 if eth_plugged_in {
                     return CouldConnect::Connect(ConnectionSetting::Ethernet);
-                } else {
+                } else if self.hotspot.lock().unwrap().is_none() {
                     if let Ok(networks) = self.networks.lock() {
                         if networks.len() == 0 {
                             return CouldConnect::Rescan;
                         }
                         for n in networks.iter() {
                             if let NetworkInfo::Wifi(ref essid, ..) = n {
-                                if let Some(ref known) = known_networks.get(essid) {
-                                    if known.auto {
-                                        return CouldConnect::Connect(known.to_setting(essid));
+                                if let Some(ref known) = known_networks.get(&essid.key()) {
+                                    let setting = known.to_setting(essid);
+                                    if known.auto && self.usable(&setting) {
+                                        return CouldConnect::Connect(setting);
                                     }
                                 }
                             }
@@ -364,8 +874,6 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
     }
 
     pub fn scan(&mut self) {
-        use std::str;
-
         let mut networks = NetworkList::new();
         let ifaces = self.ifaces.lock().unwrap().clone();
         if let Some(eth) = ifaces.eth() {
@@ -374,63 +882,538 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
             }
         }
 
-        if let Some(wlan) = ifaces.wlan() {
+        let hotspot = self.hotspot_iface();
+        if let Some(wlan) = ifaces.wlan().filter(|wlan| hotspot.as_ref() != Some(wlan)) {
             let down = !wlan.is_up();
             if down {
                 wlan.up();
             }
+            // probe requests of an idle interface should not give the device away
+            let idle = !matches!(
+                *self.current.read().unwrap(),
+                ConnectionInfo::Wifi(..) | ConnectionInfo::ConnectingWifi(_)
+            );
+            let name = wlan.to_string();
+            if idle && !self.fixed_macs.lock().unwrap().contains(&name) {
+                match self.change_mac(&wlan, &support::random_mac()) {
+                    Ok(()) => {}
+                    // asking again on every scan would only bounce the link
+                    Err(Error::NotAllowed(_)) => {
+                        info!(interface = name.as_str(); "driver keeps its address, scanning with it");
+                        self.fixed_macs.lock().unwrap().insert(name);
+                    }
+                    Err(e) => warn!("cannot randomize address for scanning: {}", e),
+                }
+            }
 
-            let output = wlan.scan();
+            let found = wlan.scan().unwrap_or_else(|e| {
+                warn!("Scan failed: {}", e);
+                vec![]
+            });
 
             if down {
                 wlan.down();
             }
 
-            let mut quality: u32;
-            let mut essid: String;
-            let mut enc: bool;
-            for chunk in output.split(&format!("(on {})", wlan)) {
-                quality = 0;
-                enc = true;
-                essid = "".to_string();
-                if let Some(ref caps) = parse(Parsers::NetworkQuality, chunk) {
-                    quality = support::dbm2perc(
-                        caps.get(1)
-                            .unwrap()
-                            .as_str()
-                            .parse::<i32>()
-                            .expect("should be a value"),
-                    );
+            for ap in found.iter() {
+                Self::add_wifi_network(&mut networks, ap.to_network());
+            }
+            networks.as_mut_slice().sort();
+            let mut found = found;
+            found.sort_by_key(|ap| std::cmp::Reverse(ap.signal));
+            *self.access_points.lock().unwrap() = found;
+        }
+        *self.networks.lock().unwrap() = networks.clone();
+        self.signal(SignalMsg::NetworkList(networks));
+    }
+
+    pub fn check_connectivity(&mut self) {
+        let connection = self.current.read().unwrap().clone();
+        if let ConnectionInfo::Ethernet(_) | ConnectionInfo::Wifi(..) = connection {
+            let connectivity = self.checker.check();
+            self.change_connectivity(connectivity);
+        }
+    }
+
+    pub fn connectivity_check_interval(&self) -> Option<u64> {
+        if self.checker.enabled() {
+            Some(self.checker.interval())
+        } else {
+            None
+        }
+    }
+
+    pub fn roaming_check_interval(&self) -> u64 {
+        self.roamer.lock().unwrap().interval()
+    }
+
+    pub fn check_roaming(&mut self) {
+        if self.state() != State::Connected {
+            return;
+        }
+        let watched = self
+            .roamer
+            .lock()
+            .unwrap()
+            .watched_iface()
+            .map(str::to_owned);
+        let wlan = self
+            .ifaces
+            .lock()
+            .unwrap()
+            .wlan()
+            .filter(|wlan| Some(wlan.to_string()) == watched);
+        let (wlan, station) = match wlan.and_then(|wlan| wlan.station().map(|s| (wlan, s))) {
+            Some(found) => found,
+            None => return,
+        };
+        let now = time::Instant::now();
+        if self.roamer.lock().unwrap().weak(&station, now) {
+            self.scan();
+        }
+        let candidates = self.get_access_points(&station.essid);
+        let roam = self
+            .roamer
+            .lock()
+            .unwrap()
+            .check(&station, &candidates, now);
+        match roam {
+            Some(Roam::Moved { iface, from }) => {
+                self.signal(SignalMsg::Roamed(RoamEvent {
+                    iface,
+                    essid: station.essid.clone(),
+                    from,
+                    to: station.bssid.clone(),
+                }));
+                let current = self.current.read().unwrap().clone();
+                if let ConnectionInfo::Wifi(essid, _, enc, ip, _) = current {
+                    let (quality, band) = (station.quality(), station.band());
+                    self.change_state(ConnectionInfo::Wifi(essid, quality, enc, ip, band));
                 }
+            }
+            Some(Roam::Better { iface, bssid }) => {
+                info!(interface = iface.as_str(); "Roaming from {} to {}", station.bssid, bssid);
+                wlan.roam(&bssid)
+                    .unwrap_or_else(|e| warn!(interface = iface.as_str(); "Roam failed: {}", e));
+            }
+            None => {}
+        }
+    }
 
-                if let Some(ref caps) = parse(Parsers::NetworkEssid, chunk) {
-                    let parsed = support::parse_essid(caps.get(1).unwrap().as_str());
-                    let decoded = str::from_utf8(&parsed);
-                    if let Ok(value) = decoded {
-                        essid = value.to_string();
-                    }
+    fn link_changed(last: &LinkStats, stats: &LinkStats) -> bool {
+        let moved = |last: u32, now: u32| {
+            u64::from(last.abs_diff(now)) * 100 >= u64::from(last.max(1)) * BITRATE_STEP_PERCENT
+        };
+        (last.signal - stats.signal).abs() >= SIGNAL_STEP
+            || moved(last.tx_bitrate, stats.tx_bitrate)
+            || moved(last.rx_bitrate, stats.rx_bitrate)
+    }
+
+    pub fn set_mac_secret(&self, secret: Vec<u8>) {
+        *self.mac_secret.write().unwrap() = secret;
+    }
+
+    // the hardware address of the interface in use
+    pub fn active_mac(&self) -> Option<String> {
+        let ifaces = self.ifaces.lock().unwrap();
+        let iface = match *self.current.read().unwrap() {
+            ConnectionInfo::Ethernet(_) | ConnectionInfo::ConnectingEth => ifaces.eth(),
+            ConnectionInfo::Wifi(..) | ConnectionInfo::ConnectingWifi(_) => ifaces.wlan(),
+            ConnectionInfo::NotConnected => None,
+        };
+        iface.and_then(|iface| iface.mac())
+    }
+
+    fn link_iface(&self) -> Option<Interface> {
+        if let ConnectionInfo::Wifi(..) = *self.current.read().unwrap() {
+            return self.ifaces.lock().unwrap().wlan();
+        }
+        None
+    }
+
+    pub fn link_stats(&self) -> Result<(String, LinkStats)> {
+        let wlan = self.link_iface().ok_or(Error::NoInterface)?;
+        let stats = wlan
+            .link_stats()
+            .ok_or_else(|| Error::NotPluggedIn(wlan.to_string()))?;
+        Ok((wlan.to_string(), stats))
+    }
+
+    pub fn check_link(&mut self) {
+        if self.state() != State::Connected {
+            return;
+        }
+        let (iface, stats) = match self.link_stats() {
+            Ok(found) => found,
+            Err(_) => return,
+        };
+        let changed = {
+            let mut last = self.link.lock().unwrap();
+            let changed = last
+                .as_ref()
+                .is_none_or(|last| Self::link_changed(last, &stats));
+            if changed {
+                *last = Some(stats.clone());
+            }
+            changed
+        };
+        if changed {
+            self.signal(SignalMsg::LinkStats(iface, stats.clone()));
+        }
+        let current = self.current.read().unwrap().clone();
+        if let ConnectionInfo::Wifi(essid, quality, enc, ip, band) = current {
+            if quality.abs_diff(stats.quality()) >= QUALITY_STEP {
+                self.change_state(ConnectionInfo::Wifi(essid, stats.quality(), enc, ip, band));
+            }
+        }
+    }
+
+    fn active_iface(&self) -> Option<Interface> {
+        let ifaces = self.ifaces.lock().unwrap();
+        match *self.current.read().unwrap() {
+            ConnectionInfo::Ethernet(_) => ifaces.eth(),
+            ConnectionInfo::Wifi(..) => ifaces.wlan(),
+            _ => None,
+        }
+    }
+
+    // an empty name stands for the interface of the current connection
+    pub fn statistics(&self, name: &str) -> Result<(String, Statistics)> {
+        let active = self.active_iface();
+        let iface = if name.is_empty() {
+            active.clone().ok_or(Error::NoInterface)?
+        } else {
+            self.ifaces
+                .lock()
+                .unwrap()
+                .by_name(name)
+                .ok_or_else(|| Error::NoDevice(name.to_owned()))?
+        };
+        let name = iface.to_string();
+        let mut stats = iface
+            .statistics()
+            .ok_or_else(|| Error::NoDevice(name.clone()))?;
+        let mut traffic = self.traffic.lock().unwrap();
+        let delta = traffic.sample(&name, &mut stats, time::Instant::now());
+        if let ConnectionInfo::Wifi(ref essid, ..) = *self.current.read().unwrap() {
+            if active.is_some_and(|active| active == iface) {
+                traffic.account(&essid.key(), delta, traffic::month(time::SystemTime::now()));
+            }
+        }
+        Ok((name, stats))
+    }
+
+    pub fn check_statistics(&mut self) {
+        if self.state() != State::Connected {
+            return;
+        }
+        if let Ok((iface, stats)) = self.statistics("") {
+            self.signal(SignalMsg::Statistics(iface, stats));
+        }
+    }
+
+    pub fn usage(&self, essid: &Ssid) -> DataUsage {
+        let month = traffic::month(time::SystemTime::now());
+        self.traffic.lock().unwrap().usage(&essid.key(), month)
+    }
+
+    pub fn reset_usage(&self, essid: &Ssid) {
+        self.traffic.lock().unwrap().reset(&essid.key());
+    }
+
+    pub fn set_usage_totals(&self, usage: UsageTotals) {
+        self.traffic.lock().unwrap().set_totals(usage);
+    }
+
+    // the totals to save, if they changed since the last call
+    pub fn usage_changes(&self) -> Option<UsageTotals> {
+        self.traffic.lock().unwrap().take_totals()
+    }
+
+    // a profile setting wins over what the dhcp server says
+    pub fn check_metered(&mut self, known: &KnownNetworks) {
+        let detected = self.detected_metered.load(Ordering::Relaxed);
+        let current = self.current.read().unwrap().clone();
+        let state = match current {
+            ConnectionInfo::Wifi(ref essid, ..) => {
+                let profile = known.get(&essid.key());
+                let over_cap = profile
+                    .and_then(|profile| profile.data_cap)
+                    .is_some_and(|cap| cap <= self.usage(essid).month_total());
+                MeteredState {
+                    metered: profile
+                        .and_then(|profile| profile.metered)
+                        .unwrap_or(detected),
+                    over_cap,
                 }
+            }
+            ConnectionInfo::Ethernet(_) => MeteredState {
+                metered: detected,
+                over_cap: false,
+            },
+            _ => MeteredState::default(),
+        };
+        let changed = {
+            let mut last = self.metered.write().unwrap();
+            let changed = *last != state;
+            *last = state;
+            changed
+        };
+        if changed {
+            self.signal(SignalMsg::MeteredChanged(state));
+        }
+    }
 
-                if let Some(ref caps) = parse(Parsers::NetworkEnc, chunk) {
-                    if caps.get(1).unwrap().as_str().matches("Privacy").count() == 0 {
-                        enc = false;
-                    }
+    pub fn metered(&self) -> MeteredState {
+        *self.metered.read().unwrap()
+    }
+
+    // credentials stored for the port's MAC win over those for its name
+    fn wired_auth(&self, iface: &Interface) -> Option<WiredAuth> {
+        let wired = self.wired.read().unwrap();
+        iface
+            .mac()
+            .and_then(|mac| wired.get(&mac))
+            .or_else(|| wired.get(&iface.to_string()))
+            .cloned()
+    }
+
+    // the key credentials of a port are stored under, an empty port
+    // stands for the MAC of the ethernet interface
+    pub fn wired_port(&self, port: &str) -> Result<String> {
+        if !port.is_empty() {
+            return Ok(if AccessPoint::valid_bssid(port) {
+                port.to_ascii_lowercase()
+            } else {
+                port.to_owned()
+            });
+        }
+        let eth = self
+            .ifaces
+            .lock()
+            .unwrap()
+            .eth()
+            .ok_or(Error::NoInterface)?;
+        Ok(eth.mac().unwrap_or_else(|| eth.to_string()))
+    }
+
+    pub fn wired_credentials(&self) -> WiredCredentials {
+        self.wired.read().unwrap().clone()
+    }
+
+    pub fn set_wired_credentials(&self, credentials: WiredCredentials) {
+        *self.wired.write().unwrap() = credentials;
+    }
+
+    pub fn set_wired_auth(&self, port: &str, auth: Option<WiredAuth>) {
+        let mut wired = self.wired.write().unwrap();
+        match auth {
+            Some(mut auth) => {
+                auth.keep_secrets(wired.get(port));
+                wired.insert(port.to_owned(), auth)
+            }
+            None => wired.remove(port),
+        };
+    }
+
+    pub fn wired_profiles(&self) -> WiredProfiles {
+        self.wired_profiles.read().unwrap().clone()
+    }
+
+    pub fn set_wired_profiles(&self, profiles: WiredProfiles) {
+        *self.wired_profiles.write().unwrap() = profiles;
+    }
+
+    pub fn set_wired_profile(&self, name: &str, profile: Option<WiredProfile>) {
+        let mut profiles = self.wired_profiles.write().unwrap();
+        match profile {
+            Some(profile) => profiles.insert(name.to_owned(), profile),
+            None => profiles.remove(name),
+        };
+    }
+
+    // a port without a profile connects on its own
+    fn wired_auto(&self, eth: &Interface) -> bool {
+        let profiles = self.wired_profiles.read().unwrap();
+        let mac = eth.mac();
+        match_wired(&profiles, &eth.to_string(), mac.as_deref(), None)
+            .is_none_or(|(_, profile)| profile.auto)
+    }
+
+    pub fn active_wired_profile(&self) -> Option<String> {
+        self.wired_profile.read().unwrap().clone()
+    }
+
+    pub fn start_hotspot(&mut self, setting: HotspotSetting) -> Result<()> {
+        if !setting.valid() {
+            return Err(Error::NotAllowed(format!(
+                "hotspot {} with this password or band",
+                setting.essid
+            )));
+        }
+        let (wlan, uplink) = {
+            let ifaces = self.ifaces.lock().unwrap();
+            (
+                ifaces.wlan().ok_or(Error::NoInterface)?,
+                ifaces.eth().ok_or(Error::NoInterface)?,
+            )
+        };
+        if !uplink.is_plugged_in() {
+            return Err(Error::NotPluggedIn(uplink.to_string()));
+        }
+        let replaced = {
+            let mut hosting = self.hotspot.lock().unwrap();
+            if let Some(Hosting::Starting(_)) = *hosting {
+                return Err(Error::NotAllowed(
+                    "a hotspot while another one starts".to_owned(),
+                ));
+            }
+            hosting.replace(Hosting::Starting(wlan.clone()))
+        };
+        let restarted = match replaced {
+            Some(Hosting::Running(hotspot)) => {
+                let was_up = hotspot.was_up;
+                Some((Self::shut_down(*hotspot), was_up))
+            }
+            _ => None,
+        };
+        let on_wifi = match &*self.current.read().unwrap() {
+            ConnectionInfo::Wifi(essid, ..) | ConnectionInfo::ConnectingWifi(essid) => {
+                Some(essid.clone())
+            }
+            _ => None,
+        };
+        if on_wifi.is_some() {
+            self.cancel_active();
+            self.disconnect();
+            // disconnect leaves the reserved interface alone
+            wlan.disconnect();
+        }
+        let (previous, was_up) = match restarted {
+            Some((previous, was_up)) => (previous.or(on_wifi), was_up),
+            None => (on_wifi, wlan.is_up()),
+        };
+
+        let forwarding = match self.bring_up_hotspot(&wlan, &uplink, &setting) {
+            Ok(forwarding) => forwarding,
+            Err(e) => {
+                wlan.disconnect();
+                if !was_up {
+                    wlan.down();
                 }
+                *self.hotspot.lock().unwrap() = None;
+                return Err(e);
+            }
+        };
+        let serving = Arc::new(AtomicBool::new(true));
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let listed = clients.clone();
+        wlan.serve_dhcp(HOTSPOT_ADDRESS, serving.clone(), move |current| {
+            *listed.lock().unwrap() = current;
+        });
+        info!(
+            interface = wlan.to_string().as_str(), uplink = uplink.to_string().as_str();
+            "hotspot {} started", setting.essid
+        );
+        *self.hotspot.lock().unwrap() = Some(Hosting::Running(Box::new(Hotspot {
+            setting,
+            iface: wlan,
+            uplink,
+            forwarding,
+            serving,
+            clients,
+            previous,
+            was_up,
+        })));
+        Ok(())
+    }
 
-                if !essid.is_empty() {
-                    Self::add_wifi_network(&mut networks, NetworkInfo::Wifi(essid, quality, enc));
+    // whether forwarding was on before, as share reports it
+    fn bring_up_hotspot(
+        &self,
+        wlan: &Interface,
+        uplink: &Interface,
+        setting: &HotspotSetting,
+    ) -> Result<bool> {
+        wlan.up();
+        let config = support::gen_hotspot_config(setting)?;
+        let started = wlan
+            .start_supplicant(Driver::Nl80211, &config)
+            .and_then(|_| self.wait_for_auth(wlan, ASSOC_MAX_TRIES));
+        fs::remove_file(Path::new(&config)).unwrap_or_default();
+        if !started? {
+            return Err(Error::AssocFail(setting.essid.to_string()));
+        }
+        wlan.configure(HOTSPOT_ADDRESS, None)?;
+        wlan.share(HOTSPOT_SUBNET, uplink)
+    }
+
+    // the wifi network given up for the hotspot, if any
+    pub fn stop_hotspot(&mut self) -> Option<Ssid> {
+        let hotspot = {
+            let mut hosting = self.hotspot.lock().unwrap();
+            match hosting.take() {
+                Some(Hosting::Running(hotspot)) => *hotspot,
+                starting => {
+                    *hosting = starting;
+                    return None;
                 }
             }
-            networks.as_mut_slice().sort();
+        };
+        Self::shut_down(hotspot)
+    }
+
+    fn shut_down(hotspot: Hotspot) -> Option<Ssid> {
+        hotspot.serving.store(false, Ordering::SeqCst);
+        hotspot
+            .iface
+            .unshare(HOTSPOT_SUBNET, &hotspot.uplink, hotspot.forwarding);
+        hotspot.iface.disconnect();
+        if !hotspot.was_up {
+            hotspot.iface.down();
+        }
+        info!(interface = hotspot.iface.to_string().as_str(); "hotspot {} stopped", hotspot.setting.essid);
+        hotspot.previous
+    }
+
+    // the wifi network a hotspot started now would take over
+    pub fn given_up_wifi(&self) -> Option<Ssid> {
+        if let Some(Hosting::Running(hotspot)) = &*self.hotspot.lock().unwrap() {
+            return hotspot.previous.clone();
+        }
+        match &*self.current.read().unwrap() {
+            ConnectionInfo::Wifi(essid, ..) | ConnectionInfo::ConnectingWifi(essid) => {
+                Some(essid.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // the setting and the interface it runs on
+    pub fn hotspot(&self) -> Option<(HotspotSetting, String)> {
+        match &*self.hotspot.lock().unwrap() {
+            Some(Hosting::Running(hotspot)) => {
+                Some((hotspot.setting.clone(), hotspot.iface.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn hotspot_clients(&self) -> Vec<HotspotClient> {
+        match &*self.hotspot.lock().unwrap() {
+            Some(Hosting::Running(hotspot)) => hotspot.clients.lock().unwrap().clone(),
+            _ => Vec::new(),
         }
-        *self.networks.lock().unwrap() = networks.clone();
-        self.signal(SignalMsg::NetworkList(networks));
     }
 
     pub fn allow_reconnect(&self) -> bool {
         !self.current.read().unwrap().wired()
     }
 
+    pub fn state(&self) -> State {
+        self.machine.lock().unwrap().state()
+    }
+
     pub fn current_state(&self) -> ConnectionInfo {
         self.current.read().unwrap().clone()
     }
@@ -438,4 +1421,26 @@ impl<SignalHandler: FnMut(SignalMsg)> Connection<SignalHandler> {
     pub fn get_networks(&self) -> NetworkList {
         self.networks.lock().unwrap().clone()
     }
+
+    pub fn get_access_points(&self, essid: &Ssid) -> Vec<AccessPoint> {
+        self.access_points
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|ap| ap.essid == *essid)
+            .cloned()
+            .collect()
+    }
+
+    pub fn connectivity(&self) -> Connectivity {
+        *self.connectivity.read().unwrap()
+    }
+
+    pub fn online_watcher(&self) -> OnlineWatcher {
+        OnlineWatcher::new(
+            self.ifaces.clone(),
+            self.current.clone(),
+            self.connectivity.clone(),
+        )
+    }
 }
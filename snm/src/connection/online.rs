@@ -0,0 +1,83 @@
+use super::connectivity::Connectivity;
+use super::interfaces::{Interface, Interfaces};
+use super::types::ConnectionInfo;
+
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::{thread, time};
+
+const WAIT_ONLINE_POLL_INTERVAL_MS: u64 = 250;
+
+pub enum OnlineCriteria {
+    Any,
+    Interface(String),
+    Ipv4,
+    Ipv6,
+    Connectivity,
+}
+
+#[derive(Clone)]
+pub struct OnlineWatcher {
+    ifaces: Arc<Mutex<Interfaces>>,
+    current: Arc<RwLock<ConnectionInfo>>,
+    connectivity: Arc<RwLock<Connectivity>>,
+}
+
+impl OnlineWatcher {
+    pub fn new(
+        ifaces: Arc<Mutex<Interfaces>>,
+        current: Arc<RwLock<ConnectionInfo>>,
+        connectivity: Arc<RwLock<Connectivity>>,
+    ) -> Self {
+        OnlineWatcher {
+            ifaces,
+            current,
+            connectivity,
+        }
+    }
+
+    fn active_interface(&self, info: &ConnectionInfo) -> Option<Interface> {
+        let ifaces = self.ifaces.lock().unwrap();
+        match info {
+            ConnectionInfo::Ethernet(_) => ifaces.eth(),
+            ConnectionInfo::Wifi(..) => ifaces.wlan(),
+            _ => None,
+        }
+    }
+
+    pub fn online(&self, criteria: &OnlineCriteria) -> bool {
+        let info = self.current.read().unwrap().clone();
+        let ip = match info {
+            ConnectionInfo::Ethernet(ref ip) | ConnectionInfo::Wifi(_, _, _, ref ip, _) => ip,
+            _ => return false,
+        };
+        match criteria {
+            OnlineCriteria::Any => true,
+            OnlineCriteria::Interface(name) => self
+                .active_interface(&info)
+                .is_some_and(|iface| iface.to_string() == *name),
+            OnlineCriteria::Ipv4 => ip
+                .parse::<Ipv4Addr>()
+                .is_ok_and(|addr| !addr.is_unspecified()),
+            OnlineCriteria::Ipv6 => self
+                .active_interface(&info)
+                .is_some_and(|iface| iface.has_global_ipv6()),
+            OnlineCriteria::Connectivity => {
+                *self.connectivity.read().unwrap() == Connectivity::Full
+            }
+        }
+    }
+
+    pub fn wait(&self, criteria: &OnlineCriteria, timeout: time::Duration) -> bool {
+        let start = time::Instant::now();
+        loop {
+            if self.online(criteria) {
+                return true;
+            }
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(time::Duration::from_millis(WAIT_ONLINE_POLL_INTERVAL_MS));
+        }
+    }
+}
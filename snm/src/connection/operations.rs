@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+pub type OperationId = u32;
+
+#[derive(PartialEq, Debug)]
+pub enum Cancel {
+    Active,
+    Pending,
+    Unknown,
+}
+
+#[derive(Default)]
+struct State {
+    last: OperationId,
+    active: Option<OperationId>,
+    pending: HashSet<OperationId>,
+    cancelled: HashSet<OperationId>,
+}
+
+#[derive(Clone, Default)]
+pub struct Operations {
+    state: Arc<Mutex<State>>,
+}
+
+impl Operations {
+    pub fn start(&self) -> OperationId {
+        let mut state = self.state.lock().unwrap();
+        // 0 is reserved for status changes not caused by any operation
+        state.last = state.last.checked_add(1).unwrap_or(1);
+        let op = state.last;
+        state.pending.insert(op);
+        op
+    }
+
+    // returns false if the operation was canceled while pending
+    pub fn begin(&self, op: OperationId) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.pending.remove(&op);
+        if state.cancelled.remove(&op) {
+            return false;
+        }
+        state.active = Some(op);
+        true
+    }
+
+    pub fn finish(&self, op: OperationId) {
+        let mut state = self.state.lock().unwrap();
+        state.pending.remove(&op);
+        state.cancelled.remove(&op);
+        if state.active == Some(op) {
+            state.active = None;
+        }
+    }
+
+    pub fn active(&self) -> Option<OperationId> {
+        self.state.lock().unwrap().active
+    }
+
+    pub fn cancel(&self, op: OperationId) -> Cancel {
+        let mut state = self.state.lock().unwrap();
+        if state.active == Some(op) {
+            Cancel::Active
+        } else if state.pending.contains(&op) {
+            state.cancelled.insert(op);
+            Cancel::Pending
+        } else {
+            Cancel::Unknown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifecycle() {
+        let ops = Operations::default();
+        let first = ops.start();
+        let second = ops.start();
+        assert_ne!(first, second);
+        assert_ne!(first, 0);
+
+        assert!(ops.begin(first));
+        assert_eq!(ops.active(), Some(first));
+        assert_eq!(ops.cancel(first), Cancel::Active);
+        assert_eq!(ops.cancel(second), Cancel::Pending);
+        ops.finish(first);
+        assert_eq!(ops.active(), None);
+
+        assert!(!ops.begin(second));
+        ops.finish(second);
+        assert_eq!(ops.cancel(second), Cancel::Unknown);
+        assert_eq!(ops.active(), None);
+    }
+}
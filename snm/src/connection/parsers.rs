@@ -5,17 +5,84 @@ pub enum Parsers {
     NetworkQuality,
     NetworkEnc,
     NetworkEssid,
+    Bssid,
+    Frequency,
+    LastSeen,
+    ConnectedTo,
+    TxBitrate,
+    RxBitrate,
+    ConnectedTime,
 }
 
 lazy_static! {
     static ref PARSERS: Vec<Regex> = vec![
         Regex::new(r".*wpa_state=(.*?)\n").unwrap(),
-        Regex::new(r".*signal: ([^\.]+)\.").unwrap(),
+        Regex::new(r".*signal:\s*(-?[0-9]+)").unwrap(),
         Regex::new(r".*capability: ([^\n]*)\n").unwrap(),
-        Regex::new(r".*SSID: ([^\n]*)\n").unwrap()
+        Regex::new(r".*SSID: ([^\n]*)\n").unwrap(),
+        Regex::new(r"BSS ([0-9a-fA-F:]{17})$").unwrap(),
+        Regex::new(r"freq: ([0-9]+)").unwrap(),
+        Regex::new(r"last seen: ([0-9]+) ms ago").unwrap(),
+        Regex::new(r"Connected to ([0-9a-fA-F:]{17})").unwrap(),
+        Regex::new(r"tx bitrate:\s*([0-9]+(?:\.[0-9]+)?) MBit/s").unwrap(),
+        Regex::new(r"rx bitrate:\s*([0-9]+(?:\.[0-9]+)?) MBit/s").unwrap(),
+        Regex::new(r"connected time:\s*([0-9]+) seconds").unwrap(),
     ];
 }
 
 pub fn parse(parser: Parsers, text: &str) -> Option<Captures> {
     PARSERS[parser as usize].captures(text)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn all() -> Vec<Parsers> {
+        vec![
+            Parsers::WpaState,
+            Parsers::NetworkQuality,
+            Parsers::NetworkEnc,
+            Parsers::NetworkEssid,
+            Parsers::Bssid,
+            Parsers::Frequency,
+            Parsers::LastSeen,
+            Parsers::ConnectedTo,
+            Parsers::TxBitrate,
+            Parsers::RxBitrate,
+            Parsers::ConnectedTime,
+        ]
+    }
+
+    #[test]
+    fn link_signal_without_fraction() {
+        let output = include_str!("../../fuzz/corpus/parsers/link.txt");
+        assert_eq!(&parse(Parsers::NetworkQuality, output).unwrap()[1], "-44");
+        assert_eq!(&parse(Parsers::NetworkEssid, output).unwrap()[1], "home");
+        let status = include_str!("../../fuzz/corpus/parsers/wpa_status.txt");
+        assert_eq!(&parse(Parsers::WpaState, status).unwrap()[1], "COMPLETED");
+    }
+
+    proptest! {
+        #[test]
+        fn parsers_are_total(text in any::<String>()) {
+            for parser in all() {
+                parse(parser, &text);
+            }
+        }
+
+        #[test]
+        fn signal_is_captured(dbm in -120i32..0, fraction in "(\\.[0-9]{2})?") {
+            let text = format!("\tfreq: 2412\n\tsignal: {}{} dBm\n", dbm, fraction);
+            let caps = parse(Parsers::NetworkQuality, &text).unwrap();
+            prop_assert_eq!(caps[1].parse::<i32>(), Ok(dbm));
+        }
+
+        #[test]
+        fn essid_is_captured(essid in "[^\n:]*") {
+            let text = format!("\tcapability: ESS (0x0001)\n\tSSID: {}\n\tfreq: 2412\n", essid);
+            prop_assert_eq!(&parse(Parsers::NetworkEssid, &text).unwrap()[1], essid.as_str());
+        }
+    }
+}
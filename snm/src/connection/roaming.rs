@@ -0,0 +1,221 @@
+use super::types::{AccessPoint, Station};
+use std::time;
+
+#[derive(Clone, Deserialize)]
+pub struct RoamingSettings {
+    // snm still reports roams done by the supplicant when disabled
+    #[serde(default = "RoamingSettings::default_enabled")]
+    pub enabled: bool,
+    // dB a bss must beat the current one by
+    #[serde(default = "RoamingSettings::default_hysteresis")]
+    pub hysteresis: i32,
+    #[serde(default = "RoamingSettings::default_interval")]
+    pub interval: u64,
+    // seconds to stay on a bss after a roam
+    #[serde(default = "RoamingSettings::default_holdoff")]
+    pub holdoff: u64,
+}
+
+impl RoamingSettings {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_hysteresis() -> i32 {
+        8
+    }
+
+    fn default_interval() -> u64 {
+        10
+    }
+
+    fn default_holdoff() -> u64 {
+        60
+    }
+}
+
+impl Default for RoamingSettings {
+    fn default() -> Self {
+        RoamingSettings {
+            enabled: Self::default_enabled(),
+            hysteresis: Self::default_hysteresis(),
+            interval: Self::default_interval(),
+            holdoff: Self::default_holdoff(),
+        }
+    }
+}
+
+// the association being watched for roams
+struct Watched {
+    iface: String,
+    bssid: String,
+    // roam below this signal, only report roams if None
+    threshold: Option<i32>,
+    freq_list: Option<Vec<u32>>,
+}
+
+pub enum Roam {
+    // the station moved to another bss on its own
+    Moved { iface: String, from: String },
+    // a better bss to move to
+    Better { iface: String, bssid: String },
+}
+
+pub struct Roamer {
+    settings: RoamingSettings,
+    watched: Option<Watched>,
+    last_roam: Option<time::Instant>,
+}
+
+impl Roamer {
+    pub fn new(settings: RoamingSettings) -> Self {
+        Roamer {
+            settings,
+            watched: None,
+            last_roam: None,
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.settings.interval
+    }
+
+    pub fn watch(&mut self, iface: &str, threshold: Option<i32>, freq_list: Option<Vec<u32>>) {
+        self.watched = Some(Watched {
+            iface: iface.to_owned(),
+            bssid: String::new(),
+            threshold,
+            freq_list,
+        });
+    }
+
+    pub fn unwatch(&mut self) {
+        self.watched = None;
+    }
+
+    pub fn watched_iface(&self) -> Option<&str> {
+        self.watched.as_ref().map(|watched| watched.iface.as_str())
+    }
+
+    // whether the current bss is weak enough to look for another one
+    pub fn weak(&self, station: &Station, now: time::Instant) -> bool {
+        let threshold = match self.watched {
+            Some(Watched {
+                threshold: Some(threshold),
+                ..
+            }) if self.settings.enabled => threshold,
+            _ => return false,
+        };
+        let settled = self.last_roam.is_none_or(|at| {
+            now.duration_since(at) >= time::Duration::from_secs(self.settings.holdoff)
+        });
+        settled && station.signal.is_some_and(|signal| signal < threshold)
+    }
+
+    pub fn check(
+        &mut self,
+        station: &Station,
+        candidates: &[AccessPoint],
+        now: time::Instant,
+    ) -> Option<Roam> {
+        let weak = self.weak(station, now);
+        let hysteresis = self.settings.hysteresis;
+        let watched = self.watched.as_mut()?;
+        if watched.bssid.is_empty() {
+            watched.bssid = station.bssid.clone();
+        }
+        if watched.bssid != station.bssid {
+            let from = std::mem::replace(&mut watched.bssid, station.bssid.clone());
+            let iface = watched.iface.clone();
+            self.last_roam = Some(now);
+            return Some(Roam::Moved { iface, from });
+        }
+        if !weak {
+            return None;
+        }
+        let current = station.signal?;
+        let best = candidates
+            .iter()
+            .filter(|ap| ap.essid == station.essid && ap.bssid != station.bssid)
+            .filter(|ap| {
+                watched
+                    .freq_list
+                    .as_ref()
+                    .is_none_or(|frequencies| frequencies.contains(&ap.frequency))
+            })
+            .filter(|ap| ap.signal >= current + hysteresis)
+            .max_by_key(|ap| ap.signal)?;
+        self.last_roam = Some(now);
+        Some(Roam::Better {
+            iface: watched.iface.clone(),
+            bssid: best.bssid.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::Security;
+    use super::*;
+
+    fn station(bssid: &str, signal: i32) -> Station {
+        Station {
+            essid: "home".into(),
+            bssid: bssid.to_owned(),
+            frequency: 2412,
+            signal: Some(signal),
+        }
+    }
+
+    fn bss(bssid: &str, signal: i32) -> AccessPoint {
+        AccessPoint {
+            bssid: bssid.to_owned(),
+            essid: "home".into(),
+            frequency: 2412,
+            signal,
+            last_seen: 0,
+            security: Security::Wpa2,
+            capabilities: String::new(),
+        }
+    }
+
+    #[test]
+    fn roams_with_hysteresis_and_holdoff() {
+        let mut roamer = Roamer::new(RoamingSettings::default());
+        roamer.watch("wlan0", Some(-70), None);
+        let now = time::Instant::now();
+        let candidates = [bss("a", -75), bss("b", -70), bss("c", -68)];
+
+        assert!(roamer.check(&station("a", -60), &candidates, now).is_none());
+        // b is not 8 dB better than a
+        let weak = station("a", -76);
+        assert!(roamer.check(&weak, &candidates[..2], now).is_none());
+        assert!(matches!(
+            roamer.check(&weak, &candidates, now),
+            Some(Roam::Better { ref bssid, .. }) if bssid == "c"
+        ));
+        assert!(matches!(
+            roamer.check(&station("c", -68), &candidates, now),
+            Some(Roam::Moved { ref from, .. }) if from == "a"
+        ));
+
+        let later = now + time::Duration::from_secs(30);
+        let weak = station("c", -90);
+        assert!(roamer.check(&weak, &candidates, later).is_none());
+        let later = now + time::Duration::from_secs(61);
+        assert!(roamer.check(&weak, &candidates, later).is_some());
+    }
+
+    #[test]
+    fn only_reports_without_threshold() {
+        let mut roamer = Roamer::new(RoamingSettings::default());
+        roamer.watch("wlan0", None, None);
+        let now = time::Instant::now();
+        let candidates = [bss("b", -40)];
+        assert!(roamer.check(&station("a", -90), &candidates, now).is_none());
+        assert!(matches!(
+            roamer.check(&station("b", -40), &candidates, now),
+            Some(Roam::Moved { .. })
+        ));
+    }
+}
@@ -1,47 +1,123 @@
-use super::types::{ConnectionInfo, ConnectionStatus, NetworkList};
+use super::connectivity::Connectivity;
+use super::types::{
+    ConnectionInfo, ConnectionStatus, DhcpEvent, LinkStats, MeteredState, NetworkList, RoamEvent,
+    Statistics, StatusChange,
+};
+use crate::error::Reason;
+use log::Level;
 
 pub enum SignalMsg {
     NetworkList(NetworkList),
-    ConnectStatusChanged(ConnectionStatus),
+    ConnectStatusChanged(StatusChange),
     StateChanged(ConnectionInfo),
+    ConnectivityChanged(Connectivity),
+    DhcpLease(DhcpEvent),
+    ConnectFinished(u32, Reason),
+    Roamed(RoamEvent),
+    LinkStats(String, LinkStats),
+    Statistics(String, Statistics),
+    MeteredChanged(MeteredState),
 }
 
 impl SignalMsg {
     pub fn log(&self) {
         match self {
             SignalMsg::NetworkList(ref networks) => {
-                println!("Scan complete. Found {} networks", networks.len());
+                info!("Scan complete. Found {} networks", networks.len());
             }
 
-            SignalMsg::ConnectStatusChanged(ref status) => {
-                println!(
-                    "Connect status changed to {}",
-                    match status {
-                        ConnectionStatus::Initializing => "Bringing Interface Up",
-                        ConnectionStatus::Connecting => "Connecting",
-                        ConnectionStatus::Authenticating => "Authenticating",
-                        ConnectionStatus::GettingIP => "Getting ip address",
-                        ConnectionStatus::AuthFail => "Authorization failed",
-                        ConnectionStatus::Aborted => "Connection canceled",
-                        ConnectionStatus::ConnectFail => "Connection failed",
-                    }
+            SignalMsg::ConnectStatusChanged(ref change) => {
+                let text = match change.status {
+                    ConnectionStatus::Initializing => "Bringing Interface Up",
+                    ConnectionStatus::Connecting => "Connecting",
+                    ConnectionStatus::Authenticating => "Authenticating",
+                    ConnectionStatus::GettingIP => "Getting ip address",
+                    ConnectionStatus::AuthFail => "Authorization failed",
+                    ConnectionStatus::Aborted => "Connection canceled",
+                    ConnectionStatus::ConnectFail => "Connection failed",
+                };
+                let level = match change.reason {
+                    Reason::None | Reason::Aborted => Level::Info,
+                    _ => Level::Warn,
+                };
+                log!(
+                    level,
+                    status = text,
+                    reason = change.reason.name(),
+                    ssid = change.essid.to_string().as_str(),
+                    interface = change.iface.as_str(),
+                    op = change.op;
+                    "Connect status changed to {}", text
                 );
             }
             SignalMsg::StateChanged(ref info) => match info {
-                ConnectionInfo::ConnectingEth | ConnectionInfo::ConnectingWifi(_) => {
-                    println!("Connecting")
+                ConnectionInfo::ConnectingEth => info!("Connecting"),
+                ConnectionInfo::ConnectingWifi(ref essid) => {
+                    info!(ssid = essid.to_string().as_str(); "Connecting")
                 }
 
-                ConnectionInfo::NotConnected => println!("Disconnected"),
+                ConnectionInfo::NotConnected => info!("Disconnected"),
 
                 ConnectionInfo::Ethernet(ref ip) => {
-                    println!("Connected to eth: {}", ip);
+                    info!(ip = ip.as_str(); "Connected to eth: {}", ip);
                 }
 
-                ConnectionInfo::Wifi(ref essid, _, _, ref ip) => {
-                    println!("Connected to wifi: {}, ip: {}", essid, ip)
+                ConnectionInfo::Wifi(ref essid, _, _, ref ip, _) => {
+                    info!(ssid = essid.to_string().as_str(), ip = ip.as_str(); "Connected to wifi: {}, ip: {}", essid, ip)
                 }
             },
+            SignalMsg::ConnectivityChanged(ref connectivity) => {
+                info!(
+                    "Connectivity changed to {}",
+                    match connectivity {
+                        Connectivity::Unknown => "unknown",
+                        Connectivity::None => "none",
+                        Connectivity::Portal => "captive portal",
+                        Connectivity::Limited => "limited",
+                        Connectivity::Full => "full",
+                    }
+                );
+            }
+            SignalMsg::DhcpLease(ref event) => match event {
+                DhcpEvent::Bound {
+                    ref iface,
+                    ref address,
+                    ..
+                } => info!(interface = iface.as_str(); "Got dhcp lease on {}: {}", iface, address),
+                DhcpEvent::Lost { ref iface } => {
+                    warn!(interface = iface.as_str(); "Lost dhcp lease on {}", iface)
+                }
+            },
+            SignalMsg::ConnectFinished(op, reason) => {
+                info!(op = op, reason = reason.name(); "Connect operation {} finished: {}", op, reason.name())
+            }
+            SignalMsg::Roamed(ref event) => info!(
+                interface = event.iface.as_str(),
+                ssid = event.essid.to_string().as_str(),
+                from = event.from.as_str(),
+                to = event.to.as_str();
+                "Roamed from {} to {}", event.from, event.to
+            ),
+            SignalMsg::LinkStats(ref iface, ref stats) => debug!(
+                interface = iface.as_str(),
+                signal = stats.signal,
+                tx_bitrate = stats.tx_bitrate,
+                rx_bitrate = stats.rx_bitrate;
+                "Link signal {} dBm", stats.signal
+            ),
+            SignalMsg::Statistics(ref iface, ref stats) => debug!(
+                interface = iface.as_str(),
+                rx_bytes = stats.rx_bytes,
+                tx_bytes = stats.tx_bytes;
+                "Traffic rx {} B/s, tx {} B/s", stats.rx_rate, stats.tx_rate
+            ),
+            SignalMsg::MeteredChanged(ref state) => info!(
+                metered = state.metered,
+                over_cap = state.over_cap;
+                "Metered changed to {}{}",
+                state.metered,
+                if state.over_cap { ", data cap reached" } else { "" }
+            ),
         }
     }
 }
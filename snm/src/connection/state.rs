@@ -0,0 +1,187 @@
+use super::types::ConnectionStatus;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State {
+    Idle,
+    Preparing,
+    Associating,
+    Authenticating,
+    ConfiguringIP,
+    Connected,
+    Disconnecting,
+    Failed,
+}
+
+impl State {
+    pub fn can_transition(self, to: State) -> bool {
+        use State::*;
+        match self {
+            Idle => matches!(to, Preparing | Disconnecting),
+            Preparing => matches!(
+                to,
+                Associating | Authenticating | ConfiguringIP | Failed | Disconnecting
+            ),
+            Associating | Authenticating => matches!(to, ConfiguringIP | Failed | Disconnecting),
+            ConfiguringIP => matches!(to, Connected | Failed | Disconnecting),
+            Connected => matches!(to, Disconnecting),
+            Disconnecting => matches!(to, Idle),
+            Failed => matches!(to, Preparing | Disconnecting),
+        }
+    }
+
+    // an attempt is interrupted when someone else has torn the connection down
+    pub fn interrupted(self) -> bool {
+        matches!(self, State::Idle | State::Disconnecting)
+    }
+
+    pub fn status(self) -> Option<ConnectionStatus> {
+        match self {
+            State::Preparing => Some(ConnectionStatus::Initializing),
+            State::Associating => Some(ConnectionStatus::Connecting),
+            State::Authenticating => Some(ConnectionStatus::Authenticating),
+            State::ConfiguringIP => Some(ConnectionStatus::GettingIP),
+            _ => None,
+        }
+    }
+}
+
+pub struct StateMachine {
+    state: State,
+}
+
+impl StateMachine {
+    pub fn new() -> Self {
+        StateMachine { state: State::Idle }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    // on success returns the previous state, otherwise the current one
+    pub fn transition(&mut self, to: State) -> Result<State, State> {
+        let from = self.state;
+        if !from.can_transition(to) {
+            return Err(from);
+        }
+        self.state = to;
+        Ok(from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::State::*;
+    use super::*;
+
+    const STATES: [State; 8] = [
+        Idle,
+        Preparing,
+        Associating,
+        Authenticating,
+        ConfiguringIP,
+        Connected,
+        Disconnecting,
+        Failed,
+    ];
+
+    const ALLOWED: [(State, State); 20] = [
+        (Idle, Preparing),
+        (Idle, Disconnecting),
+        (Preparing, Associating),
+        (Preparing, Authenticating),
+        (Preparing, ConfiguringIP),
+        (Preparing, Failed),
+        (Preparing, Disconnecting),
+        (Associating, ConfiguringIP),
+        (Associating, Failed),
+        (Associating, Disconnecting),
+        (Authenticating, ConfiguringIP),
+        (Authenticating, Failed),
+        (Authenticating, Disconnecting),
+        (ConfiguringIP, Connected),
+        (ConfiguringIP, Failed),
+        (ConfiguringIP, Disconnecting),
+        (Connected, Disconnecting),
+        (Disconnecting, Idle),
+        (Failed, Preparing),
+        (Failed, Disconnecting),
+    ];
+
+    fn machine_in(state: State) -> StateMachine {
+        StateMachine { state }
+    }
+
+    #[test]
+    fn every_transition() {
+        for from in STATES.iter() {
+            for to in STATES.iter() {
+                let allowed = ALLOWED.contains(&(*from, *to));
+                let mut machine = machine_in(*from);
+                let result = machine.transition(*to);
+                if allowed {
+                    assert_eq!(result, Ok(*from), "{:?} -> {:?}", from, to);
+                    assert_eq!(machine.state(), *to);
+                } else {
+                    assert_eq!(result, Err(*from), "{:?} -> {:?}", from, to);
+                    assert_eq!(machine.state(), *from);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wifi_attempt() {
+        let mut machine = StateMachine::new();
+        for to in [Preparing, Authenticating, ConfiguringIP, Connected].iter() {
+            assert!(machine.transition(*to).is_ok());
+        }
+        assert_eq!(machine.state(), Connected);
+        assert!(machine.transition(Preparing).is_err());
+        assert!(machine.transition(Disconnecting).is_ok());
+        assert!(machine.transition(Idle).is_ok());
+    }
+
+    #[test]
+    fn interrupted_attempt() {
+        let mut machine = StateMachine::new();
+        machine.transition(Preparing).unwrap();
+        machine.transition(Associating).unwrap();
+        // disconnect from another thread
+        machine.transition(Disconnecting).unwrap();
+        machine.transition(Idle).unwrap();
+        assert_eq!(machine.transition(ConfiguringIP), Err(Idle));
+        assert!(machine.state().interrupted());
+    }
+
+    #[test]
+    fn failed_attempt_can_retry() {
+        let mut machine = machine_in(Authenticating);
+        machine.transition(Failed).unwrap();
+        assert!(!machine.state().interrupted());
+        assert_eq!(machine.transition(Preparing), Ok(Failed));
+    }
+
+    #[test]
+    fn statuses() {
+        assert!(matches!(
+            Preparing.status(),
+            Some(ConnectionStatus::Initializing)
+        ));
+        assert!(matches!(
+            Associating.status(),
+            Some(ConnectionStatus::Connecting)
+        ));
+        assert!(matches!(
+            Authenticating.status(),
+            Some(ConnectionStatus::Authenticating)
+        ));
+        assert!(matches!(
+            ConfiguringIP.status(),
+            Some(ConnectionStatus::GettingIP)
+        ));
+        for state in [Idle, Connected, Disconnecting, Failed].iter() {
+            assert!(state.status().is_none());
+        }
+    }
+}
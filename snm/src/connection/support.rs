@@ -1,84 +1,198 @@
+use super::types::{ConnectionSetting, HotspotSetting, Ssid, WiredAuth};
+use crate::error::{Error, Result};
+use std::os::unix::fs::OpenOptionsExt;
 use std::process::Command;
-use std::{collections::VecDeque, fs, io::Write, str};
+use std::{fs, io::Write};
 
-pub fn run(cmd: &str, err: bool) -> String {
+const EXIT_COMMAND_NOT_FOUND: i32 = 127;
+const MAC_SECRET_LEN: usize = 32;
+
+pub fn run(cmd: &str, err: bool) -> Result<String> {
     let output = Command::new("sh")
         .arg("-c")
         .arg(cmd)
         .output()
-        .expect("failed to execute command");
+        .map_err(|e| Error::Spawn(cmd.to_owned(), e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let status = output.status.code().unwrap_or(-1);
+    if output.status.success() {
+        debug!(command = cmd, exit_status = status; "`{}` exited with {}", cmd, status);
+    } else {
+        warn!(
+            command = cmd, exit_status = status, stderr = stderr.trim_end();
+            "`{}` exited with {}: {}", cmd, status, stderr.trim_end()
+        );
+        if status == EXIT_COMMAND_NOT_FOUND {
+            return Err(Error::ToolMissing(cmd.to_owned()));
+        }
+    }
     if err {
-        str::from_utf8(&output.stderr)
-            .expect("process returned bad output")
-            .to_string()
+        Ok(stderr.into_owned())
     } else {
-        str::from_utf8(&output.stdout)
-            .expect("process returned bad output")
-            .to_string()
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     }
 }
 
-pub fn parse_essid(input: &str) -> Vec<u8> {
-    let mut queue: VecDeque<_> = String::from(input).chars().collect();
+// for commands whose failure the caller has to undo, a non-zero exit is an error
+pub fn run_checked(cmd: &str) -> Result<()> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .output()
+        .map_err(|e| Error::Spawn(cmd.to_owned(), e))?;
+    let status = output.status.code().unwrap_or(-1);
+    if output.status.success() {
+        debug!(command = cmd, exit_status = status; "`{}` exited with {}", cmd, status);
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!(
+            command = cmd, exit_status = status, stderr = stderr.trim_end();
+            "`{}` exited with {}: {}", cmd, status, stderr.trim_end()
+        );
+        if status == EXIT_COMMAND_NOT_FOUND {
+            return Err(Error::ToolMissing(cmd.to_owned()));
+        }
+        Err(Error::CommandFailed(
+            cmd.to_owned(),
+            status,
+            stderr.trim_end().to_owned(),
+        ))
+    }
+}
+
+pub fn parse_essid(input: &str) -> Result<Vec<u8>> {
+    let ill_formed = || Error::IllFormedEssid(input.to_owned());
+    let mut chars = input.chars();
     let mut result = vec![];
-    let mut bytes2 = String::with_capacity(2);
-    while let Some(c) = queue.pop_front() {
+    let mut utf8 = [0; 4];
+    while let Some(c) = chars.next() {
         if c != '\\' {
-            result.push(c as u8);
+            result.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
             continue;
         }
 
-        match queue.pop_front() {
+        match chars.next() {
             Some('t') => result.push(0x9),
             Some('\'') => result.push(0x27),
             Some('\"') => result.push(0x22),
             Some('\\') => result.push(0x5c),
             Some('x') => {
-                bytes2.push(queue.pop_front().expect("Ill-formed string"));
-                bytes2.push(queue.pop_front().expect("Ill-formed string"));
-                result.push(u8::from_str_radix(&bytes2, 16).ok().unwrap());
-                bytes2.clear();
+                let hex = chars
+                    .as_str()
+                    .get(..2)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .ok_or_else(ill_formed)?;
+                result.push(u8::from_str_radix(hex, 16).map_err(|_| ill_formed())?);
+                chars.nth(1);
             }
-            _ => return result,
+            _ => return Ok(result),
         };
     }
-    result
+    Ok(result)
+}
+
+pub fn psk(essid: &[u8], password: &str) -> String {
+    use ring::pbkdf2;
+    let mut result: [u8; 32] = [0; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA1,
+        std::num::NonZeroU32::new(4096).unwrap(),
+        essid,
+        password.as_bytes(),
+        &mut result,
+    );
+    result.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    use ring::rand::{SecureRandom, SystemRandom};
+    let mut bytes = vec![0; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("system random source failed");
+    bytes
+}
+
+// locally administered unicast
+fn local_mac(bytes: &[u8]) -> String {
+    let first = (bytes[0] & 0xfe) | 0x02;
+    std::iter::once(first)
+        .chain(bytes[1..6].iter().copied())
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
 }
 
-fn mktemp() -> String {
-    let output = run("mktemp -u", false);
-    output[0..output.len() - 1].to_string()
+pub fn random_mac() -> String {
+    local_mac(&random_bytes(6))
+}
+
+pub fn new_mac_secret() -> Vec<u8> {
+    random_bytes(MAC_SECRET_LEN)
+}
+
+// the same for an ssid as long as the secret is kept
+pub fn stable_mac(secret: &[u8], essid: &Ssid) -> String {
+    use ring::hmac;
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    local_mac(hmac::sign(&key, essid.as_bytes()).as_ref())
+}
+
+fn mktemp() -> Result<String> {
+    Ok(run("mktemp -u", false)?.trim_end().to_string())
+}
+
+// supplicant configs carry secrets, only root may read them
+fn create_private(filename: &str) -> Result<fs::File> {
+    Ok(fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(filename)?)
+}
+
+// wpa_supplicant takes strings either quoted or as plain hex
+fn wpa_string(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.contains(|c: char| c == '"' || c.is_control()) => {
+            format!("\"{}\"", text)
+        }
+        _ => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
+
+fn wpa_ssid(essid: &Ssid) -> String {
+    wpa_string(essid.as_bytes())
 }
 
 pub fn gen_wpa_config(
-    essid: &str,
-    password: Option<&str>,
-    signal_threshold: Option<i32>,
+    setting: &ConnectionSetting,
+    freq_list: Option<&[u32]>,
+    bssid_hint: Option<&str>,
     roaming_db: &str,
     short_interval: u32,
     long_interval: u32,
-) -> std::io::Result<String> {
-    let filename = mktemp();
-    let mut file = fs::File::create(&filename)?;
-    writeln!(file, "network={{\n\tssid=\"{}\"", essid)?;
-    if let Some(pass) = password {
-        use ring::pbkdf2;
-        let mut result: [u8; 32] = [0; 32];
-        pbkdf2::derive(
-            pbkdf2::PBKDF2_HMAC_SHA1,
-            std::num::NonZeroU32::new(4096).unwrap(),
-            essid.as_bytes(),
-            pass.as_bytes(),
-            &mut result,
-        );
-        write!(file, "\tpsk=")?;
-        for i in result.iter() {
-            write!(file, "{:02x}", i)?;
-        }
+) -> Result<String> {
+    let essid = setting.essid();
+    let filename = mktemp()?;
+    let mut file = create_private(&filename)?;
+    writeln!(file, "network={{\n\tssid={}", wpa_ssid(essid))?;
+    if let Some(bssid) = setting.bssid() {
+        writeln!(file, "\tbssid={}", bssid)?;
+    } else if let Some(bssid) = bssid_hint {
+        writeln!(file, "\tbssid_hint={}", bssid)?;
+    }
+    if let Some(frequencies) = freq_list {
+        let frequencies: Vec<String> = frequencies.iter().map(u32::to_string).collect();
+        writeln!(file, "\tfreq_list={}", frequencies.join(" "))?;
+    }
+    if let Some(pass) = setting.password() {
+        write!(file, "\tpsk={}", psk(essid.as_bytes(), pass))?;
     } else {
         write!(file, "\tkey_mgmt=NONE")?;
     }
-    if let Some(threshold) = signal_threshold {
+    if let Some(threshold) = setting.threshold() {
         write!(
             file,
             "\n\tbgscan=\"learn:{}:{}:{}:{}\"",
@@ -89,6 +203,48 @@ pub fn gen_wpa_config(
     Ok(filename)
 }
 
+pub fn gen_wired_config(auth: &WiredAuth) -> Result<String> {
+    let filename = mktemp()?;
+    let mut file = create_private(&filename)?;
+    writeln!(file, "ap_scan=0\nnetwork={{\n\tkey_mgmt=IEEE8021X")?;
+    writeln!(file, "\teap={}", auth.eap.trim())?;
+    writeln!(file, "\tidentity={}", wpa_string(auth.identity.as_bytes()))?;
+    let optional = [
+        ("anonymous_identity", &auth.anonymous_identity),
+        ("password", &auth.password),
+        ("phase2", &auth.phase2),
+        ("ca_cert", &auth.ca_cert),
+        ("client_cert", &auth.client_cert),
+        ("private_key", &auth.private_key),
+        ("private_key_passwd", &auth.private_key_password),
+    ];
+    for (key, value) in optional.iter() {
+        if let Some(value) = value {
+            writeln!(file, "\t{}={}", key, wpa_string(value.as_bytes()))?;
+        }
+    }
+    writeln!(file, "\teapol_flags=0\n}}")?;
+    Ok(filename)
+}
+
+pub fn gen_hotspot_config(setting: &HotspotSetting) -> Result<String> {
+    let filename = mktemp()?;
+    let mut file = create_private(&filename)?;
+    writeln!(file, "network={{\n\tssid={}", wpa_ssid(&setting.essid))?;
+    writeln!(file, "\tmode=2\n\tfrequency={}", setting.frequency())?;
+    if let Some(pass) = &setting.password {
+        writeln!(
+            file,
+            "\tkey_mgmt=WPA-PSK\n\tproto=RSN\n\tpairwise=CCMP\n\tgroup=CCMP"
+        )?;
+        writeln!(file, "\tpsk={}", psk(setting.essid.as_bytes(), pass))?;
+    } else {
+        writeln!(file, "\tkey_mgmt=NONE")?;
+    }
+    writeln!(file, "}}")?;
+    Ok(filename)
+}
+
 pub fn dbm2perc(dbm: i32) -> u32 {
     if dbm < -92 {
         1
@@ -99,3 +255,130 @@ pub fn dbm2perc(dbm: i32) -> u32 {
         ((-0.0154 * x * x) - (0.3794 * x) + 98.182).round() as u32
     }
 }
+
+// (year, month, day) from days since epoch, see http://howardhinnant.github.io/date_algorithms.html
+pub fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+
+    // the way iw prints an ssid
+    fn escape(essid: &[u8]) -> String {
+        let mut result = String::new();
+        for (i, byte) in essid.iter().enumerate() {
+            let inner_space = *byte == b' ' && i != 0 && i != essid.len() - 1;
+            if (byte.is_ascii_graphic() && *byte != b'\\') || inner_space {
+                result.push(*byte as char);
+            } else {
+                result.push_str(&format!("\\x{:02x}", byte));
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn wpa_ssid_quotes_plain_text_only() {
+        assert_eq!(wpa_ssid(&Ssid::from("home")), "\"home\"");
+        assert_eq!(wpa_ssid(&Ssid::from("say \"hi\"")), "7361792022686922");
+        assert_eq!(wpa_ssid(&Ssid::new(vec![0x63, 0xe9])), "63e9");
+    }
+
+    #[test]
+    fn wired_config_uses_8021x() {
+        let auth = WiredAuth {
+            eap: "PEAP".to_owned(),
+            identity: "alice".to_owned(),
+            password: Some("se\"cret".to_owned()),
+            phase2: Some("auth=MSCHAPV2".to_owned()),
+            ..WiredAuth::default()
+        };
+        let path = gen_wired_config(&auth).unwrap();
+        let config = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(config.starts_with("ap_scan=0\n"));
+        for line in [
+            "\tkey_mgmt=IEEE8021X",
+            "\teap=PEAP",
+            "\tidentity=\"alice\"",
+            "\tpassword=73652263726574\n",
+            "\tphase2=\"auth=MSCHAPV2\"",
+        ]
+        .iter()
+        {
+            assert!(config.contains(line), "{} missing in {}", line, config);
+        }
+        assert!(!config.contains("ca_cert"));
+    }
+
+    #[test]
+    fn checked_failures_carry_status_and_stderr() {
+        assert!(run_checked("true").is_ok());
+        assert!(matches!(
+            run_checked("echo denied >&2; exit 4"),
+            Err(Error::CommandFailed(ref cmd, 4, ref stderr))
+                if cmd == "echo denied >&2; exit 4" && stderr == "denied"
+        ));
+        assert!(matches!(
+            run_checked("exit 127"),
+            Err(Error::ToolMissing(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_escapes_are_ill_formed() {
+        for input in ["\\x", "\\x4", "cut\\x4", "\\xg0", "\\x+1", "\\x\u{e9}0"].iter() {
+            assert!(matches!(
+                parse_essid(input),
+                Err(Error::IllFormedEssid(ref essid)) if essid == input
+            ));
+        }
+    }
+
+    #[test]
+    fn stable_mac_is_local_and_per_ssid() {
+        let home = stable_mac(b"secret", &Ssid::from("home"));
+        assert_eq!(home, stable_mac(b"secret", &Ssid::from("home")));
+        assert_ne!(home, stable_mac(b"secret", &Ssid::from("work")));
+        assert_ne!(home, stable_mac(b"other", &Ssid::from("home")));
+        for mac in [home, random_mac()].iter() {
+            let first = u8::from_str_radix(&mac[..2], 16).unwrap();
+            assert_eq!(first & 0x03, 0x02);
+            assert_eq!(mac.len(), 17);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn parse_essid_is_total(input in any::<String>()) {
+            let _ = parse_essid(&input);
+        }
+
+        #[test]
+        fn parse_essid_reverses_iw_escaping(essid in vec(any::<u8>(), 0..33)) {
+            prop_assert_eq!(parse_essid(&escape(&essid)).unwrap(), essid);
+        }
+
+        #[test]
+        fn parse_essid_keeps_plain_text(text in "[^\\\\]*") {
+            prop_assert_eq!(parse_essid(&text).unwrap(), text.as_bytes());
+        }
+
+        #[test]
+        fn dbm2perc_is_a_percentage(dbm in any::<i32>()) {
+            prop_assert!((1..=100).contains(&dbm2perc(dbm)));
+        }
+    }
+}
@@ -0,0 +1,1060 @@
+use super::*;
+
+type Signals = Arc<Mutex<Vec<SignalMsg>>>;
+
+fn setup(
+    ports: &[&str],
+) -> (
+    Fake,
+    Signals,
+    Connection<impl FnMut(SignalMsg) + Clone + Send>,
+) {
+    let fake = Fake::new();
+    for port in ports {
+        fake.add_port(port);
+    }
+    fake.set_dns(&["10.0.0.1"]);
+    let (signals, connection) = setup_with(Hardware::fake(&fake));
+    (fake, signals, connection)
+}
+
+fn setup_with(hw: Hardware) -> (Signals, Connection<impl FnMut(SignalMsg) + Clone + Send>) {
+    let signals: Signals = Arc::default();
+    let recorder = signals.clone();
+    let checker = ConnectivityChecker::new(ConnectivitySettings {
+        enabled: false,
+        ..ConnectivitySettings::default()
+    });
+    let roamer = Roamer::new(RoamingSettings::default());
+    let connection = Connection::with_hardware(
+        move |msg| recorder.lock().unwrap().push(msg),
+        checker,
+        roamer,
+        hw,
+    );
+    (signals, connection)
+}
+
+fn bss(essid: &str, bssid: &str, dbm: i32) -> AccessPoint {
+    AccessPoint {
+        bssid: bssid.to_owned(),
+        essid: essid.into(),
+        frequency: 2412,
+        signal: dbm,
+        last_seen: 0,
+        security: Security::Wpa2,
+        capabilities: String::new(),
+    }
+}
+
+fn wifi(essid: &str, dbm: i32) -> AccessPoint {
+    bss(essid, "02:00:00:00:00:01", dbm)
+}
+
+fn known(essid: &str) -> KnownNetworks {
+    let mut networks = KnownNetworks::new();
+    networks.insert(
+        essid.to_owned(),
+        KnownNetwork::new(true, true, false, "secret", 0),
+    );
+    networks
+}
+
+fn finished(signals: &Signals) -> Vec<(u32, Reason)> {
+    signals
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|msg| match msg {
+            SignalMsg::ConnectFinished(op, reason) => Some((*op, *reason)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn connect_to<F: FnMut(SignalMsg) + Clone + Send + 'static>(
+    connection: &mut Connection<F>,
+    setting: ConnectionSetting,
+) -> Result<()> {
+    let op = connection.start_operation();
+    connection.connect(op, setting)
+}
+
+#[test]
+fn ethernet_plug_and_unplug() {
+    let (fake, signals, mut connection) = setup(&["eth0"]);
+    fake.set_lease("eth0", Some("10.0.0.2/24"));
+    let known = KnownNetworks::new();
+
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Rescan
+    ));
+    fake.plug("eth0", true);
+    let setting = match connection.auto_connect_possible(&known) {
+        CouldConnect::Connect(setting) => setting,
+        _ => panic!("plugged in ethernet must be connected"),
+    };
+    connect_to(&mut connection, setting).unwrap();
+    assert_eq!(connection.state(), State::Connected);
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Ethernet(ref ip) if ip == "10.0.0.2"
+    ));
+    assert_eq!(fake.applied_dns(), vec!["10.0.0.1".to_owned()]);
+    assert_eq!(finished(&signals), vec![(1, Reason::None)]);
+
+    fake.plug("eth0", false);
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Disconnect
+    ));
+    connection.disconnect();
+    assert_eq!(connection.state(), State::Idle);
+    assert!(!connection.current_state().active());
+    assert_eq!(fake.address("eth0"), None);
+}
+
+#[test]
+fn known_wifi_in_range() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![wifi("cafe", -50), wifi("home", -80)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    let known = known("home");
+
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Rescan
+    ));
+    connection.scan();
+    assert_eq!(connection.get_networks().len(), 2);
+    let setting = match connection.auto_connect_possible(&known) {
+        CouldConnect::Connect(setting) => setting,
+        _ => panic!("known network in range must be connected"),
+    };
+    assert_eq!(setting.essid(), "home");
+    connect_to(&mut connection, setting).unwrap();
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Wifi(ref essid, quality, true, ref ip, Band::Ghz2)
+            if essid == "home" && quality == support::dbm2perc(-80) && ip == "192.168.1.5"
+    ));
+    let statuses: Vec<u32> = signals
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|msg| match msg {
+            SignalMsg::ConnectStatusChanged(change) => Some(match change.status {
+                ConnectionStatus::Initializing => 0,
+                ConnectionStatus::Authenticating => 2,
+                ConnectionStatus::GettingIP => 3,
+                _ => 99,
+            }),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(statuses, vec![0, 2, 3]);
+}
+
+#[test]
+fn roams_from_wifi_to_ethernet_and_back() {
+    let (fake, _, mut connection) = setup(&["eth0", "wlan0"]);
+    fake.set_access_points(vec![wifi("home", -60)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    fake.set_lease("eth0", Some("10.0.0.2/24"));
+    let known = known("home");
+    connection.scan();
+    connect_to(
+        &mut connection,
+        known["home"].to_setting(&Ssid::from("home")),
+    )
+    .unwrap();
+
+    fake.plug("eth0", true);
+    let setting = match connection.auto_connect_possible(&known) {
+        CouldConnect::Connect(setting) => setting,
+        _ => panic!("ethernet must be preferred over wifi"),
+    };
+    connect_to(&mut connection, setting).unwrap();
+    assert!(connection.current_state().wired());
+    assert!(!fake.is_port_up("wlan0"));
+    assert_eq!(fake.address("wlan0"), None);
+
+    fake.plug("eth0", false);
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Disconnect
+    ));
+    connection.disconnect();
+    connection.scan();
+    match connection.auto_connect_possible(&known) {
+        CouldConnect::Connect(setting) => {
+            connect_to(&mut connection, setting).unwrap();
+        }
+        _ => panic!("known wifi must be reconnected"),
+    }
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Wifi(..)
+    ));
+}
+
+#[test]
+fn wifi_lost_disconnects() {
+    let (fake, _, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![wifi("home", -60)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    let known = known("home");
+    connection.scan();
+    connect_to(
+        &mut connection,
+        known["home"].to_setting(&Ssid::from("home")),
+    )
+    .unwrap();
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::DoNothing
+    ));
+
+    fake.set_access_points(vec![]);
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Disconnect
+    ));
+    assert!(connection.get_networks().is_empty());
+}
+
+#[test]
+fn authentication_failure() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![wifi("home", -60)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    fake.reject("home");
+    connection.scan();
+
+    let result = connect_to(
+        &mut connection,
+        known("home")["home"].to_setting(&Ssid::from("home")),
+    );
+    assert!(matches!(result, Err(Error::AuthFail(ref essid)) if essid == "home"));
+    assert_eq!(connection.state(), State::Failed);
+    assert_eq!(finished(&signals), vec![(1, Reason::AuthFail)]);
+    assert!(signals.lock().unwrap().iter().any(|msg| matches!(
+        msg,
+        SignalMsg::ConnectStatusChanged(StatusChange {
+            status: ConnectionStatus::AuthFail,
+            reason: Reason::AuthFail,
+            ..
+        })
+    )));
+}
+
+#[test]
+fn connection_failures() {
+    let (fake, signals, mut connection) = setup(&["eth0", "wlan0"]);
+    fake.set_access_points(vec![wifi("home", -60)]);
+    connection.scan();
+
+    let result = connect_to(
+        &mut connection,
+        known("away")["away"].to_setting(&Ssid::from("away")),
+    );
+    assert!(matches!(result, Err(Error::UnknownNetwork(_))));
+
+    let result = connect_to(&mut connection, ConnectionSetting::Ethernet);
+    assert!(matches!(result, Err(Error::NotPluggedIn(_))));
+
+    fake.plug("eth0", true);
+    let result = connect_to(&mut connection, ConnectionSetting::Ethernet);
+    assert!(matches!(result, Err(Error::DhcpTimeout(_))));
+
+    fake.remove_port("wlan0");
+    let result = connect_to(
+        &mut connection,
+        known("home")["home"].to_setting(&Ssid::from("home")),
+    );
+    assert!(matches!(result, Err(Error::NoDevice(_))));
+
+    assert_eq!(
+        finished(&signals),
+        vec![
+            (1, Reason::UnknownNetwork),
+            (2, Reason::NotPluggedIn),
+            (3, Reason::DhcpTimeout),
+            (4, Reason::NoDevice),
+        ]
+    );
+    assert_eq!(connection.state(), State::Failed);
+}
+
+#[test]
+fn disconnect_interrupts_attempt() {
+    let (fake, signals, mut connection) = setup(&["eth0"]);
+    fake.plug("eth0", true);
+    let op = connection.start_operation();
+    connection.cancel(op).unwrap();
+    assert!(matches!(
+        connection.connect(op, ConnectionSetting::Ethernet),
+        Err(Error::Aborted)
+    ));
+    assert_eq!(finished(&signals), vec![(op, Reason::Aborted)]);
+    assert_eq!(connection.state(), State::Idle);
+}
+
+#[test]
+fn non_utf8_wifi() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    let essid = Ssid::new(vec![0x63, 0x61, 0x66, 0xe9]);
+    fake.set_access_points(vec![AccessPoint {
+        essid: essid.clone(),
+        ..wifi("", -60)
+    }]);
+    fake.set_password(essid.clone(), Some("secret"));
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    let mut known = KnownNetworks::new();
+    known.insert(
+        "hex:636166e9".to_owned(),
+        KnownNetwork::new(true, true, false, "secret", 0),
+    );
+    connection.scan();
+
+    match connection.auto_connect_possible(&known) {
+        CouldConnect::Connect(setting) => {
+            assert_eq!(*setting.essid(), essid);
+            connect_to(&mut connection, setting).unwrap();
+        }
+        _ => panic!("known network in range must be connected"),
+    }
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Wifi(ref connected, ..) if *connected == essid
+    ));
+    assert_eq!(finished(&signals), vec![(1, Reason::None)]);
+}
+
+#[test]
+fn access_points_and_pinned_bssid() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![
+        bss("home", "02:00:00:00:00:01", -70),
+        bss("home", "02:00:00:00:00:02", -50),
+        bss("cafe", "02:00:00:00:00:03", -40),
+    ]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    connection.scan();
+
+    let bssids: Vec<String> = connection
+        .get_access_points(&"home".into())
+        .into_iter()
+        .map(|ap| ap.bssid)
+        .collect();
+    assert_eq!(bssids, vec!["02:00:00:00:00:02", "02:00:00:00:00:01"]);
+    assert!(matches!(
+        connection.get_networks()[1],
+        NetworkInfo::Wifi(ref essid, quality, true)
+            if essid == "home" && quality == support::dbm2perc(-50)
+    ));
+
+    let mut known = known("home");
+    known.get_mut("home").unwrap().bssid = Some("02:00:00:00:00:09".to_owned());
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::DoNothing
+    ));
+    let setting = known["home"].to_setting(&"home".into());
+    assert!(matches!(
+        connect_to(&mut connection, setting),
+        Err(Error::UnknownNetwork(_))
+    ));
+    assert_eq!(finished(&signals), vec![(1, Reason::UnknownNetwork)]);
+    connection.disconnect();
+
+    // the fake only associates through the pinned bss
+    known.get_mut("home").unwrap().bssid = Some("02:00:00:00:00:01".to_owned());
+    let setting = match connection.auto_connect_possible(&known) {
+        CouldConnect::Connect(setting) => setting,
+        _ => panic!("pinned bss in range must be connected"),
+    };
+    assert_eq!(setting.bssid(), Some("02:00:00:00:00:01"));
+    connect_to(&mut connection, setting).unwrap();
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Wifi(ref essid, ..) if essid == "home"
+    ));
+}
+
+#[test]
+fn band_preference() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![
+        bss("home", "02:00:00:00:00:01", -50),
+        AccessPoint {
+            frequency: 5180,
+            ..bss("home", "02:00:00:00:00:02", -65)
+        },
+    ]);
+    fake.set_password("home", Some("secret"));
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    connection.scan();
+
+    let mut known = known("home");
+    known.get_mut("home").unwrap().band = Some("6".to_owned());
+    let setting = known["home"].to_setting(&"home".into());
+    connect_to(&mut connection, setting).unwrap();
+    assert_eq!(connection.current_state().band(), Band::Ghz2);
+
+    known.get_mut("home").unwrap().band_only = true;
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::DoNothing
+    ));
+    let setting = known["home"].to_setting(&"home".into());
+    assert!(matches!(
+        connect_to(&mut connection, setting),
+        Err(Error::UnknownNetwork(_))
+    ));
+
+    // the weaker 5 GHz bss wins once preferred
+    known.get_mut("home").unwrap().band = Some("5".to_owned());
+    known.get_mut("home").unwrap().band_only = false;
+    let setting = known["home"].to_setting(&"home".into());
+    connect_to(&mut connection, setting).unwrap();
+    assert_eq!(connection.current_state().band(), Band::Ghz5);
+    assert_eq!(
+        finished(&signals),
+        vec![
+            (1, Reason::None),
+            (2, Reason::UnknownNetwork),
+            (3, Reason::None)
+        ]
+    );
+}
+
+const SCENARIO: &str = r#"
+[[interface]]
+name = "wlan0"
+lease = "192.168.1.5/24"
+
+[[ap]]
+ssid = "home"
+signal = -55
+password = "secret"
+
+[[ap]]
+ssid = "cafe"
+signal = -70
+
+[[event]]
+at = 60
+ssid = "home"
+present = false
+
+[[event]]
+at = 120
+ssid = "home"
+present = true
+password = "changed"
+"#;
+
+#[test]
+fn simulated_ap_disappears() {
+    let simulation = Simulation::new(Scenario::parse(SCENARIO).unwrap());
+    let (_, mut connection) = setup_with(simulation.hardware());
+    let known = known("home");
+    connection.scan();
+    assert_eq!(connection.get_networks().len(), 2);
+    match connection.auto_connect_possible(&known) {
+        CouldConnect::Connect(setting) => connect_to(&mut connection, setting).unwrap(),
+        _ => panic!("known network in range must be connected"),
+    }
+
+    simulation.advance(30);
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::DoNothing
+    ));
+    simulation.advance(60);
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Disconnect
+    ));
+    connection.disconnect();
+    connection.scan();
+    assert_eq!(connection.get_networks().len(), 1);
+}
+
+#[test]
+fn simulated_password_change() {
+    let simulation = Simulation::new(Scenario::parse(SCENARIO).unwrap());
+    let (signals, mut connection) = setup_with(simulation.hardware());
+    let known = known("home");
+    connection.scan();
+    connect_to(
+        &mut connection,
+        known["home"].to_setting(&Ssid::from("home")),
+    )
+    .unwrap();
+
+    simulation.advance(120);
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Disconnect
+    ));
+    connection.disconnect();
+    connection.scan();
+    let result = connect_to(
+        &mut connection,
+        known["home"].to_setting(&Ssid::from("home")),
+    );
+    assert!(matches!(result, Err(Error::AuthFail(ref essid)) if essid == "home"));
+    assert_eq!(
+        finished(&signals),
+        vec![(1, Reason::None), (2, Reason::AuthFail)]
+    );
+}
+
+#[test]
+fn roams_to_a_stronger_bss() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![
+        bss("home", "02:00:00:00:00:01", -60),
+        bss("home", "02:00:00:00:00:02", -75),
+    ]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    connection.scan();
+    let mut known = known("home");
+    known.get_mut("home").unwrap().threshold = Some(-70);
+    connect_to(&mut connection, known["home"].to_setting(&"home".into())).unwrap();
+    connection.check_roaming();
+
+    // the fake stays on the weakened bss until asked to roam
+    fake.set_access_points(vec![
+        bss("home", "02:00:00:00:00:01", -80),
+        bss("home", "02:00:00:00:00:02", -60),
+    ]);
+    connection.check_roaming();
+    connection.check_roaming();
+    let roams: Vec<(String, String)> = signals
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|msg| match msg {
+            SignalMsg::Roamed(event) => Some((event.from.clone(), event.to.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        roams,
+        vec![(
+            "02:00:00:00:00:01".to_owned(),
+            "02:00:00:00:00:02".to_owned()
+        )]
+    );
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Wifi(_, quality, ..) if quality == support::dbm2perc(-60)
+    ));
+}
+
+#[test]
+fn link_stats_are_throttled() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![wifi("home", -60)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    fake.set_bitrates("wlan0", 65000, 72200);
+    connection.scan();
+    connect_to(
+        &mut connection,
+        known("home")["home"].to_setting(&"home".into()),
+    )
+    .unwrap();
+
+    let link_signals = |signals: &Signals| {
+        signals
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|msg| match msg {
+                SignalMsg::LinkStats(iface, stats) => Some((iface.clone(), stats.signal)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    connection.check_link();
+    // small changes are not reported
+    fake.set_access_points(vec![wifi("home", -61)]);
+    fake.set_bitrates("wlan0", 65000, 72000);
+    connection.check_link();
+    assert_eq!(link_signals(&signals), vec![("wlan0".to_owned(), -60)]);
+
+    fake.set_access_points(vec![wifi("home", -80)]);
+    connection.check_link();
+    assert_eq!(
+        link_signals(&signals),
+        vec![("wlan0".to_owned(), -60), ("wlan0".to_owned(), -80)]
+    );
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Wifi(_, quality, ..) if quality == support::dbm2perc(-80)
+    ));
+    let (_, stats) = connection.link_stats().unwrap();
+    assert_eq!((stats.tx_bitrate, stats.rx_bitrate), (65000, 72000));
+
+    connection.disconnect();
+    assert!(matches!(connection.link_stats(), Err(Error::NoInterface)));
+}
+
+#[test]
+fn traffic_statistics_and_usage() {
+    let (fake, signals, mut connection) = setup(&["eth0", "wlan0"]);
+    fake.set_access_points(vec![wifi("home", -60)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    connection.scan();
+    assert!(matches!(connection.statistics(""), Err(Error::NoInterface)));
+    assert!(matches!(
+        connection.statistics("wlan9"),
+        Err(Error::NoDevice(_))
+    ));
+    connect_to(
+        &mut connection,
+        known("home")["home"].to_setting(&"home".into()),
+    )
+    .unwrap();
+
+    connection.check_statistics();
+    fake.add_traffic("wlan0", 4000, 1000);
+    // traffic on other interfaces is not counted against the network
+    fake.add_traffic("eth0", 500, 500);
+    connection.statistics("eth0").unwrap();
+    fake.add_traffic("eth0", 500, 500);
+    connection.check_statistics();
+    let (iface, stats) = connection.statistics("wlan0").unwrap();
+    assert_eq!((iface.as_str(), stats.rx_bytes), ("wlan0", 4000));
+    let usage = connection.usage(&"home".into());
+    assert_eq!(
+        (usage.rx, usage.tx, usage.month_total()),
+        (4000, 1000, 5000)
+    );
+    let reported = signals
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|msg| matches!(msg, SignalMsg::Statistics(iface, _) if iface == "wlan0"))
+        .count();
+    assert_eq!(reported, 2);
+    assert_eq!(connection.usage_changes().unwrap().len(), 1);
+
+    connection.reset_usage(&"home".into());
+    assert_eq!(connection.usage(&"home".into()).rx, 0);
+}
+
+#[test]
+fn metered_networks_and_data_caps() {
+    let (fake, signals, mut connection) = setup(&["wlan0"]);
+    fake.set_access_points(vec![wifi("home", -60)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    fake.set_metered("wlan0", true);
+    connection.scan();
+    let mut networks = known("home");
+    connect_to(&mut connection, networks["home"].to_setting(&"home".into())).unwrap();
+
+    connection.check_metered(&networks);
+    assert_eq!(
+        connection.metered(),
+        MeteredState {
+            metered: true,
+            over_cap: false
+        }
+    );
+    // the profile wins over the dhcp server
+    networks.get_mut("home").unwrap().metered = Some(false);
+    connection.check_metered(&networks);
+    assert!(!connection.metered().metered);
+
+    networks.get_mut("home").unwrap().data_cap = Some(4000);
+    connection.check_statistics();
+    fake.add_traffic("wlan0", 3000, 1000);
+    connection.check_metered(&networks);
+    connection.check_statistics();
+    connection.check_metered(&networks);
+    assert!(connection.metered().over_cap);
+    connection.check_metered(&networks);
+
+    let changes = signals
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|msg| match msg {
+            SignalMsg::MeteredChanged(state) => Some(*state),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(changes.len(), 3);
+
+    connection.disconnect();
+    connection.check_metered(&networks);
+    assert_eq!(connection.metered(), MeteredState::default());
+}
+
+#[test]
+fn wired_8021x_authentication() {
+    let (fake, signals, mut connection) = setup(&["eth0"]);
+    fake.plug("eth0", true);
+    fake.set_lease("eth0", Some("10.0.0.2/24"));
+    fake.set_mac("eth0", "02:00:00:00:00:0e");
+    fake.require_8021x("eth0", "alice", "secret");
+    let auth = |password: &str| WiredAuth {
+        eap: "PEAP".to_owned(),
+        identity: "alice".to_owned(),
+        password: Some(password.to_owned()),
+        ..WiredAuth::default()
+    };
+
+    // the switch drops traffic until the port is authorized
+    let result = connect_to(&mut connection, ConnectionSetting::Ethernet);
+    assert!(matches!(result, Err(Error::DhcpTimeout(_))));
+
+    assert_eq!(connection.wired_port("").unwrap(), "02:00:00:00:00:0e");
+    connection.set_wired_auth("eth0", Some(auth("wrong")));
+    let result = connect_to(&mut connection, ConnectionSetting::Ethernet);
+    assert!(matches!(result, Err(Error::AuthFail(ref port)) if port == "eth0"));
+
+    // credentials for the MAC win over those for the interface name
+    connection.set_wired_auth("02:00:00:00:00:0e", Some(auth("secret")));
+    connect_to(&mut connection, ConnectionSetting::Ethernet).unwrap();
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Ethernet(ref ip) if ip == "10.0.0.2"
+    ));
+
+    let statuses: Vec<u32> = signals
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|msg| match msg {
+            SignalMsg::ConnectStatusChanged(change) if change.op > 1 => Some(match change.status {
+                ConnectionStatus::Initializing => 0,
+                ConnectionStatus::Authenticating => 2,
+                ConnectionStatus::GettingIP => 3,
+                ConnectionStatus::AuthFail => 4,
+                _ => 99,
+            }),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(statuses, vec![0, 2, 4, 0, 2, 3]);
+    assert_eq!(
+        finished(&signals),
+        vec![
+            (1, Reason::DhcpTimeout),
+            (2, Reason::AuthFail),
+            (3, Reason::None)
+        ]
+    );
+
+    // what bus callers get back hides the password but sending it back keeps it
+    let port = "02:00:00:00:00:0e";
+    let shown = connection.wired_credentials()[port].clone().redacted();
+    assert_eq!(shown.password.as_deref(), Some(HIDDEN_SECRET));
+    connection.set_wired_auth(port, Some(shown));
+    assert!(connection.wired_credentials()[port] == auth("secret"));
+}
+
+#[test]
+fn wired_profiles_pick_addressing() {
+    let (fake, _signals, mut connection) = setup(&["eth0"]);
+    fake.plug("eth0", true);
+    fake.set_lease("eth0", Some("10.0.0.2/24"));
+    fake.set_mac("eth0", "02:00:00:00:00:0e");
+    fake.set_neighbor("10.0.0.1", "aa:bb:cc:dd:ee:ff");
+    connection.set_wired_profile(
+        "port",
+        Some(WiredProfile {
+            mac: Some("02:00:00:00:00:0e".to_owned()),
+            ..WiredProfile::default()
+        }),
+    );
+    connection.set_wired_profile(
+        "office",
+        Some(WiredProfile {
+            gateway_mac: Some("aa:bb:cc:dd:ee:ff".to_owned()),
+            method: IP_METHOD_STATIC.to_owned(),
+            address: Some("192.168.5.10/24".to_owned()),
+            gateway: Some("192.168.5.1".to_owned()),
+            dns: vec!["192.168.5.53".to_owned()],
+            mtu: Some(1400),
+            ..WiredProfile::default()
+        }),
+    );
+
+    // the gateway of the lease identifies the office network
+    connect_to(&mut connection, ConnectionSetting::Ethernet).unwrap();
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Ethernet(ref ip) if ip == "192.168.5.10"
+    ));
+    assert_eq!(fake.gateway("eth0").as_deref(), Some("192.168.5.1"));
+    assert_eq!(fake.applied_dns(), vec!["192.168.5.53".to_owned()]);
+    assert_eq!(fake.mtu("eth0"), Some(1400));
+    assert_eq!(connection.active_wired_profile().as_deref(), Some("office"));
+
+    connection.disconnect();
+    assert_eq!(connection.active_wired_profile(), None);
+    fake.set_neighbor("10.0.0.1", "aa:bb:cc:dd:ee:00");
+    connect_to(&mut connection, ConnectionSetting::Ethernet).unwrap();
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::Ethernet(ref ip) if ip == "10.0.0.2"
+    ));
+    assert_eq!(fake.applied_dns(), vec!["10.0.0.1".to_owned()]);
+    assert_eq!(connection.active_wired_profile().as_deref(), Some("port"));
+
+    connection.disconnect();
+    connection.set_wired_profile(
+        "port",
+        Some(WiredProfile {
+            auto: false,
+            mac: Some("02:00:00:00:00:0e".to_owned()),
+            ..WiredProfile::default()
+        }),
+    );
+    assert!(!matches!(
+        connection.auto_connect_possible(&KnownNetworks::new()),
+        CouldConnect::Connect(ConnectionSetting::Ethernet)
+    ));
+}
+
+#[test]
+fn mac_address_policies() {
+    let (fake, _signals, mut connection) = setup(&["wlan0"]);
+    let permanent = "00:11:22:33:44:55";
+    fake.set_mac("wlan0", permanent);
+    fake.set_access_points(vec![wifi("home", -50)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    connection.set_mac_secret(b"secret".to_vec());
+    let mut known = known("home");
+    let connect = |known: &KnownNetworks, connection: &mut Connection<_>| {
+        connect_to(connection, known["home"].to_setting(&"home".into())).unwrap();
+        let mac = fake.associated_mac("wlan0").unwrap();
+        assert_eq!(connection.active_mac().as_deref(), Some(mac.as_str()));
+        connection.disconnect();
+        mac
+    };
+
+    // probes of an idle interface carry a throwaway address
+    connection.scan();
+    let scanned = fake.mac("wlan0").unwrap();
+    assert_ne!(scanned, permanent);
+    assert_eq!(connect(&known, &mut connection), permanent);
+
+    known.get_mut("home").unwrap().mac_policy = Some("stable".to_owned());
+    let stable = support::stable_mac(b"secret", &"home".into());
+    assert_eq!(connect(&known, &mut connection), stable);
+    connection.scan();
+    assert_eq!(connect(&known, &mut connection), stable);
+
+    known.get_mut("home").unwrap().mac_policy = Some("random".to_owned());
+    let first = connect(&known, &mut connection);
+    let second = connect(&known, &mut connection);
+    assert_ne!(first, second);
+    assert!(![permanent, stable.as_str()].contains(&first.as_str()));
+
+    // without a readable permanent address the one snm found comes back
+    fake.clear_permanent_mac("wlan0");
+    known.get_mut("home").unwrap().mac_policy = None;
+    assert_eq!(connect(&known, &mut connection), permanent);
+}
+
+#[test]
+fn fixed_address_is_left_alone() {
+    let (fake, _signals, mut connection) = setup(&["wlan0"]);
+    let own = "00:11:22:33:44:55";
+    fake.set_mac("wlan0", own);
+    fake.clear_permanent_mac("wlan0");
+    fake.fix_mac("wlan0");
+    fake.set_access_points(vec![wifi("home", -50)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+
+    connection.scan();
+    connection.scan();
+    assert_eq!(fake.mac("wlan0").as_deref(), Some(own));
+    connect_to(
+        &mut connection,
+        known("home")["home"].to_setting(&"home".into()),
+    )
+    .unwrap();
+    assert_eq!(fake.associated_mac("wlan0").as_deref(), Some(own));
+}
+
+#[test]
+fn link_settings_follow_profiles() {
+    let (fake, _signals, mut connection) = setup(&["eth0", "wlan0"]);
+    fake.plug("eth0", true);
+    fake.set_lease("eth0", Some("10.0.0.2/24"));
+    fake.set_lease_mtu("eth0", Some(1492));
+    connection.set_wired_profile(
+        "port",
+        Some(WiredProfile {
+            interface: Some("eth0".to_owned()),
+            mtu: Some(1400),
+            speed: Some(100),
+            duplex: Some(DUPLEX_FULL.to_owned()),
+            autoneg: Some(false),
+            wake_on_lan: Some(true),
+            ..WiredProfile::default()
+        }),
+    );
+
+    connect_to(&mut connection, ConnectionSetting::Ethernet).unwrap();
+    assert_eq!(fake.mtu("eth0"), Some(1400));
+    assert_eq!(
+        fake.link_mode("eth0"),
+        LinkMode {
+            speed: Some(100),
+            full_duplex: Some(true),
+            autoneg: Some(false),
+        }
+    );
+    assert_eq!(fake.wake_on_lan("eth0"), Some(true));
+
+    // the offer wins once the profile asks for it, and is undone on disconnect
+    connection.disconnect();
+    assert_eq!(fake.mtu("eth0"), Some(1500));
+    let mut profile = connection.wired_profiles().remove("port").unwrap();
+    profile.dhcp_mtu = true;
+    connection.set_wired_profile("port", Some(profile));
+    connect_to(&mut connection, ConnectionSetting::Ethernet).unwrap();
+    assert_eq!(fake.mtu("eth0"), Some(1492));
+    connection.disconnect();
+
+    fake.plug("eth0", false);
+    fake.set_access_points(vec![wifi("home", -50)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    fake.set_lease_mtu("wlan0", Some(1280));
+    let mut known = known("home");
+    known.get_mut("home").unwrap().mtu = Some(1300);
+    connection.scan();
+    connect_to(&mut connection, known["home"].to_setting(&"home".into())).unwrap();
+    assert_eq!(fake.mtu("wlan0"), Some(1300));
+    connection.disconnect();
+    known.get_mut("home").unwrap().dhcp_mtu = true;
+    connect_to(&mut connection, known["home"].to_setting(&"home".into())).unwrap();
+    assert_eq!(fake.mtu("wlan0"), Some(1280));
+}
+
+#[test]
+fn refused_sharing_stops_the_hotspot() {
+    let (fake, _signals, mut connection) = setup(&["eth0", "wlan0"]);
+    fake.plug("eth0", true);
+    fake.refuse_sharing(true);
+    let hotspot = HotspotSetting {
+        essid: "shared".into(),
+        password: Some("hunter22".to_owned()),
+        band: Band::Ghz2,
+    };
+    let result = connection.start_hotspot(hotspot.clone());
+    assert!(matches!(result, Err(Error::CommandFailed(_, 4, _))));
+    assert_eq!(result.unwrap_err().reason(), Reason::CommandFailed);
+    assert!(fake.hosted("wlan0").is_none());
+    assert!(connection.hotspot().is_none());
+
+    fake.refuse_sharing(false);
+    connection.start_hotspot(hotspot).unwrap();
+    assert!(fake.shared().is_some());
+}
+
+#[test]
+fn hotspot_shares_the_wired_uplink() {
+    let (fake, _signals, mut connection) = setup(&["eth0", "wlan0"]);
+    fake.set_access_points(vec![wifi("home", -50)]);
+    fake.set_lease("wlan0", Some("192.168.1.5/24"));
+    connection.scan();
+    let known = known("home");
+    let setting = known["home"].to_setting(&"home".into());
+    connect_to(&mut connection, known["home"].to_setting(&"home".into())).unwrap();
+
+    let hotspot = HotspotSetting {
+        essid: "shared".into(),
+        password: Some("hunter22".to_owned()),
+        band: Band::Ghz5,
+    };
+    assert!(matches!(
+        connection.start_hotspot(hotspot.clone()),
+        Err(Error::NotPluggedIn(_))
+    ));
+    fake.plug("eth0", true);
+    connection.start_hotspot(hotspot.clone()).unwrap();
+    assert!(matches!(
+        connection.current_state(),
+        ConnectionInfo::NotConnected
+    ));
+    assert_eq!(
+        fake.hosted("wlan0")
+            .map(|hosted| (hosted.frequency, hosted.psk)),
+        Some((5180, Some(support::psk(b"shared", "hunter22"))))
+    );
+    assert_eq!(fake.address("wlan0").as_deref(), Some("10.42.0.1"));
+    assert_eq!(
+        fake.shared(),
+        Some((
+            "wlan0".to_owned(),
+            HOTSPOT_SUBNET.to_owned(),
+            "eth0".to_owned()
+        ))
+    );
+    assert!(fake.forwarding());
+    assert_eq!(connection.hotspot(), Some((hotspot, "wlan0".to_owned())));
+
+    fake.join_hotspot("02:00:00:00:00:aa", "phone");
+    let mut tries = 0;
+    while connection.hotspot_clients().is_empty() && tries < 1000 {
+        thread::sleep(time::Duration::from_millis(1));
+        tries += 1;
+    }
+    assert_eq!(
+        connection.hotspot_clients(),
+        vec![HotspotClient {
+            mac: "02:00:00:00:00:aa".to_owned(),
+            ip: "10.42.0.2".to_owned(),
+            hostname: "phone".to_owned(),
+        }]
+    );
+
+    // the wired side keeps working, wifi waits for the hotspot to stop
+    assert!(matches!(
+        connection.check_setting(&setting),
+        Err(Error::NotAllowed(_))
+    ));
+    assert!(matches!(
+        connection.auto_connect_possible(&known),
+        CouldConnect::Connect(ConnectionSetting::Ethernet)
+    ));
+    fake.set_lease("eth0", Some("10.0.0.2/24"));
+    connect_to(&mut connection, ConnectionSetting::Ethernet).unwrap();
+    assert!(fake.is_port_up("wlan0"));
+    assert!(fake.hosted("wlan0").is_some());
+
+    // a restart that cannot work leaves the running one alone
+    fake.plug("eth0", false);
+    let restart = HotspotSetting {
+        band: Band::Ghz2,
+        ..connection.hotspot().unwrap().0
+    };
+    assert!(matches!(
+        connection.start_hotspot(restart),
+        Err(Error::NotPluggedIn(_))
+    ));
+    assert_eq!(
+        fake.hosted("wlan0").map(|hosted| hosted.frequency),
+        Some(5180)
+    );
+    assert_eq!(connection.given_up_wifi(), Some("home".into()));
+    fake.plug("eth0", true);
+
+    assert_eq!(connection.stop_hotspot(), Some("home".into()));
+    assert!(fake.hosted("wlan0").is_none());
+    assert!(fake.shared().is_none());
+    assert!(!fake.forwarding());
+    assert!(connection.hotspot_clients().is_empty());
+    assert_eq!(connection.stop_hotspot(), None);
+}
@@ -0,0 +1,164 @@
+use super::support::civil_date;
+use super::types::{DataUsage, Statistics, UsageTotals};
+use std::collections::HashMap;
+use std::time;
+
+// the month usage is accounted to, counted from year 0
+pub fn month(now: time::SystemTime) -> u64 {
+    let secs = now
+        .duration_since(time::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let (year, month, _) = civil_date((secs / 86400) as i64);
+    (year * 12 + month - 1) as u64
+}
+
+// previous counters of every interface, for rates and per network usage
+#[derive(Default)]
+pub struct Traffic {
+    samples: HashMap<String, (Statistics, time::Instant)>,
+    usage: UsageTotals,
+    // usage changed since it was last taken for saving
+    dirty: bool,
+}
+
+impl Traffic {
+    // fills in the rates and returns the bytes moved since the previous sample
+    pub fn sample(&mut self, iface: &str, stats: &mut Statistics, now: time::Instant) -> DataUsage {
+        // counters start over when the interface is recreated
+        let moved = |last: u64, now: u64| now.checked_sub(last).unwrap_or(now);
+        let delta = match self.samples.get(iface) {
+            Some((last, at)) => {
+                let delta = DataUsage {
+                    rx: moved(last.rx_bytes, stats.rx_bytes),
+                    tx: moved(last.tx_bytes, stats.tx_bytes),
+                    ..DataUsage::default()
+                };
+                let elapsed = now.duration_since(*at).as_secs_f64();
+                if elapsed > 0.0 {
+                    stats.rx_rate = (delta.rx as f64 / elapsed) as u64;
+                    stats.tx_rate = (delta.tx as f64 / elapsed) as u64;
+                } else {
+                    stats.rx_rate = last.rx_rate;
+                    stats.tx_rate = last.tx_rate;
+                }
+                delta
+            }
+            None => DataUsage::default(),
+        };
+        self.samples.insert(iface.to_owned(), (stats.clone(), now));
+        delta
+    }
+
+    pub fn account(&mut self, network: &str, delta: DataUsage, month: u64) {
+        if delta.rx == 0 && delta.tx == 0 {
+            return;
+        }
+        let usage = self.usage.entry(network.to_owned()).or_default();
+        usage.roll(month);
+        usage.rx += delta.rx;
+        usage.tx += delta.tx;
+        usage.month_rx += delta.rx;
+        usage.month_tx += delta.tx;
+        self.dirty = true;
+    }
+
+    pub fn usage(&self, network: &str, month: u64) -> DataUsage {
+        let mut usage = self.usage.get(network).copied().unwrap_or_default();
+        usage.roll(month);
+        usage
+    }
+
+    pub fn reset(&mut self, network: &str) {
+        if self.usage.remove(network).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    pub fn set_totals(&mut self, usage: UsageTotals) {
+        self.usage = usage;
+        self.dirty = false;
+    }
+
+    pub fn take_totals(&mut self) -> Option<UsageTotals> {
+        if !std::mem::replace(&mut self.dirty, false) {
+            return None;
+        }
+        Some(self.usage.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counters(rx_bytes: u64, tx_bytes: u64) -> Statistics {
+        Statistics {
+            rx_bytes,
+            tx_bytes,
+            ..Statistics::default()
+        }
+    }
+
+    #[test]
+    fn rates_and_counter_restart() {
+        let mut traffic = Traffic::default();
+        let now = time::Instant::now();
+        let mut stats = counters(1000, 500);
+        assert_eq!(
+            traffic.sample("wlan0", &mut stats, now),
+            DataUsage::default()
+        );
+
+        let mut stats = counters(5000, 2500);
+        let later = now + time::Duration::from_secs(2);
+        let delta = traffic.sample("wlan0", &mut stats, later);
+        assert_eq!((delta.rx, delta.tx), (4000, 2000));
+        assert_eq!((stats.rx_rate, stats.tx_rate), (2000, 1000));
+
+        let mut stats = counters(300, 100);
+        let delta = traffic.sample("wlan0", &mut stats, later + time::Duration::from_secs(1));
+        assert_eq!((delta.rx, delta.tx), (300, 100));
+    }
+
+    fn moved(rx: u64, tx: u64) -> DataUsage {
+        DataUsage {
+            rx,
+            tx,
+            ..DataUsage::default()
+        }
+    }
+
+    #[test]
+    fn usage_is_saved_only_when_changed() {
+        let mut traffic = Traffic::default();
+        assert!(traffic.take_totals().is_none());
+        traffic.account("home", moved(10, 5), 1);
+        traffic.account("home", moved(1, 1), 1);
+        let usage = traffic.usage("home", 1);
+        assert_eq!((usage.rx, usage.tx, usage.month_total()), (11, 6, 17));
+        assert_eq!(traffic.take_totals().unwrap().len(), 1);
+        assert!(traffic.take_totals().is_none());
+        traffic.reset("home");
+        assert_eq!(
+            traffic.usage("home", 1),
+            DataUsage {
+                month: 1,
+                ..DataUsage::default()
+            }
+        );
+        assert!(traffic.take_totals().unwrap().is_empty());
+    }
+
+    #[test]
+    fn monthly_usage_starts_over() {
+        let mut traffic = Traffic::default();
+        traffic.account("home", moved(10, 5), 1);
+        assert_eq!(traffic.usage("home", 2).month_total(), 0);
+        traffic.account("home", moved(1, 1), 2);
+        let usage = traffic.usage("home", 2);
+        assert_eq!((usage.rx, usage.month_rx), (11, 1));
+        // 2026-10-18
+        let now = time::UNIX_EPOCH + time::Duration::from_secs(1_792_281_600);
+        assert_eq!(month(now), 2026 * 12 + 9);
+    }
+}
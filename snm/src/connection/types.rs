@@ -1,5 +1,93 @@
+use crate::error::Reason;
 use std::cmp::{Ord, Ordering};
 use std::collections::HashMap;
+use std::{fmt, str};
+
+const SSID_HEX_PREFIX: &str = "hex:";
+
+// ssids are up to 32 arbitrary bytes, not necessarily text
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Ssid(Vec<u8>);
+
+impl Ssid {
+    pub const fn new(bytes: Vec<u8>) -> Self {
+        Ssid(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_utf8(&self) -> Option<&str> {
+        str::from_utf8(&self.0).ok()
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    // reversible text form, text ssids stay as they are
+    pub fn key(&self) -> String {
+        match self.as_utf8() {
+            Some(text) if !text.starts_with(SSID_HEX_PREFIX) => text.to_owned(),
+            _ => format!("{}{}", SSID_HEX_PREFIX, self.to_hex()),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Self {
+        key.strip_prefix(SSID_HEX_PREFIX)
+            .and_then(Self::from_hex)
+            .unwrap_or_else(|| Ssid::from(key))
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()
+            .map(Ssid)
+    }
+}
+
+impl From<&str> for Ssid {
+    fn from(text: &str) -> Self {
+        Ssid(text.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Ssid {
+    fn from(text: String) -> Self {
+        Ssid(text.into_bytes())
+    }
+}
+
+impl PartialEq<str> for Ssid {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for Ssid {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+// lossy, for humans
+impl fmt::Display for Ssid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+const NO_SSID: &Ssid = &Ssid::new(Vec::new());
 
 pub enum ConnectionStatus {
     Initializing,
@@ -11,13 +99,289 @@ pub enum ConnectionStatus {
     ConnectFail,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Band {
+    Unknown,
+    Ghz2,
+    Ghz5,
+    Ghz6,
+}
+
+impl Band {
+    pub fn from_frequency(frequency: u32) -> Band {
+        match frequency {
+            2400..=2500 => Band::Ghz2,
+            5150..=5895 => Band::Ghz5,
+            5925..=7125 => Band::Ghz6,
+            _ => Band::Unknown,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Band> {
+        match name {
+            "2.4" => Some(Band::Ghz2),
+            "5" => Some(Band::Ghz5),
+            "6" => Some(Band::Ghz6),
+            _ => None,
+        }
+    }
+
+    // in GHz
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Band::Unknown => None,
+            Band::Ghz2 => Some("2.4"),
+            Band::Ghz5 => Some("5"),
+            Band::Ghz6 => Some("6"),
+        }
+    }
+}
+
+// the hardware address used to join a network
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MacPolicy {
+    Permanent,
+    // new address for every connection
+    Random,
+    // derived from the ssid and a machine secret
+    Stable,
+}
+
+impl MacPolicy {
+    pub fn from_name(name: &str) -> Option<MacPolicy> {
+        match name {
+            "permanent" => Some(MacPolicy::Permanent),
+            "random" => Some(MacPolicy::Random),
+            "stable" => Some(MacPolicy::Stable),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MacPolicy::Permanent => "permanent",
+            MacPolicy::Random => "random",
+            MacPolicy::Stable => "stable",
+        }
+    }
+}
+
+// which frequencies a network may be joined on
+#[derive(Clone, Default, Debug)]
+pub struct BandPolicy {
+    pub band: Option<Band>,
+    // never fall back to the other bands
+    pub only: bool,
+    pub frequencies: Vec<u32>,
+}
+
+impl BandPolicy {
+    fn listed(&self, frequency: u32) -> bool {
+        self.frequencies.is_empty() || self.frequencies.contains(&frequency)
+    }
+
+    // None allows any frequency, an empty list none at all
+    pub fn freq_list(&self, seen: &[u32]) -> Option<Vec<u32>> {
+        let band = match self.band {
+            Some(band) if self.only => band,
+            _ if self.frequencies.is_empty() => return None,
+            _ => return Some(self.frequencies.clone()),
+        };
+        let mut on_band: Vec<u32> = seen
+            .iter()
+            .chain(self.frequencies.iter())
+            .copied()
+            .filter(|frequency| self.listed(*frequency) && Band::from_frequency(*frequency) == band)
+            .collect();
+        on_band.sort_unstable();
+        on_band.dedup();
+        Some(on_band)
+    }
+
+    // the strongest bss on the preferred band, the supplicant may still pick another
+    pub fn preferred<'a>(&self, seen: &'a [AccessPoint]) -> Option<&'a AccessPoint> {
+        let band = self.band?;
+        seen.iter()
+            .filter(|ap| ap.band() == band && self.listed(ap.frequency))
+            .max_by_key(|ap| ap.signal)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Security {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3,
+}
+
+// a single bss as seen by the last scan
+#[derive(Clone, Debug)]
+pub struct AccessPoint {
+    pub bssid: String,
+    pub essid: Ssid,
+    pub frequency: u32,
+    pub signal: i32,
+    // milliseconds since the bss was last seen
+    pub last_seen: u32,
+    pub security: Security,
+    pub capabilities: String,
+}
+
+impl AccessPoint {
+    pub fn valid_bssid(bssid: &str) -> bool {
+        bssid.len() == 17
+            && bssid.split(':').count() == 6
+            && bssid
+                .split(':')
+                .all(|octet| octet.len() == 2 && octet.bytes().all(|b| b.is_ascii_hexdigit()))
+    }
+
+    pub fn band(&self) -> Band {
+        Band::from_frequency(self.frequency)
+    }
+
+    pub fn channel(&self) -> u32 {
+        match self.frequency {
+            2484 => 14,
+            2407..=2483 => (self.frequency - 2407) / 5,
+            5000..=5895 => (self.frequency - 5000) / 5,
+            5955..=7115 => (self.frequency - 5950) / 5,
+            _ => 0,
+        }
+    }
+
+    pub fn quality(&self) -> u32 {
+        super::support::dbm2perc(self.signal)
+    }
+
+    pub fn to_network(&self) -> NetworkInfo {
+        NetworkInfo::Wifi(
+            self.essid.clone(),
+            self.quality(),
+            self.security != Security::Open,
+        )
+    }
+}
+
+// the bss an interface is associated with
+#[derive(Clone, Debug)]
+pub struct Station {
+    pub essid: Ssid,
+    pub bssid: String,
+    pub frequency: u32,
+    pub signal: Option<i32>,
+}
+
+impl Station {
+    pub fn band(&self) -> Band {
+        Band::from_frequency(self.frequency)
+    }
+
+    pub fn quality(&self) -> u32 {
+        self.signal.map_or(100, super::support::dbm2perc)
+    }
+}
+
+// link figures of the associated bss, bitrates in kbit/s
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkStats {
+    pub signal: i32,
+    pub tx_bitrate: u32,
+    pub rx_bitrate: u32,
+    // seconds since association
+    pub connected_time: u32,
+}
+
+impl LinkStats {
+    pub fn quality(&self) -> u32 {
+        super::support::dbm2perc(self.signal)
+    }
+}
+
+// interface counters, rates in bytes per second
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub rx_rate: u64,
+    pub tx_rate: u64,
+}
+
+// bytes transferred over a network, in total and in the current month
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct DataUsage {
+    pub rx: u64,
+    pub tx: u64,
+    // months since year 0 the monthly counters belong to
+    #[serde(default = "DataUsage::default_counter")]
+    pub month: u64,
+    #[serde(default = "DataUsage::default_counter")]
+    pub month_rx: u64,
+    #[serde(default = "DataUsage::default_counter")]
+    pub month_tx: u64,
+}
+
+impl DataUsage {
+    fn default_counter() -> u64 {
+        0
+    }
+
+    pub fn month_total(&self) -> u64 {
+        self.month_rx + self.month_tx
+    }
+
+    // starts the monthly counters over when a new month begins
+    pub fn roll(&mut self, month: u64) {
+        if self.month != month {
+            self.month = month;
+            self.month_rx = 0;
+            self.month_tx = 0;
+        }
+    }
+}
+
+// keyed like KnownNetworks
+pub type UsageTotals = HashMap<String, DataUsage>;
+
+// whether traffic on the active connection costs money
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeteredState {
+    pub metered: bool,
+    // the monthly data cap of the network is used up
+    pub over_cap: bool,
+}
+
+pub struct StatusChange {
+    pub status: ConnectionStatus,
+    pub reason: Reason,
+    pub essid: Ssid,
+    pub iface: String,
+    pub op: u32,
+}
+
+// the station moved from one bss to another of the same network
+pub struct RoamEvent {
+    pub iface: String,
+    pub essid: Ssid,
+    pub from: String,
+    pub to: String,
+}
+
 #[derive(Clone)]
 pub enum ConnectionInfo {
     NotConnected,
     Ethernet(String),
-    Wifi(String, u32, bool, String),
+    Wifi(Ssid, u32, bool, String, Band),
     ConnectingEth,
-    ConnectingWifi(String),
+    ConnectingWifi(Ssid),
 }
 
 impl ConnectionInfo {
@@ -35,6 +399,22 @@ impl ConnectionInfo {
         }
     }
 
+    pub fn essid(&self) -> &Ssid {
+        match self {
+            ConnectionInfo::Wifi(ref essid, ..) | ConnectionInfo::ConnectingWifi(ref essid) => {
+                essid
+            }
+            _ => NO_SSID,
+        }
+    }
+
+    pub fn band(&self) -> Band {
+        match self {
+            ConnectionInfo::Wifi(.., band) => *band,
+            _ => Band::Unknown,
+        }
+    }
+
     pub fn connecting(&self) -> bool {
         match self {
             ConnectionInfo::ConnectingEth | ConnectionInfo::ConnectingWifi(_) => true,
@@ -46,13 +426,23 @@ impl ConnectionInfo {
 pub enum ConnectionSetting {
     Ethernet,
     Wifi {
-        essid: String,
+        essid: Ssid,
         password: String,
         threshold: Option<i32>,
+        bssid: Option<String>,
+        bands: BandPolicy,
+        mac_policy: MacPolicy,
+        mtu: Option<u32>,
+        dhcp_mtu: bool,
     },
     OpenWifi {
-        essid: String,
+        essid: Ssid,
         threshold: Option<i32>,
+        bssid: Option<String>,
+        bands: BandPolicy,
+        mac_policy: MacPolicy,
+        mtu: Option<u32>,
+        dhcp_mtu: bool,
     },
 }
 
@@ -63,6 +453,62 @@ impl ConnectionSetting {
             _ => false,
         }
     }
+
+    pub fn essid(&self) -> &Ssid {
+        match self {
+            ConnectionSetting::Wifi { ref essid, .. }
+            | ConnectionSetting::OpenWifi { ref essid, .. } => essid,
+            ConnectionSetting::Ethernet => NO_SSID,
+        }
+    }
+
+    pub fn bssid(&self) -> Option<&str> {
+        match self {
+            ConnectionSetting::Wifi { ref bssid, .. }
+            | ConnectionSetting::OpenWifi { ref bssid, .. } => bssid.as_deref(),
+            ConnectionSetting::Ethernet => None,
+        }
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        match self {
+            ConnectionSetting::Wifi { ref password, .. } => Some(password),
+            _ => None,
+        }
+    }
+
+    pub fn threshold(&self) -> Option<i32> {
+        match self {
+            ConnectionSetting::Wifi { threshold, .. }
+            | ConnectionSetting::OpenWifi { threshold, .. } => *threshold,
+            ConnectionSetting::Ethernet => None,
+        }
+    }
+
+    pub fn bands(&self) -> BandPolicy {
+        match self {
+            ConnectionSetting::Wifi { ref bands, .. }
+            | ConnectionSetting::OpenWifi { ref bands, .. } => bands.clone(),
+            ConnectionSetting::Ethernet => BandPolicy::default(),
+        }
+    }
+
+    pub fn mac_policy(&self) -> MacPolicy {
+        match self {
+            ConnectionSetting::Wifi { mac_policy, .. }
+            | ConnectionSetting::OpenWifi { mac_policy, .. } => *mac_policy,
+            ConnectionSetting::Ethernet => MacPolicy::Permanent,
+        }
+    }
+
+    // the mtu to use and whether the one offered by the dhcp server wins
+    pub fn mtu(&self) -> (Option<u32>, bool) {
+        match self {
+            ConnectionSetting::Wifi { mtu, dhcp_mtu, .. }
+            | ConnectionSetting::OpenWifi { mtu, dhcp_mtu, .. } => (*mtu, *dhcp_mtu),
+            ConnectionSetting::Ethernet => (None, false),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
@@ -71,6 +517,31 @@ pub struct KnownNetwork {
     pub password: Option<String>,
     #[serde(default = "KnownNetwork::default_threshold")]
     pub threshold: Option<i32>,
+    // connect only through this access point
+    #[serde(default = "KnownNetwork::default_bssid")]
+    pub bssid: Option<String>,
+    // "2.4", "5" or "6", preferred unless band_only is set
+    #[serde(default = "KnownNetwork::default_band")]
+    pub band: Option<String>,
+    #[serde(default = "KnownNetwork::default_band_only")]
+    pub band_only: bool,
+    // MHz, any if empty
+    #[serde(default = "KnownNetwork::default_frequencies")]
+    pub frequencies: Vec<u32>,
+    // None follows what the dhcp server says
+    #[serde(default = "KnownNetwork::default_metered")]
+    pub metered: Option<bool>,
+    // bytes per calendar month
+    #[serde(default = "KnownNetwork::default_data_cap")]
+    pub data_cap: Option<u64>,
+    // "permanent", "random" or "stable", permanent if unset
+    #[serde(default = "KnownNetwork::default_mac_policy")]
+    pub mac_policy: Option<String>,
+    #[serde(default = "KnownNetwork::default_mtu")]
+    pub mtu: Option<u32>,
+    // the mtu offered by the dhcp server wins over the one above
+    #[serde(default = "KnownNetwork::default_dhcp_mtu")]
+    pub dhcp_mtu: bool,
 }
 
 impl KnownNetwork {
@@ -78,6 +549,42 @@ impl KnownNetwork {
         None
     }
 
+    fn default_bssid() -> Option<String> {
+        None
+    }
+
+    fn default_band() -> Option<String> {
+        None
+    }
+
+    fn default_band_only() -> bool {
+        false
+    }
+
+    fn default_frequencies() -> Vec<u32> {
+        Vec::new()
+    }
+
+    fn default_metered() -> Option<bool> {
+        None
+    }
+
+    fn default_data_cap() -> Option<u64> {
+        None
+    }
+
+    fn default_mac_policy() -> Option<String> {
+        None
+    }
+
+    fn default_mtu() -> Option<u32> {
+        None
+    }
+
+    fn default_dhcp_mtu() -> bool {
+        false
+    }
+
     fn make_threshold(roaming: bool, value: i32) -> Option<i32> {
         if roaming {
             Some(value)
@@ -99,20 +606,62 @@ impl KnownNetwork {
             auto,
             password: KnownNetwork::make_password(enc, password.to_string()),
             threshold: KnownNetwork::make_threshold(roaming, threshold),
+            ..KnownNetwork::default()
+        }
+    }
+
+    // set_props carries only these, the per-network settings stay as they were
+    pub fn with_props(&self, props: KnownNetwork) -> Self {
+        KnownNetwork {
+            auto: props.auto,
+            password: props.password,
+            threshold: props.threshold,
+            ..self.clone()
+        }
+    }
+
+    pub fn set_bands(&mut self, bands: BandPolicy) {
+        self.band = bands.band.and_then(Band::name).map(str::to_owned);
+        self.band_only = bands.only;
+        self.frequencies = bands.frequencies;
+    }
+
+    pub fn bands(&self) -> BandPolicy {
+        BandPolicy {
+            band: self.band.as_deref().and_then(Band::from_name),
+            only: self.band_only,
+            frequencies: self.frequencies.clone(),
         }
     }
 
-    pub fn to_setting(&self, essid: &str) -> ConnectionSetting {
+    pub fn mac_policy(&self) -> MacPolicy {
+        self.mac_policy
+            .as_deref()
+            .and_then(MacPolicy::from_name)
+            .unwrap_or(MacPolicy::Permanent)
+    }
+
+    pub fn to_setting(&self, essid: &Ssid) -> ConnectionSetting {
         if let Some(ref pass) = self.password {
             ConnectionSetting::Wifi {
-                essid: essid.to_string(),
+                essid: essid.clone(),
                 password: pass.to_string(),
                 threshold: self.threshold,
+                bssid: self.bssid.clone(),
+                bands: self.bands(),
+                mac_policy: self.mac_policy(),
+                mtu: self.mtu,
+                dhcp_mtu: self.dhcp_mtu,
             }
         } else {
             ConnectionSetting::OpenWifi {
-                essid: essid.to_string(),
+                essid: essid.clone(),
                 threshold: self.threshold,
+                bssid: self.bssid.clone(),
+                bands: self.bands(),
+                mac_policy: self.mac_policy(),
+                mtu: self.mtu,
+                dhcp_mtu: self.dhcp_mtu,
             }
         }
     }
@@ -122,7 +671,7 @@ impl std::convert::From<ConnectionInfo> for NetworkInfo {
     fn from(info: ConnectionInfo) -> Self {
         match info {
             ConnectionInfo::Ethernet(_) | ConnectionInfo::ConnectingEth => NetworkInfo::Ethernet,
-            ConnectionInfo::Wifi(ssid, signal, sec, _) => NetworkInfo::Wifi(ssid, signal, sec),
+            ConnectionInfo::Wifi(ssid, signal, sec, ..) => NetworkInfo::Wifi(ssid, signal, sec),
             ConnectionInfo::ConnectingWifi(ssid) => NetworkInfo::Wifi(ssid, 50, true),
             _ => {
                 panic!("Cannot cast disconnected states to NetworkInfo");
@@ -137,16 +686,324 @@ impl Default for KnownNetwork {
             auto: false,
             password: None,
             threshold: None,
+            bssid: None,
+            band: None,
+            band_only: false,
+            frequencies: Vec::new(),
+            metered: None,
+            data_cap: None,
+            mac_policy: None,
+            mtu: None,
+            dhcp_mtu: false,
         }
     }
 }
 
+// keyed by Ssid::key
 pub type KnownNetworks = HashMap<String, KnownNetwork>;
 
+// sent on the bus in place of a stored secret
+pub const HIDDEN_SECRET: &str = "********";
+
+// 802.1X credentials for a wired port
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WiredAuth {
+    // wpa_supplicant eap methods, e.g. "PEAP" or "TTLS TLS"
+    pub eap: String,
+    pub identity: String,
+    #[serde(default = "WiredAuth::default_text")]
+    pub anonymous_identity: Option<String>,
+    #[serde(default = "WiredAuth::default_text")]
+    pub password: Option<String>,
+    // e.g. "auth=MSCHAPV2"
+    #[serde(default = "WiredAuth::default_text")]
+    pub phase2: Option<String>,
+    #[serde(default = "WiredAuth::default_text")]
+    pub ca_cert: Option<String>,
+    #[serde(default = "WiredAuth::default_text")]
+    pub client_cert: Option<String>,
+    #[serde(default = "WiredAuth::default_text")]
+    pub private_key: Option<String>,
+    #[serde(default = "WiredAuth::default_text")]
+    pub private_key_password: Option<String>,
+}
+
+impl WiredAuth {
+    fn default_text() -> Option<String> {
+        None
+    }
+
+    fn secrets(&mut self) -> [&mut Option<String>; 2] {
+        [&mut self.password, &mut self.private_key_password]
+    }
+
+    // secrets are only reported as set
+    pub fn redacted(mut self) -> Self {
+        for secret in self.secrets().iter_mut() {
+            if secret.is_some() {
+                **secret = Some(HIDDEN_SECRET.to_owned());
+            }
+        }
+        self
+    }
+
+    // a redacted secret sent back keeps the stored one
+    pub fn keep_secrets(&mut self, stored: Option<&WiredAuth>) {
+        let mut stored = stored.cloned().unwrap_or_default();
+        for (secret, kept) in self.secrets().iter_mut().zip(stored.secrets().iter_mut()) {
+            if secret.as_deref() == Some(HIDDEN_SECRET) {
+                **secret = kept.take();
+            }
+        }
+    }
+
+    pub fn valid(&self) -> bool {
+        !self.eap.trim().is_empty()
+            && self
+                .eap
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-')
+    }
+}
+
+// keyed by the MAC address of the port or by the interface name
+pub type WiredCredentials = HashMap<String, WiredAuth>;
+
+// smallest mtu an ipv4 host has to take
+pub const MIN_MTU: u32 = 68;
+pub const DUPLEX_HALF: &str = "half";
+pub const DUPLEX_FULL: &str = "full";
+
+// ethernet negotiation, unset fields are left as they are
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkMode {
+    // Mb/s
+    pub speed: Option<u32>,
+    pub full_duplex: Option<bool>,
+    pub autoneg: Option<bool>,
+}
+
+impl LinkMode {
+    pub fn is_empty(&self) -> bool {
+        *self == LinkMode::default()
+    }
+}
+
+pub const IP_METHOD_DHCP: &str = "dhcp";
+pub const IP_METHOD_STATIC: &str = "static";
+
+// addressing of a wired port, applied to the ports it matches
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct WiredProfile {
+    #[serde(default = "WiredProfile::default_auto")]
+    pub auto: bool,
+    // unset criteria match any port
+    #[serde(default = "WiredProfile::default_text")]
+    pub interface: Option<String>,
+    #[serde(default = "WiredProfile::default_text")]
+    pub mac: Option<String>,
+    // known once a lease names the gateway
+    #[serde(default = "WiredProfile::default_text")]
+    pub gateway_mac: Option<String>,
+    // IP_METHOD_DHCP or IP_METHOD_STATIC
+    #[serde(default = "WiredProfile::default_method")]
+    pub method: String,
+    // CIDR, static only
+    #[serde(default = "WiredProfile::default_text")]
+    pub address: Option<String>,
+    #[serde(default = "WiredProfile::default_text")]
+    pub gateway: Option<String>,
+    // replace the servers of the lease if not empty
+    #[serde(default = "WiredProfile::default_dns")]
+    pub dns: Vec<String>,
+    #[serde(default = "WiredProfile::default_mtu")]
+    pub mtu: Option<u32>,
+    // the mtu offered by the dhcp server wins over the one above
+    #[serde(default = "WiredProfile::default_dhcp_mtu")]
+    pub dhcp_mtu: bool,
+    // Mb/s, advertised while autoneg is on and forced otherwise
+    #[serde(default = "WiredProfile::default_speed")]
+    pub speed: Option<u32>,
+    // "half" or "full"
+    #[serde(default = "WiredProfile::default_text")]
+    pub duplex: Option<String>,
+    // unset switches leave the port as it is
+    #[serde(default = "WiredProfile::default_switch")]
+    pub autoneg: Option<bool>,
+    #[serde(default = "WiredProfile::default_switch")]
+    pub wake_on_lan: Option<bool>,
+}
+
+impl WiredProfile {
+    fn default_auto() -> bool {
+        true
+    }
+
+    fn default_text() -> Option<String> {
+        None
+    }
+
+    fn default_method() -> String {
+        IP_METHOD_DHCP.to_owned()
+    }
+
+    fn default_dns() -> Vec<String> {
+        Vec::new()
+    }
+
+    fn default_mtu() -> Option<u32> {
+        None
+    }
+
+    fn default_dhcp_mtu() -> bool {
+        false
+    }
+
+    fn default_speed() -> Option<u32> {
+        None
+    }
+
+    fn default_switch() -> Option<bool> {
+        None
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.method == IP_METHOD_STATIC
+    }
+
+    pub fn link_mode(&self) -> LinkMode {
+        LinkMode {
+            speed: self.speed,
+            full_duplex: self.duplex.as_deref().map(|duplex| duplex == DUPLEX_FULL),
+            autoneg: self.autoneg,
+        }
+    }
+
+    pub fn link(&self) -> WiredLink {
+        WiredLink {
+            dhcp_mtu: self.dhcp_mtu,
+            speed: self.speed,
+            duplex: self.duplex.clone(),
+            autoneg: self.autoneg,
+            wake_on_lan: self.wake_on_lan,
+        }
+    }
+
+    pub fn set_link(&mut self, link: WiredLink) {
+        self.dhcp_mtu = link.dhcp_mtu;
+        self.speed = link.speed;
+        self.duplex = link.duplex;
+        self.autoneg = link.autoneg;
+        self.wake_on_lan = link.wake_on_lan;
+    }
+
+    pub fn valid(&self) -> bool {
+        let mac_ok = |mac: &Option<String>| mac.as_deref().is_none_or(AccessPoint::valid_bssid);
+        let ip_ok = |ip: &str| ip.parse::<std::net::IpAddr>().is_ok();
+        let address_ok = self.address.as_deref().is_none_or(|address| {
+            let mut parts = address.splitn(2, '/');
+            parts
+                .next()
+                .is_some_and(|ip| ip.parse::<std::net::Ipv4Addr>().is_ok())
+                && parts
+                    .next()
+                    .is_some_and(|prefix| prefix.parse::<u8>().is_ok_and(|prefix| prefix <= 32))
+        });
+        let method_ok = match self.method.as_str() {
+            IP_METHOD_DHCP => true,
+            IP_METHOD_STATIC => self.address.is_some(),
+            _ => false,
+        };
+        method_ok
+            && address_ok
+            && mac_ok(&self.mac)
+            && mac_ok(&self.gateway_mac)
+            && self.gateway.as_deref().is_none_or(ip_ok)
+            && self.dns.iter().all(|server| ip_ok(server))
+            && self.mtu.is_none_or(|mtu| mtu >= MIN_MTU)
+            && self.speed.is_none_or(|speed| speed > 0)
+            && self
+                .duplex
+                .as_deref()
+                .is_none_or(|duplex| duplex == DUPLEX_HALF || duplex == DUPLEX_FULL)
+    }
+
+    // how specific the profile is for a port, None if it does not apply
+    pub fn score(&self, iface: &str, mac: Option<&str>, gateway_mac: Option<&str>) -> Option<u32> {
+        let criteria = [
+            (&self.interface, Some(iface), 1),
+            (&self.mac, mac, 2),
+            (&self.gateway_mac, gateway_mac, 4),
+        ];
+        let mut score = 0;
+        for (wanted, actual, weight) in criteria.iter() {
+            if let Some(wanted) = wanted {
+                if !actual.is_some_and(|actual| actual.eq_ignore_ascii_case(wanted)) {
+                    return None;
+                }
+                score += weight;
+            }
+        }
+        Some(score)
+    }
+}
+
+impl Default for WiredProfile {
+    fn default() -> Self {
+        WiredProfile {
+            auto: true,
+            interface: None,
+            mac: None,
+            gateway_mac: None,
+            method: IP_METHOD_DHCP.to_owned(),
+            address: None,
+            gateway: None,
+            dns: Vec::new(),
+            mtu: None,
+            dhcp_mtu: false,
+            speed: None,
+            duplex: None,
+            autoneg: None,
+            wake_on_lan: None,
+        }
+    }
+}
+
+// the link settings of a wired profile, set apart from its addressing
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WiredLink {
+    pub dhcp_mtu: bool,
+    pub speed: Option<u32>,
+    pub duplex: Option<String>,
+    pub autoneg: Option<bool>,
+    pub wake_on_lan: Option<bool>,
+}
+
+// keyed by profile name
+pub type WiredProfiles = HashMap<String, WiredProfile>;
+
+// the most specific profile for a port, ties go to the first name
+pub fn match_wired<'a>(
+    profiles: &'a WiredProfiles,
+    iface: &str,
+    mac: Option<&str>,
+    gateway_mac: Option<&str>,
+) -> Option<(&'a String, &'a WiredProfile)> {
+    profiles
+        .iter()
+        .filter_map(|(name, profile)| {
+            profile
+                .score(iface, mac, gateway_mac)
+                .map(|score| (score, name, profile))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.cmp(a.1)))
+        .map(|(_, name, profile)| (name, profile))
+}
+
 #[derive(Eq, Clone)]
 pub enum NetworkInfo {
     Ethernet,
-    Wifi(String, u32, bool),
+    Wifi(Ssid, u32, bool),
 }
 
 impl NetworkInfo {
@@ -156,6 +1013,13 @@ impl NetworkInfo {
             _ => false,
         }
     }
+
+    pub fn essid(&self) -> &Ssid {
+        match self {
+            NetworkInfo::Wifi(ref essid, ..) => essid,
+            NetworkInfo::Ethernet => NO_SSID,
+        }
+    }
 }
 
 impl Ord for NetworkInfo {
@@ -194,13 +1058,17 @@ impl PartialOrd for NetworkInfo {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct NetworkList(Vec<NetworkInfo>);
 
 impl NetworkList {
     pub fn new() -> Self {
         NetworkList { 0: vec![] }
     }
+
+    pub fn ssids(&self) -> Vec<&Ssid> {
+        self.iter().map(NetworkInfo::essid).collect()
+    }
 }
 
 impl std::ops::Deref for NetworkList {
@@ -216,9 +1084,198 @@ impl std::ops::DerefMut for NetworkList {
     }
 }
 
+pub enum DhcpEvent {
+    Bound {
+        iface: String,
+        address: String,
+        router: Option<String>,
+        dns: Vec<String>,
+        // the server said the uplink is metered
+        metered: bool,
+        // interface mtu offered by the server
+        mtu: Option<u32>,
+    },
+    Lost {
+        iface: String,
+    },
+}
+
 pub enum CouldConnect {
     Connect(ConnectionSetting),
     Disconnect,
     Rescan,
     DoNothing,
 }
+
+// an access point run on the wifi interface to share the wired uplink
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotspotSetting {
+    pub essid: Ssid,
+    // open if unset
+    pub password: Option<String>,
+    pub band: Band,
+}
+
+impl HotspotSetting {
+    pub fn valid(&self) -> bool {
+        let password_ok = self.password.as_deref().is_none_or(|password| {
+            (8..=63).contains(&password.len())
+                && password.bytes().all(|b| b.is_ascii_graphic() || b == b' ')
+        });
+        !self.essid.is_empty()
+            && self.essid.as_bytes().len() <= 32
+            && password_ok
+            && matches!(self.band, Band::Ghz2 | Band::Ghz5)
+    }
+
+    // channel 6 or 36, neither needs radar detection
+    pub fn frequency(&self) -> u32 {
+        match self.band {
+            Band::Ghz5 => 5180,
+            _ => 2437,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotspotClient {
+    pub mac: String,
+    pub ip: String,
+    // as the client sent it, empty if it did not
+    pub hostname: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn most_specific_wired_profile_wins() {
+        let mut profiles = WiredProfiles::new();
+        profiles.insert("any".to_owned(), WiredProfile::default());
+        profiles.insert(
+            "dock".to_owned(),
+            WiredProfile {
+                mac: Some("02:00:00:00:00:0e".to_owned()),
+                ..WiredProfile::default()
+            },
+        );
+        profiles.insert(
+            "office".to_owned(),
+            WiredProfile {
+                interface: Some("eth0".to_owned()),
+                gateway_mac: Some("02:00:00:00:00:99".to_owned()),
+                ..WiredProfile::default()
+            },
+        );
+        let name =
+            |mac, gateway| match_wired(&profiles, "eth0", mac, gateway).map(|m| m.0.as_str());
+        assert_eq!(name(None, None), Some("any"));
+        assert_eq!(name(Some("02:00:00:00:00:0E"), None), Some("dock"));
+        assert_eq!(
+            name(Some("02:00:00:00:00:0e"), Some("02:00:00:00:00:99")),
+            Some("office")
+        );
+        assert!(
+            match_wired(&profiles, "eth1", None, Some("02:00:00:00:00:99"))
+                .is_some_and(|m| m.0 == "any")
+        );
+    }
+
+    #[test]
+    fn set_props_keeps_per_network_settings() {
+        let previous = KnownNetwork {
+            bssid: Some("02:00:00:00:00:01".to_owned()),
+            band: Some("5".to_owned()),
+            band_only: true,
+            frequencies: vec![5180],
+            metered: Some(true),
+            data_cap: Some(1 << 30),
+            mac_policy: Some("stable".to_owned()),
+            mtu: Some(1400),
+            dhcp_mtu: true,
+            ..KnownNetwork::new(true, true, false, "old", 0)
+        };
+        let updated = previous.with_props(KnownNetwork::new(false, true, true, "new", -70));
+        assert!(!updated.auto);
+        assert_eq!(
+            updated.password,
+            KnownNetwork::new(false, true, true, "new", -70).password
+        );
+        assert_eq!(updated.threshold, Some(-70));
+        let settings = KnownNetwork {
+            auto: previous.auto,
+            password: previous.password.clone(),
+            threshold: previous.threshold,
+            ..updated
+        };
+        assert!(settings == previous);
+    }
+
+    #[test]
+    fn static_wired_profile_needs_an_address() {
+        let mut profile = WiredProfile {
+            method: IP_METHOD_STATIC.to_owned(),
+            ..WiredProfile::default()
+        };
+        assert!(!profile.valid());
+        profile.address = Some("10.0.0.9/33".to_owned());
+        assert!(!profile.valid());
+        profile.address = Some("10.0.0.9/24".to_owned());
+        profile.dns = vec!["10.0.0.1".to_owned()];
+        assert!(profile.valid());
+        profile.dns.push("resolver".to_owned());
+        assert!(!profile.valid());
+    }
+
+    #[test]
+    fn band_policy_restricts_only_when_asked() {
+        let seen = [2412, 5180, 5240];
+        let any = BandPolicy::default();
+        assert_eq!(any.freq_list(&seen), None);
+
+        let mut prefer = BandPolicy {
+            band: Some(Band::Ghz5),
+            ..BandPolicy::default()
+        };
+        assert_eq!(prefer.freq_list(&seen), None);
+        prefer.frequencies = vec![2412, 5240];
+        assert_eq!(prefer.freq_list(&seen), Some(vec![2412, 5240]));
+
+        let mut only = BandPolicy {
+            only: true,
+            ..prefer
+        };
+        assert_eq!(only.freq_list(&seen), Some(vec![5240]));
+        assert_eq!(only.freq_list(&[2412]), Some(vec![5240]));
+        only.band = Some(Band::Ghz6);
+        only.frequencies.clear();
+        assert_eq!(only.freq_list(&seen), Some(vec![]));
+    }
+
+    #[test]
+    fn text_keys_are_kept() {
+        assert_eq!(Ssid::from("home").key(), "home");
+        assert_eq!(
+            Ssid::new(vec![0x63, 0x61, 0x66, 0xe9]).key(),
+            "hex:636166e9"
+        );
+        assert_eq!(Ssid::from("hex:00").key(), "hex:6865783a3030");
+        assert_eq!(Ssid::from_key("hex:zz"), Ssid::from("hex:zz"));
+        assert_eq!(Ssid::new(vec![0x63, 0xe9]).to_string(), "c\u{fffd}");
+    }
+
+    proptest! {
+        #[test]
+        fn keys_are_reversible(bytes in vec(any::<u8>(), 0..33)) {
+            let ssid = Ssid::new(bytes);
+            prop_assert_eq!(Ssid::from_key(&ssid.key()), ssid);
+        }
+
+        #[test]
+        fn from_key_is_total(key in any::<String>()) {
+            let _ = Ssid::from_key(&key);
+        }
+    }
+}
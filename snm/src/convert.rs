@@ -1,30 +1,74 @@
-use super::connection::{ConnectionSetting, KnownNetwork};
+use super::connection::{
+    AccessPoint, Band, BandPolicy, ConnectionInfo, ConnectionSetting, ConnectionStatus,
+    Connectivity, DataUsage, HotspotClient, HotspotSetting, KnownNetwork, LinkStats, MacPolicy,
+    MeteredState, NetworkInfo, NetworkList, OnlineCriteria, RoamEvent, Security, Ssid, Statistics,
+    StatusChange, WiredAuth, WiredLink, WiredProfile, MIN_MTU,
+};
+use super::error::{Error, Reason};
 use rustbus::message_builder::MarshalledMessage;
 use rustbus::params::{Base, Container, Param};
 use std::convert::TryFrom;
 
-fn dbus_convert<'a, 'e, T: TryFrom<&'a Base<'a>>>(p: &'a Param<'a, 'e>) -> Result<T, ()> {
+fn dbus_convert<'a, 'e, T: TryFrom<&'a Base<'a>>>(p: &'a Param<'a, 'e>) -> Result<T, Error> {
     if let Param::Base(ref base) = p {
-        return T::try_from(base).map_err(|_| ());
+        return T::try_from(base).map_err(|_| Error::InvalidArgs);
     }
-    Err(())
+    Err(Error::InvalidArgs)
+}
+
+fn dbus_struct<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<&'a Vec<Param<'a, 'e>>, Error> {
+    if let Param::Container(Container::Struct(fields)) = p {
+        return Ok(fields);
+    }
+    Err(Error::InvalidArgs)
+}
+
+fn dbus_array<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<&'a Vec<Param<'a, 'e>>, Error> {
+    if let Param::Container(Container::Array(array)) = p {
+        return Ok(&array.values);
+    }
+    Err(Error::InvalidArgs)
+}
+
+fn dbus_ssid<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<Ssid, Error> {
+    dbus_array(p)?
+        .iter()
+        .map(|byte| dbus_convert::<u8>(byte))
+        .collect::<Result<Vec<u8>, Error>>()
+        .map(Ssid::new)
+}
+
+// the raw ssid follows the display string in newer replies and signals
+fn raw_ssid<'a, 'e>(
+    params: &'a [Param<'a, 'e>],
+    index: usize,
+    display: String,
+) -> Result<Ssid, Error> {
+    params.get(index).map_or(Ok(Ssid::from(display)), dbus_ssid)
+}
+
+fn single<'a, 'e>(params: &'a [Param<'a, 'e>]) -> Result<&'a Param<'a, 'e>, Error> {
+    if params.len() == 1 {
+        return Ok(&params[0]);
+    }
+    Err(Error::InvalidArgs)
 }
 
 pub trait Convert: Sized {
-    fn from_params(params: &Vec<Param>) -> Result<Self, ()>;
+    fn from_params(params: &Vec<Param>) -> Result<Self, Error>;
 }
 
 impl Convert for String {
-    fn from_params(params: &Vec<Param>) -> Result<String, ()> {
+    fn from_params(params: &Vec<Param>) -> Result<String, Error> {
         if params.len() == 1 {
             return dbus_convert(&params[0]);
         }
-        Err(())
+        Err(Error::InvalidArgs)
     }
 }
 
 impl Convert for ConnectionSetting {
-    fn from_params(params: &Vec<Param>) -> Result<ConnectionSetting, ()> {
+    fn from_params(params: &Vec<Param>) -> Result<ConnectionSetting, Error> {
         if params.len() == 1 {
             if let Param::Container(c) = &params[0] {
                 if let Container::Struct(p) = c {
@@ -35,18 +79,28 @@ impl Convert for ConnectionSetting {
                                 return Ok(ConnectionSetting::Ethernet);
                             }
                             2 => {
-                                let essid = dbus_convert::<String>(&p[1])?;
+                                let essid = Ssid::from_key(&dbus_convert::<String>(&p[1])?);
                                 let enc = dbus_convert::<bool>(&p[2])?;
                                 return if enc {
                                     Ok(ConnectionSetting::Wifi {
                                         essid,
                                         password: "".to_owned(),
                                         threshold: None,
+                                        bssid: None,
+                                        bands: BandPolicy::default(),
+                                        mac_policy: MacPolicy::Permanent,
+                                        mtu: None,
+                                        dhcp_mtu: false,
                                     })
                                 } else {
                                     Ok(ConnectionSetting::OpenWifi {
                                         essid,
                                         threshold: None,
+                                        bssid: None,
+                                        bands: BandPolicy::default(),
+                                        mac_policy: MacPolicy::Permanent,
+                                        mtu: None,
+                                        dhcp_mtu: false,
                                     })
                                 };
                             }
@@ -56,14 +110,14 @@ impl Convert for ConnectionSetting {
                 }
             }
         }
-        Err(())
+        Err(Error::InvalidArgs)
     }
 }
 
 impl Convert for (String, KnownNetwork) {
-    fn from_params(params: &Vec<Param>) -> Result<(String, KnownNetwork), ()> {
+    fn from_params(params: &Vec<Param>) -> Result<(String, KnownNetwork), Error> {
         if params.len() == 6 {
-            let essid = dbus_convert::<String>(&params[0])?;
+            let essid = Ssid::from_key(&dbus_convert::<String>(&params[0])?).key();
             let password = dbus_convert::<String>(&params[1])?;
             let threshold = dbus_convert::<i32>(&params[2])?;
             let auto = dbus_convert::<bool>(&params[3])?;
@@ -74,12 +128,698 @@ impl Convert for (String, KnownNetwork) {
                 KnownNetwork::new(auto, enc, roaming, &password, threshold),
             ));
         }
-        Err(())
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for (String, String) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, String), Error> {
+        if params.len() == 2 {
+            return Ok((
+                dbus_convert::<String>(&params[0])?,
+                dbus_convert::<String>(&params[1])?,
+            ));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+fn dbus_band<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<Option<Band>, Error> {
+    let name = dbus_convert::<String>(p)?;
+    if name.is_empty() {
+        return Ok(None);
+    }
+    Band::from_name(&name).map(Some).ok_or(Error::InvalidArgs)
+}
+
+fn dbus_frequencies<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<Vec<u32>, Error> {
+    dbus_array(p)?.iter().map(dbus_convert::<u32>).collect()
+}
+
+impl Convert for (String, BandPolicy) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, BandPolicy), Error> {
+        if params.len() == 4 {
+            let essid = Ssid::from_key(&dbus_convert::<String>(&params[0])?).key();
+            let bands = BandPolicy {
+                band: dbus_band(&params[1])?,
+                only: dbus_convert::<bool>(&params[2])?,
+                frequencies: dbus_frequencies(&params[3])?,
+            };
+            return Ok((essid, bands));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+// 0 leaves it unset or to the dhcp server, 1 is on and 2 is off
+fn dbus_switch<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<Option<bool>, Error> {
+    match dbus_convert::<u32>(p)? {
+        0 => Ok(None),
+        1 => Ok(Some(true)),
+        2 => Ok(Some(false)),
+        _ => Err(Error::InvalidArgs),
+    }
+}
+
+// a zero cap is no cap
+fn dbus_data_cap<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<Option<u64>, Error> {
+    let cap = dbus_convert::<u64>(p)?;
+    Ok(if cap == 0 { None } else { Some(cap) })
+}
+
+// empty for the permanent address
+fn dbus_mac_policy<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<Option<MacPolicy>, Error> {
+    let name = dbus_convert::<String>(p)?;
+    if name.is_empty() {
+        return Ok(None);
+    }
+    MacPolicy::from_name(&name)
+        .map(Some)
+        .ok_or(Error::InvalidArgs)
+}
+
+impl Convert for (String, Option<MacPolicy>) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, Option<MacPolicy>), Error> {
+        if params.len() == 2 {
+            let essid = Ssid::from_key(&dbus_convert::<String>(&params[0])?).key();
+            return Ok((essid, dbus_mac_policy(&params[1])?));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+// a zero mtu is the default one
+impl Convert for (String, Option<u32>, bool) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, Option<u32>, bool), Error> {
+        if params.len() == 3 {
+            let mtu = dbus_convert::<u32>(&params[1])?;
+            if mtu > 0 && mtu < MIN_MTU {
+                return Err(Error::InvalidArgs);
+            }
+            let essid = Ssid::from_key(&dbus_convert::<String>(&params[0])?).key();
+            return Ok((
+                essid,
+                Some(mtu).filter(|mtu| *mtu > 0),
+                dbus_convert::<bool>(&params[2])?,
+            ));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for (String, Option<bool>, Option<u64>) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, Option<bool>, Option<u64>), Error> {
+        if params.len() == 3 {
+            let essid = Ssid::from_key(&dbus_convert::<String>(&params[0])?).key();
+            return Ok((essid, dbus_switch(&params[1])?, dbus_data_cap(&params[2])?));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+// an empty eap method stands for no credentials
+fn wired_auth<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<Option<WiredAuth>, Error> {
+    let p = dbus_struct(p)?;
+    if p.len() != 9 {
+        return Err(Error::InvalidArgs);
+    }
+    let text = |index: usize| -> Result<Option<String>, Error> {
+        let value = dbus_convert::<String>(&p[index])?;
+        Ok(if value.is_empty() { None } else { Some(value) })
+    };
+    let auth = WiredAuth {
+        eap: dbus_convert::<String>(&p[0])?,
+        identity: dbus_convert::<String>(&p[1])?,
+        anonymous_identity: text(2)?,
+        password: text(3)?,
+        phase2: text(4)?,
+        ca_cert: text(5)?,
+        client_cert: text(6)?,
+        private_key: text(7)?,
+        private_key_password: text(8)?,
+    };
+    if auth.eap.is_empty() {
+        return Ok(None);
+    }
+    if auth.valid() {
+        Ok(Some(auth))
+    } else {
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for (String, Option<WiredAuth>) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, Option<WiredAuth>), Error> {
+        if params.len() == 2 {
+            return Ok((dbus_convert::<String>(&params[0])?, wired_auth(&params[1])?));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+fn wired_profile<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<WiredProfile, Error> {
+    let p = dbus_struct(p)?;
+    if p.len() != 9 {
+        return Err(Error::InvalidArgs);
+    }
+    let text = |index: usize| -> Result<Option<String>, Error> {
+        let value = dbus_convert::<String>(&p[index])?;
+        Ok(if value.is_empty() { None } else { Some(value) })
+    };
+    let profile = WiredProfile {
+        auto: dbus_convert::<bool>(&p[0])?,
+        interface: text(1)?,
+        mac: text(2)?.map(|mac| mac.to_ascii_lowercase()),
+        gateway_mac: text(3)?.map(|mac| mac.to_ascii_lowercase()),
+        method: dbus_convert::<String>(&p[4])?,
+        address: text(5)?,
+        gateway: text(6)?,
+        dns: dbus_array(&p[7])?
+            .iter()
+            .map(dbus_convert::<String>)
+            .collect::<Result<Vec<String>, Error>>()?,
+        mtu: Some(dbus_convert::<u32>(&p[8])?).filter(|mtu| *mtu > 0),
+        ..WiredProfile::default()
+    };
+    if profile.valid() {
+        Ok(profile)
+    } else {
+        Err(Error::InvalidArgs)
+    }
+}
+
+fn wired_link<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<WiredLink, Error> {
+    let p = dbus_struct(p)?;
+    if p.len() != 5 {
+        return Err(Error::InvalidArgs);
+    }
+    let duplex = dbus_convert::<String>(&p[2])?;
+    let link = WiredLink {
+        dhcp_mtu: dbus_convert::<bool>(&p[0])?,
+        speed: Some(dbus_convert::<u32>(&p[1])?).filter(|speed| *speed > 0),
+        duplex: if duplex.is_empty() {
+            None
+        } else {
+            Some(duplex)
+        },
+        autoneg: dbus_switch(&p[3])?,
+        wake_on_lan: dbus_switch(&p[4])?,
+    };
+    let mut profile = WiredProfile::default();
+    profile.set_link(link.clone());
+    if profile.valid() {
+        Ok(link)
+    } else {
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for WiredProfile {
+    fn from_params(params: &Vec<Param>) -> Result<WiredProfile, Error> {
+        match params.len() {
+            1 => wired_profile(&params[0]),
+            2 => {
+                let mut profile = wired_profile(&params[0])?;
+                profile.set_link(wired_link(&params[1])?);
+                Ok(profile)
+            }
+            _ => Err(Error::InvalidArgs),
+        }
+    }
+}
+
+impl Convert for (String, WiredLink) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, WiredLink), Error> {
+        if params.len() == 2 {
+            let name = dbus_convert::<String>(&params[0])?;
+            if !name.is_empty() {
+                return Ok((name, wired_link(&params[1])?));
+            }
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for (String, WiredProfile) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, WiredProfile), Error> {
+        if params.len() == 2 {
+            let name = dbus_convert::<String>(&params[0])?;
+            if !name.is_empty() {
+                return Ok((name, wired_profile(&params[1])?));
+            }
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+// an empty password starts an open hotspot, an empty band means 2.4
+impl Convert for HotspotSetting {
+    fn from_params(params: &Vec<Param>) -> Result<HotspotSetting, Error> {
+        if params.len() == 3 {
+            let password = dbus_convert::<String>(&params[1])?;
+            return Ok(HotspotSetting {
+                essid: Ssid::from_key(&dbus_convert::<String>(&params[0])?),
+                password: Some(password).filter(|password| !password.is_empty()),
+                band: dbus_band(&params[2])?.unwrap_or(Band::Ghz2),
+            });
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+// the reply of get_hotspot, a protected hotspot comes with an empty password
+impl Convert for Option<(HotspotSetting, String)> {
+    fn from_params(params: &Vec<Param>) -> Result<Option<(HotspotSetting, String)>, Error> {
+        if params.len() != 3 {
+            return Err(Error::InvalidArgs);
+        }
+        let p = dbus_struct(&params[1])?;
+        if p.len() != 3 {
+            return Err(Error::InvalidArgs);
+        }
+        if !dbus_convert::<bool>(&params[0])? {
+            return Ok(None);
+        }
+        let protected = dbus_convert::<bool>(&p[2])?;
+        let setting = HotspotSetting {
+            essid: Ssid::from_key(&dbus_convert::<String>(&p[0])?),
+            password: Some(String::new()).filter(|_| protected),
+            band: dbus_band(&p[1])?.unwrap_or(Band::Ghz2),
+        };
+        Ok(Some((setting, dbus_convert::<String>(&params[2])?)))
+    }
+}
+
+impl Convert for Vec<HotspotClient> {
+    fn from_params(params: &Vec<Param>) -> Result<Vec<HotspotClient>, Error> {
+        dbus_array(single(params)?)?
+            .iter()
+            .map(|client| {
+                let p = dbus_struct(client)?;
+                if p.len() != 3 {
+                    return Err(Error::InvalidArgs);
+                }
+                Ok(HotspotClient {
+                    mac: dbus_convert::<String>(&p[0])?,
+                    ip: dbus_convert::<String>(&p[1])?,
+                    hostname: dbus_convert::<String>(&p[2])?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Convert for (Vec<String>, String) {
+    fn from_params(params: &Vec<Param>) -> Result<(Vec<String>, String), Error> {
+        if params.len() == 2 {
+            let names = dbus_array(&params[0])?
+                .iter()
+                .map(dbus_convert::<String>)
+                .collect::<Result<Vec<String>, Error>>()?;
+            return Ok((names, dbus_convert::<String>(&params[1])?));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for (u32, OnlineCriteria) {
+    fn from_params(params: &Vec<Param>) -> Result<(u32, OnlineCriteria), Error> {
+        if params.len() == 3 {
+            let timeout = dbus_convert::<u32>(&params[0])?;
+            let criteria = match dbus_convert::<u32>(&params[1])? {
+                0 => OnlineCriteria::Any,
+                1 => OnlineCriteria::Interface(dbus_convert::<String>(&params[2])?),
+                2 => OnlineCriteria::Ipv4,
+                3 => OnlineCriteria::Ipv6,
+                4 => OnlineCriteria::Connectivity,
+                _ => return Err(Error::InvalidArgs),
+            };
+            return Ok((timeout, criteria));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for u32 {
+    fn from_params(params: &Vec<Param>) -> Result<u32, Error> {
+        dbus_convert(single(params)?)
+    }
+}
+
+impl Convert for bool {
+    fn from_params(params: &Vec<Param>) -> Result<bool, Error> {
+        dbus_convert(single(params)?)
+    }
+}
+
+impl Convert for ConnectionInfo {
+    fn from_params(params: &Vec<Param>) -> Result<ConnectionInfo, Error> {
+        if params.is_empty() || params.len() > 4 {
+            return Err(Error::InvalidArgs);
+        }
+        let p = dbus_struct(&params[0])?;
+        if p.len() == 5 {
+            let essid = raw_ssid(params, 1, dbus_convert::<String>(&p[1])?)?;
+            let band = match params.get(2).map(dbus_convert::<u32>).transpose()? {
+                Some(1) => Band::Ghz2,
+                Some(2) => Band::Ghz5,
+                Some(3) => Band::Ghz6,
+                _ => Band::Unknown,
+            };
+            let enc = dbus_convert::<bool>(&p[2])?;
+            let quality = dbus_convert::<u32>(&p[3])?;
+            let ip = dbus_convert::<String>(&p[4])?;
+            return match dbus_convert::<u32>(&p[0])? {
+                0 => Ok(ConnectionInfo::NotConnected),
+                1 => Ok(ConnectionInfo::Ethernet(ip)),
+                2 => Ok(ConnectionInfo::Wifi(essid, quality, enc, ip, band)),
+                3 => Ok(ConnectionInfo::ConnectingEth),
+                4 => Ok(ConnectionInfo::ConnectingWifi(essid)),
+                _ => Err(Error::InvalidArgs),
+            };
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+// the state with the address of the interface in use
+impl Convert for (ConnectionInfo, String) {
+    fn from_params(params: &Vec<Param>) -> Result<(ConnectionInfo, String), Error> {
+        let mac = match params.get(3) {
+            Some(mac) => dbus_convert::<String>(mac)?,
+            None => String::new(),
+        };
+        Ok((ConnectionInfo::from_params(params)?, mac))
+    }
+}
+
+fn network_info(param: &Param, essid: Option<&Param>) -> Result<NetworkInfo, Error> {
+    let p = dbus_struct(param)?;
+    if p.len() == 4 {
+        return match dbus_convert::<u32>(&p[0])? {
+            1 => Ok(NetworkInfo::Ethernet),
+            2 => Ok(NetworkInfo::Wifi(
+                match essid {
+                    Some(essid) => dbus_ssid(essid)?,
+                    None => Ssid::from(dbus_convert::<String>(&p[1])?),
+                },
+                dbus_convert::<u32>(&p[3])?,
+                dbus_convert::<bool>(&p[2])?,
+            )),
+            _ => Err(Error::InvalidArgs),
+        };
+    }
+    Err(Error::InvalidArgs)
+}
+
+impl Convert for NetworkList {
+    fn from_params(params: &Vec<Param>) -> Result<NetworkList, Error> {
+        let ssids = match params.len() {
+            1 => None,
+            2 => Some(dbus_array(&params[1])?),
+            _ => return Err(Error::InvalidArgs),
+        };
+        let values = dbus_array(&params[0])?;
+        if ssids.is_some_and(|ssids| ssids.len() != values.len()) {
+            return Err(Error::InvalidArgs);
+        }
+        let mut networks = NetworkList::new();
+        for (i, value) in values.iter().enumerate() {
+            networks.push(network_info(value, ssids.map(|ssids| &ssids[i]))?);
+        }
+        Ok(networks)
+    }
+}
+
+// the pinned bssid, band policy and metering follow the properties in newer replies
+impl Convert for KnownNetwork {
+    fn from_params(params: &Vec<Param>) -> Result<KnownNetwork, Error> {
+        if !matches!(params.len(), 1 | 2 | 5 | 7 | 8 | 10) {
+            return Err(Error::InvalidArgs);
+        }
+        let p = dbus_struct(&params[0])?;
+        if p.len() == 5 {
+            let password = dbus_convert::<String>(&p[0])?;
+            let threshold = dbus_convert::<i32>(&p[1])?;
+            let auto = dbus_convert::<bool>(&p[2])?;
+            let enc = dbus_convert::<bool>(&p[3])?;
+            let roaming = dbus_convert::<bool>(&p[4])?;
+            let mut network = KnownNetwork::new(auto, enc, roaming, &password, threshold);
+            if let Some(bssid) = params.get(1) {
+                let bssid = dbus_convert::<String>(bssid)?;
+                if !bssid.is_empty() {
+                    network.bssid = Some(bssid);
+                }
+            }
+            if params.len() >= 7 {
+                network.metered = dbus_switch(&params[5])?;
+                network.data_cap = dbus_data_cap(&params[6])?;
+            }
+            if let Some(policy) = params.get(7) {
+                network.mac_policy =
+                    dbus_mac_policy(policy)?.map(|policy| policy.name().to_owned());
+            }
+            if params.len() == 10 {
+                network.mtu = Some(dbus_convert::<u32>(&params[8])?).filter(|mtu| *mtu > 0);
+                network.dhcp_mtu = dbus_convert::<bool>(&params[9])?;
+            }
+            if params.len() >= 5 {
+                network.set_bands(BandPolicy {
+                    band: dbus_band(&params[2])?,
+                    only: dbus_convert::<bool>(&params[3])?,
+                    frequencies: dbus_frequencies(&params[4])?,
+                });
+            }
+            return Ok(network);
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+fn access_point(param: &Param) -> Result<AccessPoint, Error> {
+    let p = dbus_struct(param)?;
+    if p.len() == 9 {
+        let security = match dbus_convert::<u32>(&p[7])? {
+            0 => Security::Open,
+            1 => Security::Wep,
+            2 => Security::Wpa,
+            3 => Security::Wpa2,
+            4 => Security::Wpa3,
+            _ => return Err(Error::InvalidArgs),
+        };
+        return Ok(AccessPoint {
+            bssid: dbus_convert::<String>(&p[0])?,
+            essid: dbus_ssid(&p[1])?,
+            frequency: dbus_convert::<u32>(&p[2])?,
+            signal: dbus_convert::<i32>(&p[5])?,
+            last_seen: dbus_convert::<u32>(&p[6])?,
+            security,
+            capabilities: dbus_convert::<String>(&p[8])?,
+        });
+    }
+    Err(Error::InvalidArgs)
+}
+
+impl Convert for Vec<AccessPoint> {
+    fn from_params(params: &Vec<Param>) -> Result<Vec<AccessPoint>, Error> {
+        dbus_array(single(params)?)?
+            .iter()
+            .map(access_point)
+            .collect()
+    }
+}
+
+impl Convert for (String, LinkStats) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, LinkStats), Error> {
+        if params.len() != 2 {
+            return Err(Error::InvalidArgs);
+        }
+        let p = dbus_struct(&params[1])?;
+        if p.len() != 5 {
+            return Err(Error::InvalidArgs);
+        }
+        let stats = LinkStats {
+            signal: dbus_convert::<i32>(&p[0])?,
+            tx_bitrate: dbus_convert::<u32>(&p[2])?,
+            rx_bitrate: dbus_convert::<u32>(&p[3])?,
+            connected_time: dbus_convert::<u32>(&p[4])?,
+        };
+        Ok((dbus_convert::<String>(&params[0])?, stats))
+    }
+}
+
+impl Convert for (String, Statistics) {
+    fn from_params(params: &Vec<Param>) -> Result<(String, Statistics), Error> {
+        if params.len() != 2 {
+            return Err(Error::InvalidArgs);
+        }
+        let counters = dbus_struct(&params[1])?
+            .iter()
+            .map(dbus_convert::<u64>)
+            .collect::<Result<Vec<u64>, Error>>()?;
+        if counters.len() != 10 {
+            return Err(Error::InvalidArgs);
+        }
+        let stats = Statistics {
+            rx_bytes: counters[0],
+            tx_bytes: counters[1],
+            rx_packets: counters[2],
+            tx_packets: counters[3],
+            rx_errors: counters[4],
+            tx_errors: counters[5],
+            rx_dropped: counters[6],
+            tx_dropped: counters[7],
+            rx_rate: counters[8],
+            tx_rate: counters[9],
+        };
+        Ok((dbus_convert::<String>(&params[0])?, stats))
+    }
+}
+
+impl Convert for DataUsage {
+    fn from_params(params: &Vec<Param>) -> Result<DataUsage, Error> {
+        if params.len() != 2 && params.len() != 4 {
+            return Err(Error::InvalidArgs);
+        }
+        let month = |index: usize| params.get(index).map_or(Ok(0), dbus_convert::<u64>);
+        Ok(DataUsage {
+            rx: dbus_convert::<u64>(&params[0])?,
+            tx: dbus_convert::<u64>(&params[1])?,
+            month: 0,
+            month_rx: month(2)?,
+            month_tx: month(3)?,
+        })
     }
 }
 
-pub fn convert<T: Convert>(msg: MarshalledMessage) -> Result<T, ()> {
+fn connection_status<'a, 'e>(p: &'a Param<'a, 'e>) -> Result<ConnectionStatus, Error> {
+    match dbus_convert::<u32>(p)? {
+        0 => Ok(ConnectionStatus::Initializing),
+        1 => Ok(ConnectionStatus::Connecting),
+        2 => Ok(ConnectionStatus::Authenticating),
+        3 => Ok(ConnectionStatus::GettingIP),
+        4 => Ok(ConnectionStatus::AuthFail),
+        5 => Ok(ConnectionStatus::Aborted),
+        6 => Ok(ConnectionStatus::ConnectFail),
+        _ => Err(Error::InvalidArgs),
+    }
+}
+
+impl Convert for MeteredState {
+    fn from_params(params: &Vec<Param>) -> Result<MeteredState, Error> {
+        if params.len() == 2 {
+            return Ok(MeteredState {
+                metered: dbus_convert::<bool>(&params[0])?,
+                over_cap: dbus_convert::<bool>(&params[1])?,
+            });
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for ConnectionStatus {
+    fn from_params(params: &Vec<Param>) -> Result<ConnectionStatus, Error> {
+        connection_status(single(params)?)
+    }
+}
+
+impl Convert for StatusChange {
+    fn from_params(params: &Vec<Param>) -> Result<StatusChange, Error> {
+        let status = connection_status(params.first().ok_or(Error::InvalidArgs)?)?;
+        if params.len() == 1 {
+            return Ok(StatusChange {
+                status,
+                reason: Reason::None,
+                essid: Ssid::default(),
+                iface: String::new(),
+                op: 0,
+            });
+        }
+        if params.len() == 5 || params.len() == 6 {
+            return Ok(StatusChange {
+                status,
+                reason: Reason::from_code(dbus_convert::<u32>(&params[1])?)
+                    .ok_or(Error::InvalidArgs)?,
+                essid: raw_ssid(params, 5, dbus_convert::<String>(&params[2])?)?,
+                iface: dbus_convert::<String>(&params[3])?,
+                op: dbus_convert::<u32>(&params[4])?,
+            });
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for RoamEvent {
+    fn from_params(params: &Vec<Param>) -> Result<RoamEvent, Error> {
+        if params.len() < 4 {
+            return Err(Error::InvalidArgs);
+        }
+        Ok(RoamEvent {
+            iface: dbus_convert::<String>(&params[0])?,
+            from: dbus_convert::<String>(&params[1])?,
+            to: dbus_convert::<String>(&params[2])?,
+            essid: raw_ssid(params, 4, dbus_convert::<String>(&params[3])?)?,
+        })
+    }
+}
+
+impl Convert for (u32, Reason) {
+    fn from_params(params: &Vec<Param>) -> Result<(u32, Reason), Error> {
+        if params.len() == 2 {
+            let op = dbus_convert::<u32>(&params[0])?;
+            let reason =
+                Reason::from_code(dbus_convert::<u32>(&params[1])?).ok_or(Error::InvalidArgs)?;
+            return Ok((op, reason));
+        }
+        Err(Error::InvalidArgs)
+    }
+}
+
+impl Convert for Connectivity {
+    fn from_params(params: &Vec<Param>) -> Result<Connectivity, Error> {
+        match dbus_convert::<u32>(single(params)?)? {
+            0 => Ok(Connectivity::Unknown),
+            1 => Ok(Connectivity::None),
+            2 => Ok(Connectivity::Portal),
+            3 => Ok(Connectivity::Limited),
+            4 => Ok(Connectivity::Full),
+            _ => Err(Error::InvalidArgs),
+        }
+    }
+}
+
+pub fn convert<T: Convert>(msg: MarshalledMessage) -> Result<T, Error> {
     msg.unmarshall_all()
-        .map_err(|_| ())
+        .map_err(|_| Error::InvalidArgs)
         .and_then(|m| T::from_params(&m.params))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> Param<'static, 'static> {
+        Param::Base(Base::String(value.to_owned()))
+    }
+
+    #[test]
+    fn mac_policy_essid_is_normalized() {
+        let params = vec![string("hex:686f6d65"), string("stable")];
+        let (essid, policy) = <(String, Option<MacPolicy>)>::from_params(&params).unwrap();
+        assert_eq!(essid, "home");
+        assert!(matches!(policy, Some(MacPolicy::Stable)));
+    }
+
+    #[test]
+    fn mtu_essid_is_normalized() {
+        let params = vec![
+            string("hex:686f6d65"),
+            Param::Base(Base::Uint32(1400)),
+            Param::Base(Base::Boolean(false)),
+        ];
+        let (essid, mtu, dhcp_mtu) = <(String, Option<u32>, bool)>::from_params(&params).unwrap();
+        assert_eq!(essid, "home");
+        assert_eq!(mtu, Some(1400));
+        assert!(!dhcp_mtu);
+    }
+}
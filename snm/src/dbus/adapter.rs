@@ -1,10 +1,12 @@
+use std::env;
 use std::sync::{Arc, Mutex};
 
 use rustbus::connection::{get_session_bus_path, get_system_bus_path, Error, Timeout};
 use rustbus::message_builder::MarshalledMessage;
 use rustbus::{standard_messages, DuplexConn, RecvConn, SendConn};
+use rustbus_nix::sys::socket::UnixAddr;
 
-use super::emitter;
+use super::{emitter, responder};
 
 #[allow(dead_code)]
 pub enum Bus {
@@ -12,6 +14,35 @@ pub enum Bus {
     System,
 }
 
+const SYSTEM_BUS_ADDRESS: &str = "DBUS_SYSTEM_BUS_ADDRESS";
+
+// rustbus always uses the well known system bus socket, honor the override like libdbus does
+pub fn bus_path(bus: &Bus) -> Result<UnixAddr, Error> {
+    match bus {
+        Bus::Session => get_session_bus_path(),
+        Bus::System => match env::var(SYSTEM_BUS_ADDRESS) {
+            Ok(address) => parse_address(&address),
+            Err(_) => get_system_bus_path(),
+        },
+    }
+}
+
+fn parse_address(address: &str) -> Result<UnixAddr, Error> {
+    for entry in address.split(';') {
+        if let Some(params) = entry.strip_prefix("unix:") {
+            for param in params.split(',') {
+                if let Some(path) = param.strip_prefix("path=") {
+                    return Ok(UnixAddr::new(path)?);
+                }
+                if let Some(name) = param.strip_prefix("abstract=") {
+                    return Ok(UnixAddr::new_abstract(name.as_bytes())?);
+                }
+            }
+        }
+    }
+    Err(Error::AddressTypeNotSupported(address.to_owned()))
+}
+
 pub struct Adapter {
     send: Arc<Mutex<SendConn>>,
     recv: RecvConn,
@@ -20,11 +51,7 @@ pub struct Adapter {
 
 impl Adapter {
     pub fn new(bus: Bus, iface: &str) -> Result<Self, Error> {
-        let path = match bus {
-            Bus::Session => get_session_bus_path()?,
-            Bus::System => get_system_bus_path()?,
-        };
-        let mut conn = DuplexConn::connect_to_bus(path, false)?;
+        let mut conn = DuplexConn::connect_to_bus(bus_path(&bus)?, false)?;
         conn.send_hello(Timeout::Infinite)?;
         conn.send
             .send_message(&mut standard_messages::request_name(
@@ -45,6 +72,10 @@ impl Adapter {
         emitter::Emitter::new(self.send.clone(), &self.iface, object)
     }
 
+    pub fn new_responder(&self) -> responder::Responder {
+        responder::Responder::new(self.send.clone())
+    }
+
     pub fn run<
         Service,
         Handler: FnMut(&mut Service, MarshalledMessage) -> Option<MarshalledMessage>,
@@ -1,5 +1,6 @@
 use rustbus::connection::Error;
-use rustbus::message_builder::MessageBuilder;
+use rustbus::message_builder::{MarshalledMessageBody, MessageBuilder};
+use rustbus::wire::errors::MarshalError;
 use rustbus::{Marshal, SendConn};
 use std::sync::{Arc, Mutex};
 
@@ -20,10 +21,17 @@ impl Emitter {
     }
 
     pub fn emit<P: Marshal>(&mut self, member: &str, param: P) -> Result<u32, Error> {
+        self.emit_with(member, |body| body.push_param(param))
+    }
+
+    pub fn emit_with<F>(&mut self, member: &str, push: F) -> Result<u32, Error>
+    where
+        F: FnOnce(&mut MarshalledMessageBody) -> Result<(), MarshalError>,
+    {
         let mut sig = MessageBuilder::new()
             .signal(&self.iface, member, &self.object)
             .build();
-        sig.body.push_param(param)?;
+        push(&mut sig.body)?;
         self.connection
             .lock()
             .unwrap()
@@ -1,7 +1,11 @@
 mod adapter;
 mod emitter;
+mod proxy;
 mod proxy_tracker;
+mod responder;
 
 pub use adapter::{Adapter, Bus};
 pub use emitter::Emitter;
+pub use proxy::{CallError, Proxy};
 pub use proxy_tracker::ProxyTracker;
+pub use responder::Responder;
@@ -0,0 +1,83 @@
+use rustbus::connection::{Error, Timeout};
+use rustbus::message_builder::MarshalledMessage;
+use rustbus::{standard_messages, MessageBuilder, MessageType, RpcConn};
+
+use super::adapter::{bus_path, Bus};
+
+pub enum CallError {
+    Bus(Error),
+    Remote(String, String),
+}
+
+impl std::fmt::Display for CallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CallError::Bus(e) => write!(f, "{}", e),
+            CallError::Remote(name, text) if text.is_empty() => write!(f, "{}", name),
+            CallError::Remote(name, text) => write!(f, "{}: {}", name, text),
+        }
+    }
+}
+
+impl From<Error> for CallError {
+    fn from(e: Error) -> Self {
+        CallError::Bus(e)
+    }
+}
+
+pub struct Proxy {
+    conn: RpcConn,
+    iface: String,
+    object: String,
+}
+
+impl Proxy {
+    pub fn new(bus: Bus, iface: &str, object: &str) -> Result<Self, Error> {
+        let conn = RpcConn::connect_to_path(bus_path(&bus)?, Timeout::Infinite)?;
+        Ok(Self {
+            conn,
+            iface: iface.into(),
+            object: object.into(),
+        })
+    }
+
+    pub fn method(&self, member: &str) -> MarshalledMessage {
+        MessageBuilder::new()
+            .call(member)
+            .with_interface(&self.iface)
+            .on(&self.object)
+            .at(&self.iface)
+            .build()
+    }
+
+    pub fn call(
+        &mut self,
+        mut call: MarshalledMessage,
+        timeout: Timeout,
+    ) -> Result<MarshalledMessage, CallError> {
+        let serial = self
+            .conn
+            .send_message(&mut call)?
+            .write_all()
+            .map_err(|(_, e)| e)?;
+        let response = self.conn.wait_response(serial, timeout)?;
+        match response.typ {
+            MessageType::Error => {
+                let name = response.dynheader.error_name.clone().unwrap_or_default();
+                let text = response.body.parser().get::<String>().unwrap_or_default();
+                Err(CallError::Remote(name, text))
+            }
+            _ => Ok(response),
+        }
+    }
+
+    pub fn subscribe(&mut self) -> Result<(), CallError> {
+        let rule = format!("type='signal',interface='{}'", self.iface);
+        self.call(standard_messages::add_match(&rule), Timeout::Infinite)?;
+        Ok(())
+    }
+
+    pub fn next_signal(&mut self, timeout: Timeout) -> Result<MarshalledMessage, Error> {
+        self.conn.wait_signal(timeout)
+    }
+}
@@ -6,7 +6,7 @@ use rustbus::{
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct ProxyTracker {
     proxies: Arc<Mutex<HashSet<String>>>,
 }
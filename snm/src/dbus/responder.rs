@@ -0,0 +1,22 @@
+use rustbus::connection::Error;
+use rustbus::message_builder::MarshalledMessage;
+use rustbus::SendConn;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct Responder {
+    connection: Arc<Mutex<SendConn>>,
+}
+
+impl Responder {
+    pub fn new(connection: Arc<Mutex<SendConn>>) -> Self {
+        Self { connection }
+    }
+
+    pub fn respond(&self, response: &MarshalledMessage) -> Result<u32, Error> {
+        self.connection
+            .lock()
+            .unwrap()
+            .send_message_write_all(response)
+    }
+}
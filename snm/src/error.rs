@@ -0,0 +1,237 @@
+use super::connection::{ConnectionStatus, State};
+use std::{fmt, io};
+
+const DBUS_ERROR_PREFIX: &str = "com.github.okeri.snm.Error.";
+
+#[derive(Debug)]
+pub enum Error {
+    NoInterface,
+    NotPluggedIn(String),
+    UnknownNetwork(String),
+    Spawn(String, io::Error),
+    ToolMissing(String),
+    NoHwAddr(String),
+    DhcpTimeout(String),
+    AuthFail(String),
+    AssocFail(String),
+    Aborted,
+    IllFormedEssid(String),
+    Io(io::Error),
+    NotAllowed(String),
+    SecretsRequired(String),
+    NoDevice(String),
+    ConfigWrite(io::Error),
+    UnknownOperation(u32),
+    InvalidTransition(State, State),
+    // a D-Bus message whose arguments do not fit the method
+    InvalidArgs,
+    // command, exit status and what it said on stderr
+    CommandFailed(String, i32, String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// numeric values are part of the D-Bus interface, append only
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reason {
+    None,
+    NoInterface,
+    NotPluggedIn,
+    UnknownNetwork,
+    ToolMissing,
+    NoHwAddr,
+    DhcpTimeout,
+    AuthFail,
+    AssocFail,
+    Aborted,
+    IllFormedEssid,
+    Io,
+    NotAllowed,
+    SecretsRequired,
+    NoDevice,
+    ConfigWrite,
+    UnknownOperation,
+    InvalidTransition,
+    InvalidArgs,
+    CommandFailed,
+}
+
+const REASONS: [Reason; 20] = [
+    Reason::None,
+    Reason::NoInterface,
+    Reason::NotPluggedIn,
+    Reason::UnknownNetwork,
+    Reason::ToolMissing,
+    Reason::NoHwAddr,
+    Reason::DhcpTimeout,
+    Reason::AuthFail,
+    Reason::AssocFail,
+    Reason::Aborted,
+    Reason::IllFormedEssid,
+    Reason::Io,
+    Reason::NotAllowed,
+    Reason::SecretsRequired,
+    Reason::NoDevice,
+    Reason::ConfigWrite,
+    Reason::UnknownOperation,
+    Reason::InvalidTransition,
+    Reason::InvalidArgs,
+    Reason::CommandFailed,
+];
+
+impl Reason {
+    pub fn from_code(code: u32) -> Option<Reason> {
+        REASONS.get(code as usize).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Reason::None => "None",
+            Reason::NoInterface => "NoInterface",
+            Reason::NotPluggedIn => "NotPluggedIn",
+            Reason::UnknownNetwork => "UnknownNetwork",
+            Reason::ToolMissing => "ToolMissing",
+            Reason::NoHwAddr => "NoHwAddr",
+            Reason::DhcpTimeout => "DhcpTimeout",
+            Reason::AuthFail => "AuthFail",
+            Reason::AssocFail => "AssocFail",
+            Reason::Aborted => "Aborted",
+            Reason::IllFormedEssid => "IllFormedEssid",
+            Reason::Io => "Io",
+            Reason::NotAllowed => "NotAllowed",
+            Reason::SecretsRequired => "SecretsRequired",
+            Reason::NoDevice => "NoDevice",
+            Reason::ConfigWrite => "ConfigWrite",
+            Reason::UnknownOperation => "UnknownOperation",
+            Reason::InvalidTransition => "InvalidTransition",
+            Reason::InvalidArgs => "InvalidArgs",
+            Reason::CommandFailed => "CommandFailed",
+        }
+    }
+}
+
+impl Error {
+    pub fn status(&self) -> ConnectionStatus {
+        match self {
+            Error::Aborted => ConnectionStatus::Aborted,
+            Error::AuthFail(_) | Error::SecretsRequired(_) => ConnectionStatus::AuthFail,
+            _ => ConnectionStatus::ConnectFail,
+        }
+    }
+
+    pub fn reason(&self) -> Reason {
+        match self {
+            Error::NoInterface => Reason::NoInterface,
+            Error::NotPluggedIn(_) => Reason::NotPluggedIn,
+            Error::UnknownNetwork(_) => Reason::UnknownNetwork,
+            Error::Spawn(..) | Error::ToolMissing(_) => Reason::ToolMissing,
+            Error::NoHwAddr(_) => Reason::NoHwAddr,
+            Error::DhcpTimeout(_) => Reason::DhcpTimeout,
+            Error::AuthFail(_) => Reason::AuthFail,
+            Error::AssocFail(_) => Reason::AssocFail,
+            Error::Aborted => Reason::Aborted,
+            Error::IllFormedEssid(_) => Reason::IllFormedEssid,
+            Error::Io(_) => Reason::Io,
+            Error::NotAllowed(_) => Reason::NotAllowed,
+            Error::SecretsRequired(_) => Reason::SecretsRequired,
+            Error::NoDevice(_) => Reason::NoDevice,
+            Error::ConfigWrite(_) => Reason::ConfigWrite,
+            Error::UnknownOperation(_) => Reason::UnknownOperation,
+            Error::InvalidTransition(..) => Reason::InvalidTransition,
+            Error::InvalidArgs => Reason::InvalidArgs,
+            Error::CommandFailed(..) => Reason::CommandFailed,
+        }
+    }
+
+    pub fn dbus_name(&self) -> String {
+        format!("{}{}", DBUS_ERROR_PREFIX, self.reason().name())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoInterface => write!(f, "no suitable network interface"),
+            Error::NotPluggedIn(iface) => write!(f, "{} is not plugged in", iface),
+            Error::UnknownNetwork(essid) => write!(f, "network '{}' is not in range", essid),
+            Error::Spawn(cmd, e) => write!(f, "cannot execute `{}`: {}", cmd, e),
+            Error::ToolMissing(cmd) => write!(f, "command not found: `{}`", cmd),
+            Error::NoHwAddr(iface) => write!(f, "cannot detect hardware address of {}", iface),
+            Error::DhcpTimeout(iface) => write!(f, "no dhcp lease on {}", iface),
+            Error::AuthFail(essid) => write!(f, "authentication with '{}' failed", essid),
+            Error::AssocFail(essid) => write!(f, "association with '{}' failed", essid),
+            Error::Aborted => write!(f, "connection canceled"),
+            Error::IllFormedEssid(essid) => write!(f, "ill-formed essid '{}'", essid),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::NotAllowed(what) => write!(f, "{} is not allowed", what),
+            Error::SecretsRequired(essid) => {
+                write!(
+                    f,
+                    "network '{}' is secured but no password specified",
+                    essid
+                )
+            }
+            Error::NoDevice(iface) => write!(f, "no such device: {}", iface),
+            Error::ConfigWrite(e) => write!(f, "cannot write config: {}", e),
+            Error::UnknownOperation(op) => write!(f, "no pending operation {}", op),
+            Error::InvalidTransition(from, to) => {
+                write!(f, "invalid state transition {:?} -> {:?}", from, to)
+            }
+            Error::InvalidArgs => write!(f, "invalid arguments"),
+            Error::CommandFailed(cmd, status, stderr) => {
+                write!(f, "`{}` exited with {}: {}", cmd, status, stderr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_to_status_and_dbus_names() {
+        assert_eq!(
+            Error::NoInterface.dbus_name(),
+            "com.github.okeri.snm.Error.NoInterface"
+        );
+        assert_eq!(
+            Error::ToolMissing("iw".to_owned()).dbus_name(),
+            "com.github.okeri.snm.Error.ToolMissing"
+        );
+        assert_eq!(
+            Error::SecretsRequired("home".to_owned()).dbus_name(),
+            "com.github.okeri.snm.Error.SecretsRequired"
+        );
+        assert_eq!(
+            Error::CommandFailed("iptables -A".to_owned(), 2, String::new()).dbus_name(),
+            "com.github.okeri.snm.Error.CommandFailed"
+        );
+        assert!(matches!(Error::Aborted.status(), ConnectionStatus::Aborted));
+        assert!(matches!(
+            Error::AuthFail("home".to_owned()).status(),
+            ConnectionStatus::AuthFail
+        ));
+        assert!(matches!(
+            Error::DhcpTimeout("eth0".to_owned()).status(),
+            ConnectionStatus::ConnectFail
+        ));
+    }
+
+    #[test]
+    fn reason_codes_round_trip() {
+        for (code, reason) in REASONS.iter().enumerate() {
+            assert_eq!(*reason as u32, code as u32);
+            assert_eq!(Reason::from_code(code as u32), Some(*reason));
+        }
+        assert_eq!(Reason::from_code(REASONS.len() as u32), None);
+    }
+}
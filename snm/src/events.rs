@@ -0,0 +1,202 @@
+use super::connection::{civil_date, SignalMsg};
+use super::json::{ToJson, Value};
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Mutex};
+use std::{fs, path::Path, thread, time};
+
+const CLIENT_WRITE_TIMEOUT_MS: u64 = 500;
+const SOCKET_MODE: u32 = 0o666;
+
+#[derive(Clone, Default, Deserialize)]
+pub struct EventsSettings {
+    #[serde(default = "EventsSettings::default_stdout")]
+    pub stdout: bool,
+    #[serde(default = "EventsSettings::default_socket")]
+    pub socket: Option<String>,
+}
+
+impl EventsSettings {
+    fn default_stdout() -> bool {
+        false
+    }
+
+    fn default_socket() -> Option<String> {
+        None
+    }
+}
+
+fn event_name(signal: &SignalMsg) -> &'static str {
+    match signal {
+        SignalMsg::NetworkList(_) => "network_list",
+        SignalMsg::ConnectStatusChanged(_) => "connect_status_changed",
+        SignalMsg::StateChanged(_) => "state_changed",
+        SignalMsg::ConnectivityChanged(_) => "connectivity_changed",
+        SignalMsg::DhcpLease(_) => "dhcp_lease",
+        SignalMsg::ConnectFinished(..) => "connect_finished",
+        SignalMsg::Roamed(_) => "roamed",
+        SignalMsg::LinkStats(..) => "link_stats",
+        SignalMsg::Statistics(..) => "statistics",
+        SignalMsg::MeteredChanged(_) => "metered_changed",
+    }
+}
+
+fn retained(signal: &SignalMsg) -> bool {
+    matches!(
+        signal,
+        SignalMsg::NetworkList(_) | SignalMsg::StateChanged(_) | SignalMsg::ConnectivityChanged(_)
+    )
+}
+
+fn timestamp(now: time::SystemTime) -> String {
+    let since_epoch = now
+        .duration_since(time::UNIX_EPOCH)
+        .unwrap_or_else(|_| time::Duration::from_secs(0));
+    let secs = since_epoch.as_secs();
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    let (year, month, day) = civil_date(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+pub fn event(signal: &SignalMsg, now: time::SystemTime) -> Value {
+    let mut fields = vec![
+        ("timestamp", timestamp(now).into()),
+        ("event", event_name(signal).into()),
+    ];
+    if let Value::Object(body) = signal.to_json() {
+        fields.extend(body);
+    }
+    Value::Object(fields)
+}
+
+#[derive(Clone)]
+pub struct EventStream {
+    stdout: bool,
+    clients: Arc<Mutex<Vec<UnixStream>>>,
+    retained: Arc<Mutex<HashMap<&'static str, String>>>,
+}
+
+impl EventStream {
+    pub fn new(settings: &EventsSettings) -> std::io::Result<Self> {
+        let stream = EventStream {
+            stdout: settings.stdout,
+            clients: Arc::new(Mutex::new(vec![])),
+            retained: Arc::new(Mutex::new(HashMap::new())),
+        };
+        if let Some(ref path) = settings.socket {
+            stream.listen(Path::new(path))?;
+        }
+        Ok(stream)
+    }
+
+    fn listen(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(SOCKET_MODE))?;
+
+        let clients = self.clients.clone();
+        let retained = self.retained.clone();
+        thread::spawn(move || {
+            for mut client in listener.incoming().flatten() {
+                let timeout = time::Duration::from_millis(CLIENT_WRITE_TIMEOUT_MS);
+                if client.set_write_timeout(Some(timeout)).is_err() {
+                    continue;
+                }
+                let snapshot = retained
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if snapshot
+                    .iter()
+                    .all(|line| client.write_all(line.as_bytes()).is_ok())
+                {
+                    clients.lock().unwrap().push(client);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub fn publish(&self, signal: &SignalMsg) {
+        let line = format!("{}\n", event(signal, time::SystemTime::now()));
+        if self.stdout {
+            let stdout = std::io::stdout();
+            let mut out = stdout.lock();
+            out.write_all(line.as_bytes()).unwrap_or_default();
+            out.flush().unwrap_or_default();
+        }
+        if retained(signal) {
+            self.retained
+                .lock()
+                .unwrap()
+                .insert(event_name(signal), line.clone());
+        }
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| client.write_all(line.as_bytes()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::{Connectivity, NetworkList};
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn formats_timestamps() {
+        let at = |secs: u64, millis: u64| {
+            timestamp(time::UNIX_EPOCH + time::Duration::from_millis(secs * 1000 + millis))
+        };
+        assert_eq!(at(0, 0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951782400, 5), "2000-02-29T00:00:00.005Z");
+        assert_eq!(at(1792345678, 250), "2026-10-18T17:47:58.250Z");
+    }
+
+    #[test]
+    fn socket_clients_get_snapshot_and_events() {
+        let path = std::env::temp_dir().join(format!("snm-events-{}.sock", std::process::id()));
+        let settings = EventsSettings {
+            stdout: false,
+            socket: Some(path.to_string_lossy().into_owned()),
+        };
+        let stream = EventStream::new(&settings).unwrap();
+        stream.publish(&SignalMsg::ConnectivityChanged(Connectivity::Full));
+
+        let client = UnixStream::connect(&path).unwrap();
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.contains(r#""event":"connectivity_changed","connectivity":"full""#));
+
+        while stream.clients.lock().unwrap().is_empty() {
+            thread::sleep(time::Duration::from_millis(10));
+        }
+        stream.publish(&SignalMsg::NetworkList(NetworkList::new()));
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.contains(r#""event":"network_list","networks":[]"#));
+        fs::remove_file(&path).unwrap();
+    }
+}
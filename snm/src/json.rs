@@ -0,0 +1,470 @@
+use super::connection::{
+    AccessPoint, ConnectionInfo, ConnectionStatus, Connectivity, DataUsage, DhcpEvent,
+    HotspotClient, HotspotSetting, KnownNetwork, LinkStats, MeteredState, NetworkInfo, NetworkList,
+    RoamEvent, Security, SignalMsg, Statistics, StatusChange, WiredAuth, WiredProfile,
+};
+use std::fmt;
+
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(&'static str, Value)>),
+}
+
+pub trait ToJson {
+    fn to_json(&self) -> Value;
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::String(value) => write_string(f, value),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Int(value as i64)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int(value as i64)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Int(value as i64)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, |v| v.into())
+    }
+}
+
+impl ToJson for ConnectionInfo {
+    fn to_json(&self) -> Value {
+        match self {
+            ConnectionInfo::NotConnected => Value::Object(vec![("state", "disconnected".into())]),
+            ConnectionInfo::Ethernet(ip) => Value::Object(vec![
+                ("state", "ethernet".into()),
+                ("ip", ip.as_str().into()),
+            ]),
+            ConnectionInfo::Wifi(essid, quality, enc, ip, band) => Value::Object(vec![
+                ("state", "wifi".into()),
+                ("essid", essid.to_string().into()),
+                ("quality", (*quality).into()),
+                ("encrypted", (*enc).into()),
+                ("ip", ip.as_str().into()),
+                ("band", band.name().into()),
+            ]),
+            ConnectionInfo::ConnectingEth => {
+                Value::Object(vec![("state", "connecting_ethernet".into())])
+            }
+            ConnectionInfo::ConnectingWifi(essid) => Value::Object(vec![
+                ("state", "connecting_wifi".into()),
+                ("essid", essid.to_string().into()),
+            ]),
+        }
+    }
+}
+
+impl ToJson for NetworkInfo {
+    fn to_json(&self) -> Value {
+        match self {
+            NetworkInfo::Ethernet => Value::Object(vec![("type", "ethernet".into())]),
+            NetworkInfo::Wifi(essid, quality, enc) => Value::Object(vec![
+                ("type", "wifi".into()),
+                ("essid", essid.to_string().into()),
+                ("quality", (*quality).into()),
+                ("encrypted", (*enc).into()),
+            ]),
+        }
+    }
+}
+
+impl ToJson for NetworkList {
+    fn to_json(&self) -> Value {
+        Value::Array(self.iter().map(|network| network.to_json()).collect())
+    }
+}
+
+impl ToJson for KnownNetwork {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("auto", self.auto.into()),
+            ("password", self.password.clone().into()),
+            ("threshold", self.threshold.into()),
+            ("bssid", self.bssid.clone().into()),
+            ("metered", self.metered.into()),
+            ("data_cap", self.data_cap.into()),
+            ("mac_policy", self.mac_policy().name().into()),
+            ("mtu", self.mtu.into()),
+            ("dhcp_mtu", self.dhcp_mtu.into()),
+        ])
+    }
+}
+
+impl ToJson for WiredAuth {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("eap", self.eap.as_str().into()),
+            ("identity", self.identity.as_str().into()),
+            ("anonymous_identity", self.anonymous_identity.clone().into()),
+            ("password", self.password.clone().into()),
+            ("phase2", self.phase2.clone().into()),
+            ("ca_cert", self.ca_cert.clone().into()),
+            ("client_cert", self.client_cert.clone().into()),
+            ("private_key", self.private_key.clone().into()),
+            (
+                "private_key_password",
+                self.private_key_password.clone().into(),
+            ),
+        ])
+    }
+}
+
+impl ToJson for WiredProfile {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("auto", self.auto.into()),
+            ("interface", self.interface.clone().into()),
+            ("mac", self.mac.clone().into()),
+            ("gateway_mac", self.gateway_mac.clone().into()),
+            ("method", self.method.as_str().into()),
+            ("address", self.address.clone().into()),
+            ("gateway", self.gateway.clone().into()),
+            (
+                "dns",
+                Value::Array(
+                    self.dns
+                        .iter()
+                        .map(|server| server.as_str().into())
+                        .collect(),
+                ),
+            ),
+            ("mtu", self.mtu.into()),
+            ("dhcp_mtu", self.dhcp_mtu.into()),
+            ("speed", self.speed.into()),
+            ("duplex", self.duplex.clone().into()),
+            ("autoneg", self.autoneg.into()),
+            ("wake_on_lan", self.wake_on_lan.into()),
+        ])
+    }
+}
+
+impl ToJson for AccessPoint {
+    fn to_json(&self) -> Value {
+        let security = match self.security {
+            Security::Open => "open",
+            Security::Wep => "wep",
+            Security::Wpa => "wpa",
+            Security::Wpa2 => "wpa2",
+            Security::Wpa3 => "wpa3",
+        };
+        Value::Object(vec![
+            ("bssid", self.bssid.as_str().into()),
+            ("essid", self.essid.to_string().into()),
+            ("frequency", self.frequency.into()),
+            ("band", self.band().name().into()),
+            ("channel", self.channel().into()),
+            ("signal", self.signal.into()),
+            ("quality", self.quality().into()),
+            ("last_seen", self.last_seen.into()),
+            ("security", security.into()),
+            ("capabilities", self.capabilities.as_str().into()),
+        ])
+    }
+}
+
+impl ToJson for Vec<AccessPoint> {
+    fn to_json(&self) -> Value {
+        Value::Array(self.iter().map(|ap| ap.to_json()).collect())
+    }
+}
+
+impl ToJson for HotspotSetting {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("essid", self.essid.to_string().into()),
+            ("band", self.band.name().into()),
+            ("frequency", self.frequency().into()),
+            ("protected", self.password.is_some().into()),
+        ])
+    }
+}
+
+impl ToJson for HotspotClient {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("mac", self.mac.as_str().into()),
+            ("ip", self.ip.as_str().into()),
+            ("hostname", self.hostname.as_str().into()),
+        ])
+    }
+}
+
+impl ToJson for Vec<HotspotClient> {
+    fn to_json(&self) -> Value {
+        Value::Array(self.iter().map(|client| client.to_json()).collect())
+    }
+}
+
+impl ToJson for ConnectionStatus {
+    fn to_json(&self) -> Value {
+        match self {
+            ConnectionStatus::Initializing => "initializing",
+            ConnectionStatus::Connecting => "connecting",
+            ConnectionStatus::Authenticating => "authenticating",
+            ConnectionStatus::GettingIP => "getting_ip",
+            ConnectionStatus::AuthFail => "auth_fail",
+            ConnectionStatus::Aborted => "aborted",
+            ConnectionStatus::ConnectFail => "connect_fail",
+        }
+        .into()
+    }
+}
+
+impl ToJson for StatusChange {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("status", self.status.to_json()),
+            ("reason", self.reason.name().into()),
+            ("ssid", self.essid.to_string().into()),
+            ("interface", self.iface.as_str().into()),
+            ("op", self.op.into()),
+        ])
+    }
+}
+
+impl ToJson for RoamEvent {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("interface", self.iface.as_str().into()),
+            ("ssid", self.essid.to_string().into()),
+            ("from", self.from.as_str().into()),
+            ("to", self.to.as_str().into()),
+        ])
+    }
+}
+
+impl ToJson for LinkStats {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("signal", self.signal.into()),
+            ("quality", self.quality().into()),
+            ("tx_bitrate", self.tx_bitrate.into()),
+            ("rx_bitrate", self.rx_bitrate.into()),
+            ("connected_time", self.connected_time.into()),
+        ])
+    }
+}
+
+impl ToJson for Statistics {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("rx_bytes", self.rx_bytes.into()),
+            ("tx_bytes", self.tx_bytes.into()),
+            ("rx_packets", self.rx_packets.into()),
+            ("tx_packets", self.tx_packets.into()),
+            ("rx_errors", self.rx_errors.into()),
+            ("tx_errors", self.tx_errors.into()),
+            ("rx_dropped", self.rx_dropped.into()),
+            ("tx_dropped", self.tx_dropped.into()),
+            ("rx_rate", self.rx_rate.into()),
+            ("tx_rate", self.tx_rate.into()),
+        ])
+    }
+}
+
+impl ToJson for DataUsage {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("rx_bytes", self.rx.into()),
+            ("tx_bytes", self.tx.into()),
+            ("month_rx_bytes", self.month_rx.into()),
+            ("month_tx_bytes", self.month_tx.into()),
+        ])
+    }
+}
+
+impl ToJson for MeteredState {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("metered", self.metered.into()),
+            ("over_cap", self.over_cap.into()),
+        ])
+    }
+}
+
+impl ToJson for Connectivity {
+    fn to_json(&self) -> Value {
+        match self {
+            Connectivity::Unknown => "unknown",
+            Connectivity::None => "none",
+            Connectivity::Portal => "portal",
+            Connectivity::Limited => "limited",
+            Connectivity::Full => "full",
+        }
+        .into()
+    }
+}
+
+impl ToJson for DhcpEvent {
+    fn to_json(&self) -> Value {
+        match self {
+            DhcpEvent::Bound {
+                iface,
+                address,
+                router,
+                dns,
+                metered,
+                mtu,
+            } => Value::Object(vec![
+                ("action", "bound".into()),
+                ("interface", iface.as_str().into()),
+                ("address", address.as_str().into()),
+                ("router", router.clone().into()),
+                (
+                    "dns",
+                    Value::Array(dns.iter().map(|s| s.as_str().into()).collect()),
+                ),
+                ("metered", (*metered).into()),
+                ("mtu", (*mtu).into()),
+            ]),
+            DhcpEvent::Lost { iface } => Value::Object(vec![
+                ("action", "lost".into()),
+                ("interface", iface.as_str().into()),
+            ]),
+        }
+    }
+}
+
+impl ToJson for SignalMsg {
+    fn to_json(&self) -> Value {
+        match self {
+            SignalMsg::NetworkList(networks) => {
+                Value::Object(vec![("networks", networks.to_json())])
+            }
+            SignalMsg::ConnectStatusChanged(change) => change.to_json(),
+            SignalMsg::StateChanged(info) => Value::Object(vec![("state", info.to_json())]),
+            SignalMsg::ConnectivityChanged(connectivity) => {
+                Value::Object(vec![("connectivity", connectivity.to_json())])
+            }
+            SignalMsg::DhcpLease(event) => event.to_json(),
+            SignalMsg::ConnectFinished(op, reason) => {
+                Value::Object(vec![("op", (*op).into()), ("result", reason.name().into())])
+            }
+            SignalMsg::Roamed(event) => event.to_json(),
+            SignalMsg::LinkStats(iface, stats) => Value::Object(vec![
+                ("interface", iface.as_str().into()),
+                ("stats", stats.to_json()),
+            ]),
+            SignalMsg::Statistics(iface, stats) => Value::Object(vec![
+                ("interface", iface.as_str().into()),
+                ("statistics", stats.to_json()),
+            ]),
+            SignalMsg::MeteredChanged(state) => state.to_json(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Band;
+
+    #[test]
+    fn escapes_strings() {
+        let value = Value::from("a\"b\\c\nd\u{1}");
+        assert_eq!(value.to_string(), r#""a\"b\\c\nd\u0001""#);
+    }
+
+    #[test]
+    fn nested_values() {
+        let value = Value::Object(vec![
+            ("list", Value::Array(vec![1u32.into(), Value::Null])),
+            ("flag", true.into()),
+            ("missing", Option::<i32>::None.into()),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{"list":[1,null],"flag":true,"missing":null}"#
+        );
+    }
+
+    #[test]
+    fn connection_info() {
+        let info = ConnectionInfo::Wifi("home".into(), 70, true, "10.0.0.2".to_owned(), Band::Ghz5);
+        assert_eq!(
+            info.to_json().to_string(),
+            r#"{"state":"wifi","essid":"home","quality":70,"encrypted":true,"ip":"10.0.0.2","band":"5"}"#
+        );
+    }
+}
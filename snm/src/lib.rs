@@ -0,0 +1,18 @@
+#[macro_use]
+extern crate lazy_static;
+
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+extern crate log;
+
+pub mod config;
+pub mod connection;
+pub mod convert;
+pub mod dbus;
+pub mod error;
+pub mod events;
+pub mod json;
+pub mod logger;
+pub mod marshal;
@@ -0,0 +1,284 @@
+use log::kv::{self, Key, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::net::UnixDatagram;
+use std::{env, fmt::Write as _, str::FromStr};
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_IDENTIFIER: &str = "snm";
+
+#[derive(Clone, Deserialize)]
+pub struct LogSettings {
+    #[serde(default = "LogSettings::default_level")]
+    pub level: String,
+    #[serde(default = "LogSettings::default_output")]
+    pub output: String,
+    #[serde(default = "LogSettings::default_targets")]
+    pub targets: HashMap<String, String>,
+}
+
+impl LogSettings {
+    fn default_level() -> String {
+        "info".to_owned()
+    }
+
+    fn default_output() -> String {
+        "auto".to_owned()
+    }
+
+    fn default_targets() -> HashMap<String, String> {
+        HashMap::new()
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            level: Self::default_level(),
+            output: Self::default_output(),
+            targets: Self::default_targets(),
+        }
+    }
+}
+
+enum Output {
+    Stderr,
+    Journal(UnixDatagram),
+}
+
+pub struct Logger {
+    level: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+    output: Output,
+}
+
+fn raise(level: LevelFilter, steps: u8) -> LevelFilter {
+    let all = LevelFilter::iter().collect::<Vec<_>>();
+    let index = all.iter().position(|l| *l == level).unwrap_or(0) + steps as usize;
+    all[std::cmp::min(index, all.len() - 1)]
+}
+
+fn parse_level(value: &str) -> LevelFilter {
+    LevelFilter::from_str(value).unwrap_or_else(|_| {
+        eprintln!("unknown log level '{}', using info", value);
+        LevelFilter::Info
+    })
+}
+
+fn connect_journal() -> Option<UnixDatagram> {
+    let socket = UnixDatagram::unbound().ok()?;
+    socket.connect(JOURNAL_SOCKET).ok()?;
+    Some(socket)
+}
+
+fn priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+fn journal_field_name(key: &str) -> String {
+    key.trim_start_matches('_')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' => c.to_ascii_uppercase(),
+            'A'..='Z' | '0'..='9' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn append_journal_field(buf: &mut Vec<u8>, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+struct Fields(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+impl Logger {
+    pub fn new(settings: &LogSettings, verbosity: u8) -> Self {
+        let mut targets = settings
+            .targets
+            .iter()
+            .map(|(target, level)| (target.clone(), raise(parse_level(level), verbosity)))
+            .collect::<Vec<_>>();
+        // most specific target wins
+        targets.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+
+        let output = match settings.output.as_str() {
+            "journal" => connect_journal().map_or(Output::Stderr, Output::Journal),
+            "stderr" => Output::Stderr,
+            _ => {
+                if env::var_os("JOURNAL_STREAM").is_some() {
+                    connect_journal().map_or(Output::Stderr, Output::Journal)
+                } else {
+                    Output::Stderr
+                }
+            }
+        };
+
+        Logger {
+            level: raise(parse_level(&settings.level), verbosity),
+            targets,
+            output,
+        }
+    }
+
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        let max = self
+            .targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, std::cmp::max);
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max);
+        Ok(())
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target == prefix
+                    || (target.starts_with(prefix.as_str())
+                        && target[prefix.len()..].starts_with("::"))
+            })
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    fn journal_entry(record: &Record, fields: &Fields) -> Vec<u8> {
+        let mut buf = vec![];
+        append_journal_field(&mut buf, "MESSAGE", &record.args().to_string());
+        append_journal_field(&mut buf, "PRIORITY", &priority(record.level()).to_string());
+        append_journal_field(&mut buf, "SYSLOG_IDENTIFIER", SYSLOG_IDENTIFIER);
+        append_journal_field(&mut buf, "TARGET", record.target());
+        if let Some(file) = record.file() {
+            append_journal_field(&mut buf, "CODE_FILE", file);
+        }
+        if let Some(line) = record.line() {
+            append_journal_field(&mut buf, "CODE_LINE", &line.to_string());
+        }
+        for (key, value) in fields.0.iter() {
+            append_journal_field(&mut buf, &journal_field_name(key), value);
+        }
+        buf
+    }
+
+    fn line(record: &Record, fields: &Fields) -> String {
+        let mut line = format!(
+            "{:<5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        for (key, value) in fields.0.iter() {
+            write!(line, " {}={:?}", key, value).unwrap_or_default();
+        }
+        line.push('\n');
+        line
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut fields = Fields(vec![]);
+        record.key_values().visit(&mut fields).unwrap_or_default();
+
+        if let Output::Journal(ref socket) = self.output {
+            if socket.send(&Self::journal_entry(record, &fields)).is_ok() {
+                return;
+            }
+        }
+        let stderr = std::io::stderr();
+        let mut out = stderr.lock();
+        out.write_all(Self::line(record, &fields).as_bytes())
+            .unwrap_or_default();
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logger(level: &str, targets: &[(&str, &str)], verbosity: u8) -> Logger {
+        let settings = LogSettings {
+            level: level.to_owned(),
+            output: "stderr".to_owned(),
+            targets: targets
+                .iter()
+                .map(|(t, l)| (t.to_string(), l.to_string()))
+                .collect(),
+        };
+        Logger::new(&settings, verbosity)
+    }
+
+    #[test]
+    fn target_levels() {
+        let logger = logger(
+            "warn",
+            &[
+                ("snm::connection", "info"),
+                ("snm::connection::support", "trace"),
+            ],
+            0,
+        );
+        assert_eq!(logger.level_for("snm"), LevelFilter::Warn);
+        assert_eq!(logger.level_for("snm::connection"), LevelFilter::Info);
+        assert_eq!(
+            logger.level_for("snm::connection::interfaces"),
+            LevelFilter::Info
+        );
+        assert_eq!(logger.level_for("snm::connectionx"), LevelFilter::Warn);
+        assert_eq!(
+            logger.level_for("snm::connection::support"),
+            LevelFilter::Trace
+        );
+    }
+
+    #[test]
+    fn verbosity_raises_levels() {
+        assert_eq!(logger("info", &[], 1).level, LevelFilter::Debug);
+        assert_eq!(logger("info", &[], 5).level, LevelFilter::Trace);
+        assert_eq!(logger("bogus", &[], 0).level, LevelFilter::Info);
+    }
+
+    #[test]
+    fn journal_fields() {
+        let mut buf = vec![];
+        append_journal_field(&mut buf, &journal_field_name("interface"), "wlan0");
+        append_journal_field(&mut buf, "STDERR", "a\nb");
+        let mut expected = b"INTERFACE=wlan0\nSTDERR\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\n");
+        assert_eq!(buf, expected);
+        assert_eq!(journal_field_name("_exit-status"), "EXIT_STATUS");
+    }
+}
@@ -1,22 +1,14 @@
-#[macro_use]
-extern crate lazy_static;
-
-#[macro_use]
-extern crate serde_derive;
-
-mod config;
-mod connection;
-mod convert;
-mod dbus;
-mod marshal;
-
-use connection::{
-    Connection, ConnectionInfo, ConnectionSetting, CouldConnect, KnownNetwork, KnownNetworks,
-    SignalMsg,
+use snm::connection::{
+    AccessPoint, Band, BandPolicy, Connection, ConnectionInfo, ConnectionSetting,
+    ConnectivityChecker, CouldConnect, Hardware, HotspotSetting, KnownNetwork, KnownNetworks,
+    MacPolicy, OnlineCriteria, OperationId, Roamer, SignalMsg, Simulation, Ssid, WiredAuth,
+    WiredLink, WiredProfile,
 };
 
+use snm::{config, dbus, error::Error, events, logger};
+
 use rustbus::{
-    connection::Error,
+    connection,
     message_builder::{DynamicHeader, MarshalledMessage},
     standard_messages, MessageType,
 };
@@ -25,83 +17,259 @@ use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc, Arc, Mutex,
 };
-use std::{thread, time};
+use std::{env, process, thread, time};
 
 const NETWORK_CHECK_INTERVAL: u64 = 2;
 const NETWORK_SCAN_INTERVAL: u64 = 14;
+const LINK_CHECK_INTERVAL: u64 = 4;
+const STATISTICS_INTERVAL: u64 = 10;
 
 #[derive(Clone)]
 struct ServiceData<SignalHandler: FnMut(SignalMsg)> {
     connection: Connection<SignalHandler>,
     known_networks: Arc<Mutex<KnownNetworks>>,
     auto: Arc<AtomicBool>,
+    scan_requested: Arc<AtomicBool>,
     proxy_tracker: dbus::ProxyTracker,
-    connect_sender: mpsc::Sender<ConnectionSetting>,
+    responder: dbus::Responder,
+    connect_sender: mpsc::Sender<(OperationId, ConnectionSetting)>,
 }
 
-impl<SignalHandler: FnMut(SignalMsg)> ServiceData<SignalHandler> {
-    fn new(signal_handler: SignalHandler, connect_sender: mpsc::Sender<ConnectionSetting>) -> Self {
+impl<SignalHandler: FnMut(SignalMsg) + Clone + Send + 'static> ServiceData<SignalHandler> {
+    fn new(
+        signal_handler: SignalHandler,
+        responder: dbus::Responder,
+        connect_sender: mpsc::Sender<(OperationId, ConnectionSetting)>,
+        settings: config::Settings,
+        hardware: Hardware,
+    ) -> Self {
+        let connection = Connection::with_hardware(
+            signal_handler,
+            ConnectivityChecker::new(settings.connectivity),
+            Roamer::new(settings.roaming),
+            hardware,
+        );
+        connection.set_usage_totals(config::read_usage());
+        connection.set_wired_credentials(config::read_wired());
+        connection.set_wired_profiles(config::read_wired_profiles());
+        match config::read_mac_secret() {
+            Ok(secret) => connection.set_mac_secret(secret),
+            Err(e) => log::warn!(
+                "cannot read mac secret, stable addresses last until restart: {}",
+                e
+            ),
+        }
         Self {
-            connection: Connection::new(signal_handler),
+            connection,
             known_networks: Arc::new(Mutex::new(config::read_networks())),
             auto: Arc::new(AtomicBool::new(true)),
+            scan_requested: Arc::new(AtomicBool::new(false)),
             proxy_tracker: dbus::ProxyTracker::new(),
+            responder,
             connect_sender,
         }
     }
+
+    // changes one known network and saves them all
+    fn update_network<F: FnOnce(&mut KnownNetwork)>(
+        &self,
+        essid: String,
+        update: F,
+    ) -> Result<(), Error> {
+        let mut known = self.known_networks.lock().unwrap();
+        match known.get_mut(&essid) {
+            Some(network) => update(network),
+            None => return Err(Error::UnknownNetwork(essid)),
+        }
+        config::write_networks(&known).map_err(Error::ConfigWrite)
+    }
+}
+
+struct Options {
+    verbosity: u8,
+    simulate: Option<String>,
 }
 
-fn main() -> Result<(), Error> {
-    let (connect_sender, connect_recv) = mpsc::channel::<ConnectionSetting>();
+fn usage() -> ! {
+    eprintln!("Usage: snm [-v|--verbose]... [--simulate SCENARIO]");
+    process::exit(64);
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        verbosity: 0,
+        simulate: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-v" | "--verbose" => options.verbosity += 1,
+            "--simulate" => options.simulate = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+    options
+}
+
+fn main() -> Result<(), connection::Error> {
+    let options = parse_args();
+    let (settings, malformed) = config::read_settings();
+    logger::Logger::new(&settings.log, options.verbosity)
+        .init()
+        .expect("cannot initialize logging");
+    if malformed {
+        log::warn!(file = "settings"; "cannot parse settings, using defaults");
+    }
+    let hardware = match options.simulate {
+        Some(ref scenario) => {
+            let simulation = Simulation::load(scenario).unwrap_or_else(|e| {
+                eprintln!("cannot load scenario {}: {}", scenario, e);
+                process::exit(66);
+            });
+            log::info!("simulating hardware from {}", scenario);
+            simulation.start();
+            simulation.hardware()
+        }
+        None => Hardware::system(),
+    };
+    let (connect_sender, connect_recv) = mpsc::channel::<(OperationId, ConnectionSetting)>();
     let mut adapter = dbus::Adapter::new(dbus::Bus::System, "com.github.okeri.snm")?;
     let mut emitter = adapter.new_emitter("/");
+    let event_stream = events::EventStream::new(&settings.events)?;
     let signal_handler = move |signal: SignalMsg| {
         signal.log();
+        event_stream.publish(&signal);
         match signal {
             SignalMsg::StateChanged(state) => {
-                emitter.emit("state_changed", &state).unwrap_or_default();
+                emitter
+                    .emit_with("state_changed", |body| {
+                        body.push_param3(&state, state.essid(), state.band() as u32)
+                    })
+                    .unwrap_or_default();
+            }
+            SignalMsg::ConnectStatusChanged(change) => {
+                emitter
+                    .emit_with("connect_status_changed", |body| {
+                        body.push_param4(
+                            change.status as u32,
+                            change.reason as u32,
+                            change.essid.to_string(),
+                            change.iface.as_str(),
+                        )?;
+                        body.push_param2(change.op, &change.essid)
+                    })
+                    .unwrap_or_default();
             }
-            SignalMsg::ConnectStatusChanged(status) => {
+            SignalMsg::ConnectFinished(op, reason) => {
                 emitter
-                    .emit("connect_status_changed", status as u32)
+                    .emit_with("connect_finished", |body| {
+                        body.push_param2(op, reason as u32)
+                    })
                     .unwrap_or_default();
             }
             SignalMsg::NetworkList(networks) => {
-                emitter.emit("network_list", &networks).unwrap_or_default();
+                emitter
+                    .emit_with("network_list", |body| {
+                        body.push_param2(&networks, networks.ssids())
+                    })
+                    .unwrap_or_default();
             }
+            SignalMsg::ConnectivityChanged(connectivity) => {
+                emitter
+                    .emit("connectivity_changed", connectivity as u32)
+                    .unwrap_or_default();
+            }
+            SignalMsg::Roamed(event) => {
+                emitter
+                    .emit_with("roamed", |body| {
+                        body.push_param4(
+                            event.iface.as_str(),
+                            event.from.as_str(),
+                            event.to.as_str(),
+                            event.essid.to_string(),
+                        )?;
+                        body.push_param(&event.essid)
+                    })
+                    .unwrap_or_default();
+            }
+            SignalMsg::LinkStats(iface, stats) => {
+                emitter
+                    .emit_with("link_stats", |body| {
+                        body.push_param2(iface.as_str(), &stats)
+                    })
+                    .unwrap_or_default();
+            }
+            SignalMsg::Statistics(iface, stats) => {
+                emitter
+                    .emit_with("statistics", |body| {
+                        body.push_param2(iface.as_str(), &stats)
+                    })
+                    .unwrap_or_default();
+            }
+            SignalMsg::MeteredChanged(state) => {
+                emitter
+                    .emit_with("metered_changed", |body| {
+                        body.push_param2(state.metered, state.over_cap)
+                    })
+                    .unwrap_or_default();
+            }
+            SignalMsg::DhcpLease(_) => {}
         }
     };
-    let mut service_data = ServiceData::new(signal_handler, connect_sender);
+    let mut service_data = ServiceData::new(
+        signal_handler,
+        adapter.new_responder(),
+        connect_sender,
+        settings,
+        hardware,
+    );
 
     let start_monitor = || {
         let mut service = service_data.clone();
         let mut scan_c = service.connection.clone();
         thread::spawn(move || {
             let scan_iter = NETWORK_SCAN_INTERVAL / NETWORK_CHECK_INTERVAL;
+            let connectivity_iter = service
+                .connection
+                .connectivity_check_interval()
+                .map(|interval| std::cmp::max(interval / NETWORK_CHECK_INTERVAL, 1));
             let mut iter = 0;
+            let roaming_iter = std::cmp::max(
+                service.connection.roaming_check_interval() / NETWORK_CHECK_INTERVAL,
+                1,
+            );
+            let mut connectivity_counter = 0;
+            let mut roaming_counter = 0;
+            let link_iter = LINK_CHECK_INTERVAL / NETWORK_CHECK_INTERVAL;
+            let mut link_counter = 0;
+            let statistics_iter = STATISTICS_INTERVAL / NETWORK_CHECK_INTERVAL;
+            let mut statistics_counter = 0;
             let mut doscan = || {
                 scan_c.scan();
                 0
             };
 
-            let last_message = || {
+            let last_message = |connection: &mut Connection<_>| {
                 let mut msg = Err(());
                 while let Ok(r) = connect_recv.try_recv() {
+                    if let Ok((superseded, _)) = msg {
+                        connection.abandon(superseded);
+                    }
                     msg = Ok(r);
                 }
-                return msg;
+                msg
             };
             service.connection.acquire();
             match service.connection.current_state() {
-                ConnectionInfo::NotConnected | ConnectionInfo::Wifi(_, _, _, _) => {
+                ConnectionInfo::NotConnected | ConnectionInfo::Wifi(..) => {
                     service.connection.scan();
                 }
                 _ => {}
             }
 
             loop {
-                if let Ok(setting) = last_message() {
-                    if service.connection.connect(setting) {
+                if let Ok((op, setting)) = last_message(&mut service.connection) {
+                    if service.connection.connect(op, setting).is_ok() {
                         service.auto.store(true, Ordering::SeqCst);
                         iter = 0;
                     }
@@ -112,7 +280,8 @@ fn main() -> Result<(), Error> {
                             .auto_connect_possible(&service.known_networks.lock().unwrap());
                         match result {
                             CouldConnect::Connect(setting) => {
-                                if service.connection.connect(setting) {
+                                let op = service.connection.start_operation();
+                                if service.connection.connect(op, setting).is_ok() {
                                     service.auto.store(true, Ordering::SeqCst);
                                 } else {
                                     service.connection.disconnect();
@@ -122,19 +291,48 @@ fn main() -> Result<(), Error> {
                                 service.connection.disconnect();
                                 iter = doscan();
                             }
-                            CouldConnect::Rescan => {
-                                if iter >= scan_iter {
-                                    iter = doscan();
-                                }
+                            CouldConnect::Rescan if iter >= scan_iter => {
+                                iter = doscan();
                             }
                             _ => {}
                         }
                     }
-                    if service.proxy_tracker.active() > 0 && iter >= scan_iter {
+                    if service.scan_requested.swap(false, Ordering::SeqCst)
+                        || (service.proxy_tracker.active() > 0 && iter >= scan_iter)
+                    {
                         iter = doscan();
                     } else {
                         iter += 1;
                     }
+                    if let Some(check_iter) = connectivity_iter {
+                        connectivity_counter += 1;
+                        if connectivity_counter >= check_iter {
+                            service.connection.check_connectivity();
+                            connectivity_counter = 0;
+                        }
+                    }
+                    link_counter += 1;
+                    if link_counter >= link_iter {
+                        service.connection.check_link();
+                        link_counter = 0;
+                    }
+                    statistics_counter += 1;
+                    if statistics_counter >= statistics_iter {
+                        service.connection.check_statistics();
+                        if let Some(usage) = service.connection.usage_changes() {
+                            config::write_usage(&usage)
+                                .unwrap_or_else(|e| log::warn!("cannot save data usage: {}", e));
+                        }
+                        statistics_counter = 0;
+                    }
+                    service
+                        .connection
+                        .check_metered(&service.known_networks.lock().unwrap());
+                    roaming_counter += 1;
+                    if roaming_counter >= roaming_iter {
+                        service.connection.check_roaming();
+                        roaming_counter = 0;
+                    }
                     thread::sleep(time::Duration::from_secs(NETWORK_CHECK_INTERVAL));
                 }
             }
@@ -146,19 +344,39 @@ fn main() -> Result<(), Error> {
     adapter.run(&mut service_data, dbus_handler)
 }
 
-fn make_failed(call: &DynamicHeader, text: &str) -> Option<MarshalledMessage> {
-    let reply = call.make_error_response(
-        "org.freedesktop.DBus.Error.Failed".to_owned(),
-        Some(text.to_owned()),
-    );
+// goes back to the wifi network the hotspot took over, 0 if there is none to go back to
+fn rejoin<SignalHandler: FnMut(SignalMsg) + Clone + Send + 'static>(
+    connection: &Connection<SignalHandler>,
+    known_networks: &Mutex<KnownNetworks>,
+    connect_sender: &mpsc::Sender<(OperationId, ConnectionSetting)>,
+    essid: Option<Ssid>,
+) -> OperationId {
+    let setting = essid.and_then(|essid| {
+        let known = known_networks.lock().unwrap();
+        known
+            .get(&essid.key())
+            .map(|known| known.to_setting(&essid))
+    });
+    match setting {
+        Some(setting) => {
+            let op = connection.start_operation();
+            connect_sender.send((op, setting)).unwrap();
+            op
+        }
+        None => 0,
+    }
+}
+
+fn make_error(call: &DynamicHeader, error: &Error) -> Option<MarshalledMessage> {
+    let reply = call.make_error_response(error.dbus_name(), Some(error.to_string()));
     Some(reply)
 }
 
-fn dbus_handler<SignalHandler: FnMut(SignalMsg)>(
+fn dbus_handler<SignalHandler: FnMut(SignalMsg) + Clone + Send + 'static>(
     service: &mut ServiceData<SignalHandler>,
     msg: MarshalledMessage,
 ) -> Option<MarshalledMessage> {
-    use convert::convert;
+    use snm::convert::convert;
     match msg.typ {
         MessageType::Call => {
             let mut reply = msg.dynheader.make_response();
@@ -169,74 +387,249 @@ fn dbus_handler<SignalHandler: FnMut(SignalMsg)>(
                     }
                     "connect" => {
                         if !service.connection.allow_reconnect() {
-                            return make_failed(&msg.dynheader, "Reconnect is not alowed");
-                        }
-                        if service.connection.current_state().connecting() {
-                            service.connection.disconnect();
+                            return make_error(
+                                &msg.dynheader,
+                                &Error::NotAllowed("reconnect from wired connection".to_owned()),
+                            );
                         }
                         let fallback = msg.dynheader.clone();
                         if let Ok(got_sets) = convert::<ConnectionSetting>(msg) {
-                            let settings =
-                                if let ConnectionSetting::Wifi { ref essid, .. } = got_sets {
-                                    if let Some(known) =
-                                        service.known_networks.lock().unwrap().get(essid)
-                                    {
-                                        known.to_setting(essid)
-                                    } else {
-                                        return make_failed(
-                                            &fallback,
-                                            "Connection is secured but no password specified",
-                                        );
+                            let settings = {
+                                let known = service.known_networks.lock().unwrap();
+                                match got_sets {
+                                    ConnectionSetting::Wifi { ref essid, .. } => {
+                                        if let Some(known) = known.get(&essid.key()) {
+                                            known.to_setting(essid)
+                                        } else {
+                                            return make_error(
+                                                &fallback,
+                                                &Error::SecretsRequired(essid.key()),
+                                            );
+                                        }
                                     }
-                                } else {
-                                    got_sets
-                                };
-                            service.connect_sender.send(settings).unwrap();
+                                    ConnectionSetting::OpenWifi { ref essid, .. } => {
+                                        match known.get(&essid.key()) {
+                                            Some(known) if known.password.is_none() => {
+                                                known.to_setting(essid)
+                                            }
+                                            _ => got_sets,
+                                        }
+                                    }
+                                    _ => got_sets,
+                                }
+                            };
+                            if let Err(e) = service.connection.check_setting(&settings) {
+                                return make_error(&fallback, &e);
+                            }
+                            service.connection.cancel_active();
+                            let op = service.connection.start_operation();
+                            service.connect_sender.send((op, settings)).unwrap();
+                            reply.body.push_param(op).unwrap();
                         } else {
                             return Some(standard_messages::invalid_args(&fallback, Some("(usb)")));
                         }
                     }
+                    "cancel" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(op) = convert::<u32>(msg) {
+                            if let Err(e) = service.connection.cancel(op) {
+                                return make_error(&fallback, &e);
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("u")));
+                        }
+                    }
+                    "scan" => {
+                        service.scan_requested.store(true, Ordering::SeqCst);
+                    }
                     "disconnect" => {
                         service.connection.disconnect();
                         service.auto.store(false, Ordering::SeqCst);
                     }
                     "get_state" => {
+                        let state = service.connection.current_state();
                         reply
                             .body
-                            .push_param(&service.connection.current_state())
+                            .push_param3(&state, state.essid(), state.band() as u32)
                             .unwrap();
+                        let mac = service.connection.active_mac();
+                        reply.body.push_param(mac.unwrap_or_default()).unwrap();
                     }
-                    "get_networks" => {
+                    "get_link_stats" => match service.connection.link_stats() {
+                        Ok((iface, stats)) => {
+                            reply.body.push_param2(iface.as_str(), &stats).unwrap();
+                        }
+                        Err(e) => return make_error(&msg.dynheader, &e),
+                    },
+                    "get_statistics" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(iface) = convert::<String>(msg) {
+                            match service.connection.statistics(&iface) {
+                                Ok((iface, stats)) => {
+                                    reply.body.push_param2(iface.as_str(), &stats).unwrap();
+                                }
+                                Err(e) => return make_error(&fallback, &e),
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("s")));
+                        }
+                    }
+                    "get_usage" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(essid) = convert::<String>(msg) {
+                            let usage = service.connection.usage(&Ssid::from_key(&essid));
+                            reply
+                                .body
+                                .push_param4(usage.rx, usage.tx, usage.month_rx, usage.month_tx)
+                                .unwrap();
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("s")));
+                        }
+                    }
+                    "reset_usage" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(essid) = convert::<String>(msg) {
+                            service.connection.reset_usage(&Ssid::from_key(&essid));
+                            if let Some(usage) = service.connection.usage_changes() {
+                                if let Err(e) = config::write_usage(&usage) {
+                                    return make_error(&fallback, &Error::ConfigWrite(e));
+                                }
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("s")));
+                        }
+                    }
+                    "get_metered" => {
+                        let state = service.connection.metered();
                         reply
                             .body
-                            .push_param(&service.connection.get_networks())
+                            .push_param2(state.metered, state.over_cap)
                             .unwrap();
                     }
+                    "set_metered" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((essid, metered, data_cap)) =
+                            convert::<(String, Option<bool>, Option<u64>)>(msg)
+                        {
+                            if let Err(e) = service.update_network(essid, |network| {
+                                network.metered = metered;
+                                network.data_cap = data_cap;
+                            }) {
+                                return make_error(&fallback, &e);
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("sut")));
+                        }
+                    }
+                    "set_mac_policy" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((essid, policy)) = convert::<(String, Option<MacPolicy>)>(msg) {
+                            if let Err(e) = service.update_network(essid, |network| {
+                                network.mac_policy = policy.map(|policy| policy.name().to_owned())
+                            }) {
+                                return make_error(&fallback, &e);
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("ss")));
+                        }
+                    }
+                    "set_mtu" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((essid, mtu, dhcp_mtu)) =
+                            convert::<(String, Option<u32>, bool)>(msg)
+                        {
+                            if let Err(e) = service.update_network(essid, |network| {
+                                network.mtu = mtu;
+                                network.dhcp_mtu = dhcp_mtu;
+                            }) {
+                                return make_error(&fallback, &e);
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("sub")));
+                        }
+                    }
+                    "get_connectivity" => {
+                        reply
+                            .body
+                            .push_param(service.connection.connectivity() as u32)
+                            .unwrap();
+                    }
+                    "wait_online" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((timeout, criteria)) = convert::<(u32, OnlineCriteria)>(msg) {
+                            let watcher = service.connection.online_watcher();
+                            let responder = service.responder.clone();
+                            thread::spawn(move || {
+                                let online = watcher
+                                    .wait(&criteria, time::Duration::from_secs(timeout as u64));
+                                let mut reply = fallback.make_response();
+                                reply.body.push_param(online).unwrap();
+                                responder.respond(&reply).unwrap_or_default();
+                            });
+                            return None;
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("uus")));
+                        }
+                    }
+                    "get_networks" => {
+                        let networks = service.connection.get_networks();
+                        reply.body.push_param2(&networks, networks.ssids()).unwrap();
+                    }
                     "get_props" => {
                         let fallback = msg.dynheader.clone();
                         if let Ok(ref essid) = convert::<String>(msg) {
-                            if let Some(network) = service.known_networks.lock().unwrap().get(essid)
-                            {
-                                reply.body.push_param(network).unwrap();
-                            } else {
-                                reply.body.push_param(&KnownNetwork::default()).unwrap();
-                            }
+                            let key = Ssid::from_key(essid).key();
+                            let default = KnownNetwork::default();
+                            let known = service.known_networks.lock().unwrap();
+                            let network = known.get(&key).unwrap_or(&default);
+                            reply
+                                .body
+                                .push_param2(network, network.bssid.clone().unwrap_or_default())
+                                .unwrap();
+                            reply
+                                .body
+                                .push_param3(
+                                    network.band.clone().unwrap_or_default(),
+                                    network.band_only,
+                                    network.frequencies.as_slice(),
+                                )
+                                .unwrap();
+                            let metered = match network.metered {
+                                None => 0u32,
+                                Some(true) => 1,
+                                Some(false) => 2,
+                            };
+                            reply
+                                .body
+                                .push_param3(
+                                    metered,
+                                    network.data_cap.unwrap_or_default(),
+                                    network.mac_policy.clone().unwrap_or_default(),
+                                )
+                                .unwrap();
+                            reply
+                                .body
+                                .push_param2(network.mtu.unwrap_or_default(), network.dhcp_mtu)
+                                .unwrap();
                         } else {
                             return Some(standard_messages::invalid_args(&fallback, Some("s")));
                         }
                     }
                     "set_props" => {
                         let fallback = msg.dynheader.clone();
-                        if let Ok((essid, props)) = convert(msg) {
+                        if let Ok((essid, props)) = convert::<(String, KnownNetwork)>(msg) {
                             if let Ok(mut known) = service.known_networks.lock() {
-                                let upd_props = props.clone();
+                                let props = match known.get(&essid) {
+                                    Some(previous) => previous.with_props(props),
+                                    None => props,
+                                };
                                 if props.password.is_some() || props.auto {
-                                    *known.entry(essid.to_string()).or_insert(props) = upd_props;
+                                    known.insert(essid, props);
                                 } else {
                                     known.remove(&essid);
                                 }
-                                if config::write_networks(&known).is_err() {
-                                    return make_failed(&fallback, "Cannot write config");
+                                if let Err(e) = config::write_networks(&known) {
+                                    return make_error(&fallback, &Error::ConfigWrite(e));
                                 }
                             }
                         } else {
@@ -246,6 +639,236 @@ fn dbus_handler<SignalHandler: FnMut(SignalMsg)>(
                             ));
                         }
                     }
+                    "get_access_points" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(essid) = convert::<String>(msg) {
+                            let access_points = service
+                                .connection
+                                .get_access_points(&Ssid::from_key(&essid));
+                            reply
+                                .body
+                                .push_param(access_points.iter().collect::<Vec<_>>())
+                                .unwrap();
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("s")));
+                        }
+                    }
+                    "pin_bssid" => {
+                        let fallback = msg.dynheader.clone();
+                        match convert::<(String, String)>(msg) {
+                            Ok((essid, bssid))
+                                if bssid.is_empty() || AccessPoint::valid_bssid(&bssid) =>
+                            {
+                                let key = Ssid::from_key(&essid).key();
+                                let bssid = Some(bssid.to_ascii_lowercase())
+                                    .filter(|bssid| !bssid.is_empty());
+                                if let Err(e) =
+                                    service.update_network(key, |network| network.bssid = bssid)
+                                {
+                                    return make_error(&fallback, &e);
+                                }
+                            }
+                            _ => {
+                                return Some(standard_messages::invalid_args(
+                                    &fallback,
+                                    Some("ss"),
+                                ));
+                            }
+                        }
+                    }
+                    "set_bands" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((essid, bands)) = convert::<(String, BandPolicy)>(msg) {
+                            if let Err(e) =
+                                service.update_network(essid, |network| network.set_bands(bands))
+                            {
+                                return make_error(&fallback, &e);
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("ssbau")));
+                        }
+                    }
+                    "get_wired_auth" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(port) = convert::<String>(msg) {
+                            let port = match service.connection.wired_port(&port) {
+                                Ok(port) => port,
+                                Err(e) => return make_error(&fallback, &e),
+                            };
+                            let auth = service
+                                .connection
+                                .wired_credentials()
+                                .remove(&port)
+                                .unwrap_or_default()
+                                .redacted();
+                            reply.body.push_param2(port.as_str(), &auth).unwrap();
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("s")));
+                        }
+                    }
+                    "set_wired_auth" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((port, auth)) = convert::<(String, Option<WiredAuth>)>(msg) {
+                            let port = match service.connection.wired_port(&port) {
+                                Ok(port) => port,
+                                Err(e) => return make_error(&fallback, &e),
+                            };
+                            service.connection.set_wired_auth(&port, auth);
+                            let credentials = service.connection.wired_credentials();
+                            if let Err(e) = config::write_wired(&credentials) {
+                                return make_error(&fallback, &Error::ConfigWrite(e));
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(
+                                &fallback,
+                                Some("s(sssssssss)"),
+                            ));
+                        }
+                    }
+                    "get_wired_profiles" => {
+                        let mut names = service
+                            .connection
+                            .wired_profiles()
+                            .into_keys()
+                            .collect::<Vec<_>>();
+                        names.sort();
+                        let active = service.connection.active_wired_profile();
+                        reply
+                            .body
+                            .push_param2(names.as_slice(), active.unwrap_or_default())
+                            .unwrap();
+                    }
+                    "get_wired_profile" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(name) = convert::<String>(msg) {
+                            let profile = service
+                                .connection
+                                .wired_profiles()
+                                .remove(&name)
+                                .unwrap_or_default();
+                            reply.body.push_param2(&profile, &profile.link()).unwrap();
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("s")));
+                        }
+                    }
+                    "set_wired_profile" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((name, mut profile)) = convert::<(String, WiredProfile)>(msg) {
+                            if let Some(previous) = service.connection.wired_profiles().get(&name) {
+                                profile.set_link(previous.link());
+                            }
+                            service.connection.set_wired_profile(&name, Some(profile));
+                            let profiles = service.connection.wired_profiles();
+                            if let Err(e) = config::write_wired_profiles(&profiles) {
+                                return make_error(&fallback, &Error::ConfigWrite(e));
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(
+                                &fallback,
+                                Some("s(bssssssasu)"),
+                            ));
+                        }
+                    }
+                    "set_wired_link" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok((name, link)) = convert::<(String, WiredLink)>(msg) {
+                            let mut profile = service
+                                .connection
+                                .wired_profiles()
+                                .remove(&name)
+                                .unwrap_or_default();
+                            profile.set_link(link);
+                            service.connection.set_wired_profile(&name, Some(profile));
+                            let profiles = service.connection.wired_profiles();
+                            if let Err(e) = config::write_wired_profiles(&profiles) {
+                                return make_error(&fallback, &Error::ConfigWrite(e));
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(
+                                &fallback,
+                                Some("s(busuu)"),
+                            ));
+                        }
+                    }
+                    "delete_wired_profile" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(name) = convert::<String>(msg) {
+                            service.connection.set_wired_profile(&name, None);
+                            let profiles = service.connection.wired_profiles();
+                            if let Err(e) = config::write_wired_profiles(&profiles) {
+                                return make_error(&fallback, &Error::ConfigWrite(e));
+                            }
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("s")));
+                        }
+                    }
+                    "start_hotspot" => {
+                        let fallback = msg.dynheader.clone();
+                        if let Ok(setting) = convert::<HotspotSetting>(msg) {
+                            let mut connection = service.connection.clone();
+                            let responder = service.responder.clone();
+                            let known_networks = service.known_networks.clone();
+                            let connect_sender = service.connect_sender.clone();
+                            thread::spawn(move || {
+                                let given_up = connection.given_up_wifi();
+                                let reply = match connection.start_hotspot(setting) {
+                                    Ok(()) => fallback.make_response(),
+                                    Err(e) => {
+                                        log::warn!("cannot start hotspot: {}", e);
+                                        // refused starts leave wifi and the running hotspot alone
+                                        let refused = matches!(e, Error::NotAllowed(_));
+                                        if !refused && connection.given_up_wifi().is_none() {
+                                            rejoin(
+                                                &connection,
+                                                &known_networks,
+                                                &connect_sender,
+                                                given_up,
+                                            );
+                                        }
+                                        make_error(&fallback, &e).unwrap()
+                                    }
+                                };
+                                responder.respond(&reply).unwrap_or_default();
+                            });
+                            return None;
+                        } else {
+                            return Some(standard_messages::invalid_args(&fallback, Some("sss")));
+                        }
+                    }
+                    "stop_hotspot" => {
+                        let previous = service.connection.stop_hotspot();
+                        let op = rejoin(
+                            &service.connection,
+                            &service.known_networks,
+                            &service.connect_sender,
+                            previous,
+                        );
+                        reply.body.push_param(op).unwrap();
+                    }
+                    "get_hotspot" => {
+                        let hotspot = service.connection.hotspot();
+                        let (setting, iface) = hotspot.clone().unwrap_or_else(|| {
+                            (
+                                HotspotSetting {
+                                    essid: Ssid::default(),
+                                    password: None,
+                                    band: Band::Unknown,
+                                },
+                                String::new(),
+                            )
+                        });
+                        reply
+                            .body
+                            .push_param3(hotspot.is_some(), &setting, iface)
+                            .unwrap();
+                    }
+                    "get_hotspot_clients" => {
+                        let clients = service.connection.hotspot_clients();
+                        reply
+                            .body
+                            .push_param(clients.iter().collect::<Vec<_>>())
+                            .unwrap();
+                    }
                     "Introspect" => {
                         let xml = include_str!("../xml/snm.xml").to_owned();
                         reply.body.push_param(xml).unwrap();
@@ -257,18 +880,17 @@ fn dbus_handler<SignalHandler: FnMut(SignalMsg)>(
             }
             return Some(reply);
         }
-        MessageType::Signal => {
+        MessageType::Signal
             if msg
                 .dynheader
                 .interface
-                .eq(&Some("org.freedesktop.DBus".to_owned()))
-            {
-                let fallback = msg.dynheader.clone();
-                if let Ok(umsg) = msg.unmarshall_all() {
-                    service.proxy_tracker.event(umsg);
-                } else {
-                    return Some(standard_messages::invalid_args(&fallback, None));
-                }
+                .eq(&Some("org.freedesktop.DBus".to_owned())) =>
+        {
+            let fallback = msg.dynheader.clone();
+            if let Ok(umsg) = msg.unmarshall_all() {
+                service.proxy_tracker.event(umsg);
+            } else {
+                return Some(standard_messages::invalid_args(&fallback, None));
             }
         }
         _ => {}
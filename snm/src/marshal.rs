@@ -1,4 +1,7 @@
-use super::connection::{ConnectionInfo, KnownNetwork, NetworkInfo, NetworkList};
+use super::connection::{
+    AccessPoint, ConnectionInfo, ConnectionSetting, HotspotClient, HotspotSetting, KnownNetwork,
+    LinkStats, NetworkInfo, NetworkList, Ssid, Statistics, WiredAuth, WiredLink, WiredProfile,
+};
 
 use rustbus::{
     signature,
@@ -6,6 +9,22 @@ use rustbus::{
     Marshal, Signature,
 };
 
+impl Signature for &Ssid {
+    fn signature() -> signature::Type {
+        <&[u8]>::signature()
+    }
+
+    fn alignment() -> usize {
+        <&[u8]>::alignment()
+    }
+}
+
+impl Marshal for &Ssid {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        self.as_bytes().marshal(ctx)
+    }
+}
+
 impl Signature for &ConnectionInfo {
     fn signature() -> signature::Type {
         signature::Type::Container(signature::Container::Struct(
@@ -43,9 +62,9 @@ impl Marshal for &ConnectionInfo {
                 100.marshal(ctx)?;
                 ip.marshal(ctx)?;
             }
-            ConnectionInfo::Wifi(essid, quality, enc, ip) => {
+            ConnectionInfo::Wifi(essid, quality, enc, ip, _) => {
                 2.marshal(ctx)?;
-                essid.marshal(ctx)?;
+                essid.to_string().marshal(ctx)?;
                 enc.marshal(ctx)?;
                 quality.marshal(ctx)?;
                 ip.marshal(ctx)?;
@@ -59,7 +78,7 @@ impl Marshal for &ConnectionInfo {
             }
             ConnectionInfo::ConnectingWifi(essid) => {
                 4.marshal(ctx)?;
-                essid.marshal(ctx)?;
+                essid.to_string().marshal(ctx)?;
                 false.marshal(ctx)?;
                 0.marshal(ctx)?;
                 "".marshal(ctx)?;
@@ -103,6 +122,173 @@ impl Marshal for &KnownNetwork {
     }
 }
 
+impl Signature for &WiredAuth {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![String::signature(); 9]).unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+// unset fields are empty strings
+impl Marshal for &WiredAuth {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        self.eap.marshal(ctx)?;
+        self.identity.marshal(ctx)?;
+        for field in [
+            &self.anonymous_identity,
+            &self.password,
+            &self.phase2,
+            &self.ca_cert,
+            &self.client_cert,
+            &self.private_key,
+            &self.private_key_password,
+        ]
+        .iter()
+        {
+            field.as_deref().unwrap_or_default().marshal(ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl Signature for &WiredProfile {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![
+                bool::signature(),
+                String::signature(),
+                String::signature(),
+                String::signature(),
+                String::signature(),
+                String::signature(),
+                String::signature(),
+                <&[String]>::signature(),
+                u32::signature(),
+            ])
+            .unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+// unset fields are empty strings, an unset mtu is 0
+impl Marshal for &WiredProfile {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        self.auto.marshal(ctx)?;
+        for field in [&self.interface, &self.mac, &self.gateway_mac].iter() {
+            field.as_deref().unwrap_or_default().marshal(ctx)?;
+        }
+        self.method.marshal(ctx)?;
+        self.address.as_deref().unwrap_or_default().marshal(ctx)?;
+        self.gateway.as_deref().unwrap_or_default().marshal(ctx)?;
+        self.dns.as_slice().marshal(ctx)?;
+        self.mtu.unwrap_or_default().marshal(ctx)?;
+        Ok(())
+    }
+}
+
+impl Signature for &WiredLink {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![
+                bool::signature(),
+                u32::signature(),
+                String::signature(),
+                u32::signature(),
+                u32::signature(),
+            ])
+            .unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+// 0 leaves the port as it is, switches are 1 for on and 2 for off
+impl Marshal for &WiredLink {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        let switch = |value: Option<bool>| match value {
+            None => 0u32,
+            Some(true) => 1,
+            Some(false) => 2,
+        };
+        ctx.align_to(Self::alignment());
+        self.dhcp_mtu.marshal(ctx)?;
+        self.speed.unwrap_or_default().marshal(ctx)?;
+        self.duplex.as_deref().unwrap_or_default().marshal(ctx)?;
+        switch(self.autoneg).marshal(ctx)?;
+        switch(self.wake_on_lan).marshal(ctx)?;
+        Ok(())
+    }
+}
+
+impl Signature for &HotspotSetting {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![
+                String::signature(),
+                String::signature(),
+                bool::signature(),
+            ])
+            .unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+// the password stays with the daemon, only whether there is one goes out
+impl Marshal for &HotspotSetting {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        self.essid.key().marshal(ctx)?;
+        self.band.name().unwrap_or_default().marshal(ctx)?;
+        self.password.is_some().marshal(ctx)?;
+        Ok(())
+    }
+}
+
+impl Signature for &HotspotClient {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![
+                String::signature(),
+                String::signature(),
+                String::signature(),
+            ])
+            .unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+impl Marshal for &HotspotClient {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        self.mac.marshal(ctx)?;
+        self.ip.marshal(ctx)?;
+        self.hostname.marshal(ctx)?;
+        Ok(())
+    }
+}
+
 impl Signature for &NetworkInfo {
     fn signature() -> signature::Type {
         signature::Type::Container(signature::Container::Struct(
@@ -133,7 +319,7 @@ impl Marshal for &NetworkInfo {
             }
             NetworkInfo::Wifi(essid, quality, enc) => {
                 2.marshal(ctx)?;
-                essid.marshal(ctx)?;
+                essid.to_string().marshal(ctx)?;
                 enc.marshal(ctx)?;
                 quality.marshal(ctx)?;
             }
@@ -178,3 +364,147 @@ impl Marshal for &NetworkList {
         Ok(())
     }
 }
+
+impl Signature for &AccessPoint {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![
+                String::signature(),
+                <&Ssid>::signature(),
+                u32::signature(),
+                u32::signature(),
+                u32::signature(),
+                i32::signature(),
+                u32::signature(),
+                u32::signature(),
+                String::signature(),
+            ])
+            .unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+impl Marshal for &AccessPoint {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        self.bssid.marshal(ctx)?;
+        (&self.essid).marshal(ctx)?;
+        self.frequency.marshal(ctx)?;
+        (self.band() as u32).marshal(ctx)?;
+        self.channel().marshal(ctx)?;
+        self.signal.marshal(ctx)?;
+        self.last_seen.marshal(ctx)?;
+        (self.security as u32).marshal(ctx)?;
+        self.capabilities.marshal(ctx)?;
+        Ok(())
+    }
+}
+
+impl Signature for &LinkStats {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![
+                i32::signature(),
+                u32::signature(),
+                u32::signature(),
+                u32::signature(),
+                u32::signature(),
+            ])
+            .unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+impl Marshal for &LinkStats {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        self.signal.marshal(ctx)?;
+        self.quality().marshal(ctx)?;
+        self.tx_bitrate.marshal(ctx)?;
+        self.rx_bitrate.marshal(ctx)?;
+        self.connected_time.marshal(ctx)?;
+        Ok(())
+    }
+}
+
+impl Signature for &Statistics {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![u64::signature(); 10]).unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+impl Marshal for &Statistics {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        for counter in [
+            self.rx_bytes,
+            self.tx_bytes,
+            self.rx_packets,
+            self.tx_packets,
+            self.rx_errors,
+            self.tx_errors,
+            self.rx_dropped,
+            self.tx_dropped,
+            self.rx_rate,
+            self.tx_rate,
+        ] {
+            counter.marshal(ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl Signature for &ConnectionSetting {
+    fn signature() -> signature::Type {
+        signature::Type::Container(signature::Container::Struct(
+            signature::StructTypes::new(vec![
+                u32::signature(),
+                String::signature(),
+                bool::signature(),
+            ])
+            .unwrap(),
+        ))
+    }
+
+    fn alignment() -> usize {
+        8
+    }
+}
+
+impl Marshal for &ConnectionSetting {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), Error> {
+        ctx.align_to(Self::alignment());
+        match self {
+            ConnectionSetting::Ethernet => {
+                1.marshal(ctx)?;
+                "".marshal(ctx)?;
+                false.marshal(ctx)?;
+            }
+            ConnectionSetting::Wifi { essid, .. } => {
+                2.marshal(ctx)?;
+                essid.key().marshal(ctx)?;
+                true.marshal(ctx)?;
+            }
+            ConnectionSetting::OpenWifi { essid, .. } => {
+                2.marshal(ctx)?;
+                essid.key().marshal(ctx)?;
+                false.marshal(ctx)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use super::run;
+use nix::libc;
+use nix::sched::{unshare, CloneFlags};
+use nix::sys::socket::{setsockopt, sockopt};
+use nix::unistd::Pid;
+use smoltcp::wire::{
+    DhcpMessageType, DhcpPacket, DhcpRepr, Ipv4Address, DHCP_CLIENT_PORT, DHCP_SERVER_PORT,
+};
+use std::ffi::OsString;
+use std::io;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::os::unix::io::AsRawFd;
+use std::sync::mpsc;
+use std::thread;
+
+const LEASE_SECS: u32 = 3600;
+const NETMASK: Ipv4Address = Ipv4Address([255, 255, 255, 0]);
+
+// answers every DISCOVER and REQUEST on its end of the veth pair with the same lease
+pub struct Dhcpd {
+    socket: UdpSocket,
+    server: Ipv4Address,
+    offer: Ipv4Address,
+}
+
+impl Dhcpd {
+    // the server gets a network namespace of its own, so the gateway is not a local address
+    // for the client; the client end of the pair is moved to the namespace of client_ns
+    pub fn spawn(
+        iface: &'static str,
+        peer: &'static str,
+        client_ns: Pid,
+        server: Ipv4Addr,
+        offer: Ipv4Addr,
+    ) -> io::Result<()> {
+        let (ready, started) = mpsc::channel();
+        thread::spawn(
+            move || match Self::start(iface, peer, client_ns, server, offer) {
+                Ok(dhcpd) => {
+                    ready.send(Ok(())).unwrap_or_default();
+                    dhcpd.serve();
+                }
+                Err(e) => ready.send(Err(e)).unwrap_or_default(),
+            },
+        );
+        started.recv().map_err(io::Error::other)?
+    }
+
+    fn start(
+        iface: &str,
+        peer: &str,
+        client_ns: Pid,
+        server: Ipv4Addr,
+        offer: Ipv4Addr,
+    ) -> io::Result<Self> {
+        // namespaces are per thread, commands spawned from here inherit this one
+        unshare(CloneFlags::CLONE_NEWNET).map_err(io::Error::other)?;
+        let client_ns = client_ns.to_string();
+        run("ip", &["link", "set", "lo", "up"]);
+        run(
+            "ip",
+            &[
+                "link", "add", peer, "type", "veth", "peer", "name", iface, "netns", &client_ns,
+            ],
+        );
+        run(
+            "ip",
+            &["addr", "add", &format!("{}/24", server), "dev", peer],
+        );
+        run("ip", &["link", "set", peer, "up"]);
+
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DHCP_SERVER_PORT))?;
+        setsockopt(
+            socket.as_raw_fd(),
+            sockopt::BindToDevice,
+            &OsString::from(peer),
+        )
+        .map_err(io::Error::other)?;
+        socket.set_broadcast(true)?;
+        // veth leaves checksums to offloading, which the raw socket client never sees done
+        let disabled: libc::c_int = 1;
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_NO_CHECK,
+                &disabled as *const _ as *const libc::c_void,
+                std::mem::size_of_val(&disabled) as libc::socklen_t,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Dhcpd {
+            socket,
+            server: Ipv4Address::from(server),
+            offer: Ipv4Address::from(offer),
+        })
+    }
+
+    fn serve(&self) {
+        let mut buffer = [0; 1500];
+        while let Ok((len, _)) = self.socket.recv_from(&mut buffer) {
+            if let Some(reply) = self.reply(&buffer[..len]) {
+                let destination = (Ipv4Addr::BROADCAST, DHCP_CLIENT_PORT);
+                if let Err(e) = self.socket.send_to(&reply, destination) {
+                    eprintln!("dhcpd: cannot send reply: {}", e);
+                }
+            }
+        }
+    }
+
+    fn reply(&self, request: &[u8]) -> Option<Vec<u8>> {
+        let packet = DhcpPacket::new_checked(request).ok()?;
+        let request = DhcpRepr::parse(&packet).ok()?;
+        let message_type = match request.message_type {
+            DhcpMessageType::Discover => DhcpMessageType::Offer,
+            DhcpMessageType::Request => DhcpMessageType::Ack,
+            _ => return None,
+        };
+        let reply = DhcpRepr {
+            message_type,
+            transaction_id: request.transaction_id,
+            client_hardware_address: request.client_hardware_address,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: self.offer,
+            server_ip: self.server,
+            router: Some(self.server),
+            subnet_mask: Some(NETMASK),
+            relay_agent_ip: Ipv4Address::UNSPECIFIED,
+            broadcast: true,
+            requested_ip: None,
+            client_identifier: None,
+            server_identifier: Some(self.server),
+            parameter_request_list: None,
+            dns_servers: Some([Some(self.server), None, None]),
+            max_size: None,
+            lease_duration: Some(LEASE_SECS),
+        };
+        let mut buffer = vec![0; reply.buffer_len()];
+        reply
+            .emit(&mut DhcpPacket::new_unchecked(&mut buffer))
+            .ok()?;
+        Some(buffer)
+    }
+}
@@ -0,0 +1,269 @@
+// Runs the daemon inside an unprivileged user+net+mount namespace against a veth pair with a
+// DHCP stand-in on the peer side and a private dbus-daemon. The test re-executes itself through
+// unshare and is skipped where unprivileged namespaces are not available.
+mod dhcpd;
+
+use nix::unistd;
+use rustbus::connection::Timeout;
+use snm::connection::ConnectionInfo;
+use snm::convert::convert;
+use snm::dbus::{Bus, Proxy};
+
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::{env, thread, time};
+
+const INNER: &str = "SNM_NETNS_INNER";
+const IFACE: &str = "eth0";
+const PEER: &str = "peer0";
+const SERVER: Ipv4Addr = Ipv4Addr::new(10, 77, 0, 1);
+const CLIENT: Ipv4Addr = Ipv4Addr::new(10, 77, 0, 2);
+const TIMEOUT_SECS: u64 = 30;
+
+fn succeeds(cmd: &mut Command) -> bool {
+    cmd.stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn run(cmd: &str, args: &[&str]) -> String {
+    let output = Command::new(cmd)
+        .args(args)
+        .output()
+        .unwrap_or_else(|e| panic!("cannot run {}: {}", cmd, e));
+    assert!(
+        output.status.success(),
+        "{} {:?} failed: {}",
+        cmd,
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn namespaces_available() -> bool {
+    succeeds(Command::new("ip").arg("-V"))
+        && succeeds(Command::new("dbus-daemon").arg("--version"))
+        && succeeds(Command::new("unshare").args([
+            "--user",
+            "--map-root-user",
+            "--net",
+            "--mount",
+            "mount",
+            "-t",
+            "sysfs",
+            "sysfs",
+            "/sys",
+        ]))
+}
+
+fn in_namespace(test: &str) -> bool {
+    if env::var_os(INNER).is_some() {
+        return true;
+    }
+    if !namespaces_available() {
+        eprintln!(
+            "skipping {}: unprivileged namespaces are not available",
+            test
+        );
+        return false;
+    }
+    let status = Command::new("unshare")
+        .args(["--user", "--map-root-user", "--net", "--mount"])
+        .arg(env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture", "--test-threads=1"])
+        .env(INNER, "1")
+        .status()
+        .expect("cannot run unshare");
+    assert!(status.success(), "{} failed inside the namespace", test);
+    false
+}
+
+struct Harness {
+    dir: PathBuf,
+    children: Vec<Child>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let dir = env::temp_dir().join(format!("snm-netns-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let harness = Harness {
+            dir,
+            children: vec![],
+        };
+        // interfaces are detected through sysfs, which has to belong to the new namespace
+        run("mount", &["-t", "sysfs", "sysfs", "/sys"]);
+        // keep the daemon away from the host resolver configuration
+        let resolv = harness.path("resolv.conf");
+        fs::write(&resolv, "").unwrap();
+        run(
+            "mount",
+            &["--bind", &resolv.to_string_lossy(), "/etc/resolv.conf"],
+        );
+        harness
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    fn bus_address(&self) -> String {
+        format!("unix:path={}", self.path("bus").display())
+    }
+
+    fn veth(&self) {
+        run("ip", &["link", "set", "lo", "up"]);
+        dhcpd::Dhcpd::spawn(IFACE, PEER, unistd::gettid(), SERVER, CLIENT)
+            .expect("cannot start dhcp server");
+    }
+
+    fn dbus(&mut self) {
+        let config = self.path("bus.conf");
+        fs::write(
+            &config,
+            format!(
+                r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>system</type>
+  <listen>{}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow user="*"/>
+    <allow own="*"/>
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+  </policy>
+</busconfig>
+"#,
+                self.bus_address()
+            ),
+        )
+        .unwrap();
+        let child = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--nopidfile"])
+            .spawn()
+            .expect("cannot start dbus-daemon");
+        self.children.push(child);
+        wait_for(|| self.path("bus").exists(), "dbus-daemon socket");
+        env::set_var("DBUS_SYSTEM_BUS_ADDRESS", self.bus_address());
+    }
+
+    fn daemon(&mut self) {
+        fs::write(self.path("settings"), "[connectivity]\nenabled = false\n").unwrap();
+        let child = Command::new(env!("CARGO_BIN_EXE_snm"))
+            .arg("-v")
+            .env("SNM_CONFIG_DIR", &self.dir)
+            .env("DBUS_SYSTEM_BUS_ADDRESS", self.bus_address())
+            .env_remove("JOURNAL_STREAM")
+            .spawn()
+            .expect("cannot start snm");
+        self.children.push(child);
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        for child in self.children.iter_mut().rev() {
+            child.kill().unwrap_or_default();
+            child.wait().map(|_| ()).unwrap_or_default();
+        }
+        fs::remove_dir_all(&self.dir).unwrap_or_default();
+    }
+}
+
+fn wait_for(mut condition: impl FnMut() -> bool, what: &str) {
+    let deadline = time::Instant::now() + time::Duration::from_secs(TIMEOUT_SECS);
+    while !condition() {
+        assert!(
+            time::Instant::now() < deadline,
+            "timed out waiting for {}",
+            what
+        );
+        thread::sleep(time::Duration::from_millis(50));
+    }
+}
+
+fn wait_state(proxy: &mut Proxy, what: &str, accept: impl Fn(&ConnectionInfo) -> bool) {
+    let deadline = time::Instant::now() + time::Duration::from_secs(TIMEOUT_SECS);
+    loop {
+        let left = deadline
+            .checked_duration_since(time::Instant::now())
+            .unwrap_or_else(|| panic!("timed out waiting for {}", what));
+        let msg = proxy
+            .next_signal(Timeout::Duration(left))
+            .unwrap_or_else(|e| panic!("no {} signal: {}", what, e));
+        if msg.dynheader.member.as_deref() != Some("state_changed") {
+            continue;
+        }
+        if let Ok(info) = convert::<ConnectionInfo>(msg) {
+            if accept(&info) {
+                return;
+            }
+        }
+    }
+}
+
+fn get_state(proxy: &mut Proxy) -> ConnectionInfo {
+    let call = proxy.method("get_state");
+    let reply = proxy
+        .call(call, Timeout::Duration(time::Duration::from_secs(5)))
+        .unwrap_or_else(|e| panic!("get_state failed: {}", e));
+    convert(reply).expect("ill-formed get_state reply")
+}
+
+fn has_address(iface: &str, address: Ipv4Addr) -> bool {
+    run("ip", &["-4", "-o", "addr", "show", "dev", iface]).contains(&format!("inet {}", address))
+}
+
+#[test]
+fn ethernet_autoconnect() {
+    if !in_namespace("ethernet_autoconnect") {
+        return;
+    }
+    let mut harness = Harness::new();
+    harness.veth();
+    harness.dbus();
+    let mut proxy = Proxy::new(Bus::System, "com.github.okeri.snm", "/").expect("no bus");
+    proxy.subscribe().unwrap_or_else(|e| panic!("{}", e));
+    harness.daemon();
+
+    let client = CLIENT.to_string();
+    wait_state(
+        &mut proxy,
+        "ethernet connection",
+        |info| matches!(info, ConnectionInfo::Ethernet(ref ip) if *ip == client),
+    );
+    assert!(matches!(
+        get_state(&mut proxy),
+        ConnectionInfo::Ethernet(ref ip) if *ip == client
+    ));
+    assert!(has_address(IFACE, CLIENT));
+    let routes = run("ip", &["-4", "route", "show", "dev", IFACE]);
+    assert!(
+        routes.contains(&format!("default via {}", SERVER)),
+        "{}",
+        routes
+    );
+    assert!(routes.contains("10.77.0.0/24"), "{}", routes);
+    let resolv = fs::read_to_string(Path::new("/etc/resolv.conf")).unwrap();
+    assert!(
+        resolv.contains(&format!("nameserver {}", SERVER)),
+        "{}",
+        resolv
+    );
+
+    let call = proxy.method("disconnect");
+    proxy
+        .call(call, Timeout::Duration(time::Duration::from_secs(5)))
+        .unwrap_or_else(|e| panic!("disconnect failed: {}", e));
+    wait_state(&mut proxy, "disconnect", |info| {
+        matches!(info, ConnectionInfo::NotConnected)
+    });
+    assert!(!has_address(IFACE, CLIENT));
+}